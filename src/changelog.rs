@@ -3,24 +3,409 @@
 //! This module provides functions to build changelog sections from commit information
 //! and write them to files.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 use std::fs;
+use std::io;
 use std::path::Path;
 
-use anyhow::Result;
-use chrono::NaiveDate;
-use regex::Regex;
+use anyhow::{anyhow, Result};
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use chrono::{DateTime, NaiveDate};
+use clap::ValueEnum;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
 use semver::Version;
+use serde::Serialize;
 
-use crate::classify::CommitCategory;
+use crate::classify::{
+    breaking_footer, find_trailer, parse_trailers, CommitCategory, REVERTS_SECTION,
+};
 use crate::git::{CommitInfo, RemoteInfo};
+use crate::messages::{CategoryLabel, Lang, Messages};
 
-/// Builds a markdown-formatted release section for a changelog.
+/// Options controlling how a release section is rendered.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Add a lead sentence under the header summarizing the change counts.
+    pub summary_line: bool,
+    /// Show the version as its git tag (`v1.2.3`) in the header instead of `Version 1.2.3`.
+    pub tag_prefix_in_header: bool,
+    /// Remove a single trailing period from bullet titles (ellipses are kept).
+    pub strip_trailing_period: bool,
+    /// strftime-style format of the release date, [`DEFAULT_DATE_FORMAT`] if unset.
+    /// Must have been checked with [`check_date_format`].
+    pub date_format: Option<String>,
+    /// Render the version header as plain text, without a link to the release tag.
+    pub no_release_link: bool,
+    /// Append a "Closed issues" index of every issue referenced in the release.
+    pub issues_index: bool,
+    /// Revisions to compare in the "full changes" link instead of the version tags,
+    /// e.g. the boundary commit SHAs when the release is not tagged yet.
+    pub compare_range: Option<(String, String)>,
+    /// Show at most this many bullets per section, followed by an "...and N more" line.
+    /// Breaking changes are always listed in full.
+    pub max_per_section: Option<usize>,
+    /// List the authors and co-authors of the release in a "Contributors" section.
+    pub contributors: bool,
+    /// Lead the contributors list with their number, e.g. "5 contributors".
+    pub contributors_count: bool,
+    /// Leave bot accounts (e.g. "dependabot[bot]") out of the contributors.
+    pub exclude_bots: bool,
+    /// Always list security changes (`Changelog: Security`) under a "Security fixes"
+    /// section, stating "None." when there are none.
+    pub group_security_separate: bool,
+    /// How commits are listed in Markdown sections.
+    pub list_format: ListFormat,
+    /// Only render commits of these categories, if set.
+    pub only: Option<Vec<CommitCategory>>,
+    /// Language of the headings and generated phrases.
+    pub lang: Lang,
+    /// Keep commits whose title is blank, and so sections without a visible entry, instead
+    /// of dropping them.
+    pub keep_empty_sections: bool,
+    /// Template of the Markdown version header line, e.g. "## [{version}] - {date}".
+    /// Supports `{version}`, `{tag}`, `{date}` and `{link}` (the release URL, or empty).
+    pub version_header_format: Option<String>,
+    /// Render the commits as a not yet released section under this label (e.g.
+    /// "Unreleased"), without version, date or release link.
+    pub unreleased: Option<String>,
+    /// Number of levels to shift the Markdown headings down by, e.g. 1 for `###` release
+    /// headers and `####` section headings.
+    pub base_level: usize,
+    /// Append the number of listed commits to the version header, e.g. "· 9 commits".
+    pub commit_count: bool,
+    /// Group the bullets of each Markdown section under date subheadings, newest first.
+    pub by_date: bool,
+    /// Custom names of the categories, used in section headings and the summary line
+    /// instead of the built-in ones.
+    pub category_labels: HashMap<CommitCategory, CategoryLabel>,
+    /// List each section on a single line of a paragraph instead of as bullets, e.g.
+    /// "Bug fixes: fix null pointer (#abc1234), fix typo (#def5678)."
+    pub compact: bool,
+    /// Numbers of the referenced issues confirmed closed in the tracker, marked with ✅.
+    pub closed_issue_ids: HashSet<String>,
+    /// Show the text of `BREAKING CHANGE:` footers under the breaking change bullets.
+    pub breaking_notes: bool,
+    /// How issue references are recognized in commit titles and linked.
+    pub issue_syntax: IssueSyntax,
+    /// Template of the release tag names, `v{version}` if unset. See [`tag_name`].
+    pub tag_name_format: Option<String>,
+}
+
+/// How the commits of a Markdown section are listed.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListFormat {
+    /// One bullet per commit
+    #[default]
+    Bullets,
+    /// A GitHub-flavoured table with SHA, category, summary and issue columns
+    Table,
+}
+
+impl RenderOptions {
+    /// The `from...to` range of the "full changes" link, if there is one.
+    fn compare_range(&self, last_version: &Version, new_version: &Version) -> Option<String> {
+        let last_tag = self.tag(last_version);
+        match &self.compare_range {
+            Some((from, to)) => Some(format!("{from}...{to}")),
+            None if *last_version == Version::new(0, 0, 0) => None,
+            None if self.unreleased.is_some() => Some(format!("{last_tag}...HEAD")),
+            None => Some(format!("{last_tag}...{}", self.tag(new_version))),
+        }
+    }
+
+    /// The tag name of a version, per `tag_name_format`.
+    fn tag(&self, version: &Version) -> String {
+        tag_name(self.tag_name_format.as_deref(), version)
+    }
+
+    /// The commit count appended to the version header, if enabled.
+    fn count_suffix(&self, sections: &[(String, &[FormattedCommit])]) -> String {
+        if !self.commit_count {
+            return String::new();
+        }
+        let total = sections.iter().map(|(_, commits)| commits.len()).sum();
+        let messages = self.messages();
+        format!(" · {}", messages.count(total, messages.commit))
+    }
+
+    /// The Markdown marker of a heading of the given level, after the base level shift.
+    fn hashes(&self, level: usize) -> String {
+        "#".repeat(level + self.base_level)
+    }
+
+    /// Splits off the bullets of a section beyond `max_per_section`.
+    ///
+    /// # Returns
+    ///
+    /// The commits to list and the number of hidden ones.
+    fn limit_section<'a>(
+        &self,
+        heading: &str,
+        commits: &'a [FormattedCommit],
+    ) -> (&'a [FormattedCommit], usize) {
+        match self.max_per_section {
+            Some(max) if heading != self.heading(CommitCategory::Major) && commits.len() > max => {
+                (&commits[..max], commits.len() - max)
+            }
+            _ => (commits, 0),
+        }
+    }
+
+    /// The section heading of a category, e.g. "Breaking changes".
+    fn heading(&self, cat: CommitCategory) -> String {
+        if let Some(label) = self.category_labels.get(&cat) {
+            return label.to_string();
+        }
+        let messages = self.messages();
+        match cat {
+            CommitCategory::Major => messages.breaking_changes,
+            CommitCategory::Minor => messages.new_features,
+            CommitCategory::Patch | CommitCategory::Ignore => messages.bug_fixes,
+        }
+        .to_string()
+    }
+
+    /// Drops the categories excluded by `only` from the commits to render.
+    fn visible<'a>(
+        &self,
+        grouped: &'a HashMap<CommitCategory, Vec<CommitInfo>>,
+    ) -> Cow<'a, HashMap<CommitCategory, Vec<CommitInfo>>> {
+        match &self.only {
+            Some(only) => Cow::Owned(
+                grouped
+                    .iter()
+                    .filter(|(cat, _)| only.contains(cat))
+                    .map(|(cat, list)| (*cat, list.clone()))
+                    .collect(),
+            ),
+            None => Cow::Borrowed(grouped),
+        }
+    }
+
+    /// The headings and phrases in the selected language.
+    fn messages(&self) -> &'static Messages {
+        self.lang.messages()
+    }
+
+    /// The link target of an issue reference: the `--issue-url-template` URL, or the page on
+    /// the forge of the remote, if any.
+    fn issue_link(&self, issue: &IssueRef, remote: Option<&RemoteInfo>) -> Option<String> {
+        match &self.issue_syntax.url_template {
+            Some(template) if issue.sigil == '#' => Some(template.replace("{id}", &issue.id)),
+            _ => remote.map(|r| issue.url(r)),
+        }
+    }
+
+    /// The mark appended to an issue reference, ` ✅` if the issue is known to be closed.
+    fn issue_mark(&self, issue: &IssueRef) -> &'static str {
+        if issue.sigil == '#' && self.closed_issue_ids.contains(&issue.id) {
+            " \u{2705}"
+        } else {
+            ""
+        }
+    }
+
+    /// Formats the line replacing the bullets beyond the per-section limit.
+    fn and_more(&self, hidden: usize) -> String {
+        self.messages().and_more.replace("{n}", &hidden.to_string())
+    }
+
+    /// The strftime-style format of the release date.
+    fn date_format(&self) -> &str {
+        self.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT)
+    }
+}
+
+/// Date format used in release headers unless overridden.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Checks a strftime-style date format for use in release headers.
+///
+/// # Arguments
+///
+/// * `format` - The format string, e.g. "%d %B %Y"
+///
+/// # Returns
+///
+/// The date components (year, month, day) the format doesn't include, so callers
+/// can warn about ambiguous dates.
+///
+/// # Errors
+///
+/// Returns an error if the format has invalid specifiers or produces an empty string.
+pub fn check_date_format(format: &str) -> Result<Vec<&'static str>> {
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(anyhow!("Invalid date format {format}"));
+    }
+
+    let sample = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap_or_default();
+    if sample.format(format).to_string().trim().is_empty() {
+        return Err(anyhow!("Date format {format} produces an empty date"));
+    }
+
+    let has = |pred: fn(&Item) -> bool| items.iter().any(pred);
+    let mut missing = Vec::new();
+    if !has(|item| {
+        matches!(
+            item,
+            Item::Numeric(
+                Numeric::Year
+                    | Numeric::YearDiv100
+                    | Numeric::YearMod100
+                    | Numeric::IsoYear
+                    | Numeric::IsoYearDiv100
+                    | Numeric::IsoYearMod100,
+                _
+            ) | Item::Fixed(Fixed::RFC2822 | Fixed::RFC3339)
+        )
+    }) {
+        missing.push("year");
+    }
+    if !has(|item| {
+        matches!(
+            item,
+            Item::Numeric(Numeric::Month | Numeric::Ordinal, _)
+                | Item::Fixed(
+                    Fixed::ShortMonthName | Fixed::LongMonthName | Fixed::RFC2822 | Fixed::RFC3339
+                )
+        )
+    }) {
+        missing.push("month");
+    }
+    if !has(|item| {
+        matches!(
+            item,
+            Item::Numeric(Numeric::Day | Numeric::Ordinal, _)
+                | Item::Fixed(Fixed::RFC2822 | Fixed::RFC3339)
+        )
+    }) {
+        missing.push("day");
+    }
+
+    Ok(missing)
+}
+
+/// A commit prepared for listing in a release.
+#[derive(Debug, Clone)]
+pub struct FormattedCommit {
+    /// The commit.
+    pub commit: CommitInfo,
+    /// The category the commit was classified in. Reverts, security fixes and custom
+    /// sections list commits of several categories.
+    pub category: CommitCategory,
+    /// The title, cleaned up and without the issue references.
+    pub title: String,
+    /// The issues and merge requests the commit references.
+    pub refs: Vec<IssueRef>,
+}
+
+impl FormattedCommit {
+    /// Extracts the issue references of a commit and cleans up its title.
+    ///
+    /// # Arguments
+    ///
+    /// * `commit` - The commit
+    /// * `category` - The category the commit was classified in
+    /// * `opts` - Rendering options
+    pub fn new(commit: CommitInfo, category: CommitCategory, opts: &RenderOptions) -> Self {
+        let (title, refs) = commit_refs(&commit, &opts.issue_syntax);
+        Self {
+            title: clean_title(title, opts),
+            refs,
+            commit,
+            category,
+        }
+    }
+}
+
+/// A release, ready to be rendered as Markdown, Confluence markup or JSON.
+///
+/// Built by [`build_release_section`], rendered by [`render_markdown`],
+/// [`render_confluence`] and [`render_json`].
+#[derive(Debug, Clone)]
+pub struct ReleaseSection {
+    /// The new version.
+    pub version: Version,
+    /// The release date.
+    pub date: NaiveDate,
+    /// The remote repository to link to, if any.
+    pub remote: Option<RemoteInfo>,
+    /// The commits listed as breaking changes.
+    pub breaking: Vec<FormattedCommit>,
+    /// The commits listed as new features.
+    pub features: Vec<FormattedCommit>,
+    /// The commits listed as bug fixes.
+    pub fixes: Vec<FormattedCommit>,
+    /// Reverts, security fixes and custom sections, with their headings, in order.
+    pub other: Vec<(String, Vec<FormattedCommit>)>,
+    /// The `from...to` range of the "full changes" link, if there is one.
+    pub compare_range: Option<String>,
+    /// The sentence summarizing the changes, if requested.
+    pub summary: Option<String>,
+    /// The `Known-issue:` trailers of the commits.
+    pub known_issues: Vec<String>,
+    /// The authors and co-authors of the commits, if requested.
+    pub contributors: Vec<String>,
+    /// The issues referenced by the commits, if an index is requested.
+    pub closed_issues: Vec<IssueRef>,
+}
+
+impl ReleaseSection {
+    /// The sections to list with their headings, in order: breaking changes, new features
+    /// and bug fixes, unless empty, then the other sections.
+    fn sections(&self, opts: &RenderOptions) -> Vec<(String, &[FormattedCommit])> {
+        [
+            (CommitCategory::Major, &self.breaking),
+            (CommitCategory::Minor, &self.features),
+            (CommitCategory::Patch, &self.fixes),
+        ]
+        .into_iter()
+        .filter(|(_, commits)| !commits.is_empty())
+        .map(|(cat, commits)| (opts.heading(cat), commits.as_slice()))
+        .chain(
+            self.other
+                .iter()
+                .map(|(heading, commits)| (heading.clone(), commits.as_slice())),
+        )
+        .collect()
+    }
+
+    /// The URL of the release page, unless the release link is disabled.
+    fn release_url(&self, opts: &RenderOptions) -> Option<String> {
+        self.remote
+            .as_ref()
+            .filter(|_| !opts.no_release_link)
+            .map(|r| r.release_url(&opts.tag(&self.version)))
+    }
+
+    /// The URL of the "full changes" link, if there is one.
+    fn compare_url(&self) -> Option<String> {
+        let range = self.compare_range.as_deref()?;
+        self.remote.as_ref().map(|r| r.compare_url(range))
+    }
+
+    /// The links that point at the tag of the new version, which does not exist on the
+    /// forge until the release is tagged and pushed: the release page and, unless it
+    /// compares commit ids, the "full changes" link.
+    pub fn new_tag_links(&self, opts: &RenderOptions) -> Vec<String> {
+        let compare_url = self.compare_url().filter(|_| opts.compare_range.is_none());
+        self.release_url(opts)
+            .into_iter()
+            .chain(compare_url)
+            .collect()
+    }
+}
+
+/// Prepares a release for rendering.
 ///
-/// Creates a version header with optional links to the remote repository,
-/// formats commits by category (breaking changes, new features, bug fixes),
-/// and includes links to commits and issues when remote information is available.
+/// Splits the commits into sections: breaking changes, new features and bug fixes,
+/// followed by reverts and custom sections for commits that carry a section override, in
+/// order of appearance. Also collects the summary, known issues, contributors and issue
+/// index the options ask for.
 ///
 /// # Arguments
 ///
@@ -29,297 +414,3296 @@ use crate::git::{CommitInfo, RemoteInfo};
 /// * `date` - The release date
 /// * `remote` - Optional remote repository information for generating links
 /// * `grouped` - Commits grouped by category (Major, Minor, Patch)
+/// * `opts` - Rendering options
 ///
 /// # Returns
 ///
-/// A markdown-formatted string containing the release section.
+/// The release, to render with [`render_markdown`], [`render_confluence`] or
+/// [`render_json`].
 pub fn build_release_section(
     new_version: &Version,
     last_version: &Version,
     date: NaiveDate,
     remote: Option<&RemoteInfo>,
     grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
-) -> String {
-    let date_str = date.format("%Y-%m-%d").to_string();
-    let mut out = String::new();
-
-    let version_str = new_version.to_string();
-    let last_str = last_version.to_string();
-
-    let header = if let Some(r) = remote {
-        format!(
-            "## [Version {version_str}]({}releases/tag/v{version_str}) ({date_str})\n",
-            r.base_url
-        )
-    } else {
-        format!("## Version {version_str} ({date_str})\n")
+    opts: &RenderOptions,
+) -> ReleaseSection {
+    let grouped = &*opts.visible(grouped);
+    let mut release = ReleaseSection {
+        version: new_version.clone(),
+        date,
+        remote: remote.cloned(),
+        breaking: Vec::new(),
+        features: Vec::new(),
+        fixes: Vec::new(),
+        other: Vec::new(),
+        compare_range: opts.compare_range(last_version, new_version),
+        summary: summary_line(grouped, opts).filter(|_| opts.summary_line),
+        known_issues: known_issues(grouped),
+        contributors: if opts.contributors {
+            contributors(grouped, opts.exclude_bots)
+        } else {
+            Vec::new()
+        },
+        closed_issues: if opts.issues_index {
+            closed_issues(grouped, &opts.issue_syntax)
+        } else {
+            Vec::new()
+        },
     };
-    out.push_str(&header);
 
-    if let Some(list) = grouped.get(&CommitCategory::Major) {
-        out.push_str(&format_section("Breaking changes", list, remote));
-    }
-    if let Some(list) = grouped.get(&CommitCategory::Minor) {
-        out.push_str(&format_section("New features", list, remote));
+    let mut custom: Vec<(String, Vec<FormattedCommit>)> = Vec::new();
+    for (cat, list) in [
+        (CommitCategory::Major, &mut release.breaking),
+        (CommitCategory::Minor, &mut release.features),
+        (CommitCategory::Patch, &mut release.fixes),
+    ] {
+        for commit in grouped.get(&cat).into_iter().flatten() {
+            let commit = FormattedCommit::new(commit.clone(), cat, opts);
+            if !opts.keep_empty_sections && commit.title.trim().is_empty() {
+                continue;
+            }
+            match &commit.commit.section {
+                Some(name) => match custom.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, commits)) => commits.push(commit),
+                    None => custom.push((name.clone(), vec![commit])),
+                },
+                None => list.push(commit),
+            }
+        }
     }
-    if let Some(list) = grouped.get(&CommitCategory::Patch) {
-        out.push_str(&format_section("Bug fixes", list, remote));
+
+    // Reverts come right after the category sections, whatever other sections exist.
+    if let Some(index) = custom.iter().position(|(name, _)| name == REVERTS_SECTION) {
+        let (_, reverts) = custom.remove(index);
+        custom.insert(0, (opts.messages().reverts.to_string(), reverts));
     }
 
-    if let Some(r) = remote {
-        if last_str != "0.0.0" {
-            out.push_str(&format!(
-                "\n[...full changes]({}compare/v{last_str}...v{version_str})\n\n",
-                r.base_url
-            ));
-        } else {
-            out.push('\n');
-        }
-    } else {
-        out.push('\n');
+    // The security section is kept even when empty, to state that there is nothing in it.
+    if opts.group_security_separate {
+        let security = match custom.iter().position(|(name, _)| name == "Security") {
+            Some(index) => custom.remove(index).1,
+            None => Vec::new(),
+        };
+        custom.push((opts.messages().security_fixes.to_string(), security));
     }
 
-    out
+    release.other = custom;
+    release
 }
 
-/// Formats a section of commits (e.g., "Breaking changes", "New features", "Bug fixes").
+/// Renders a release as a Markdown changelog section.
 ///
-/// Extracts issue references from commit messages and formats them as markdown list items
-/// with links to commits and issues when remote information is available.
+/// Creates a version header with optional links to the remote repository, lists the
+/// commits of each section (breaking changes, new features, bug fixes, ...), and includes
+/// links to commits and issues when remote information is available.
 ///
 /// # Arguments
 ///
-/// * `heading` - The section heading (e.g., "Breaking changes")
-/// * `commits` - The list of commits to format
-/// * `remote` - Optional remote repository information for generating links
+/// * `release` - The release, from [`build_release_section`]
+/// * `opts` - Rendering options, as given to [`build_release_section`]
 ///
 /// # Returns
 ///
-/// A markdown-formatted string containing the section.
-fn format_section(heading: &str, commits: &[CommitInfo], remote: Option<&RemoteInfo>) -> String {
+/// A markdown-formatted string containing the release section.
+pub fn render_markdown(release: &ReleaseSection, opts: &RenderOptions) -> String {
+    let date_str = release.date.format(opts.date_format()).to_string();
+    let remote = release.remote.as_ref();
     let mut out = String::new();
-    let _ = writeln!(out, "\n### {heading}");
 
-    static RE_SQUASHED: once_cell::sync::Lazy<Regex> =
-        once_cell::sync::Lazy::new(|| Regex::new(r"\s+\(#(\d+)\)").unwrap());
-    static RE_TRAILING: once_cell::sync::Lazy<Regex> =
-        once_cell::sync::Lazy::new(|| Regex::new(r"\s+#(\d+)$").unwrap());
-
-    for commit in commits {
-        let mut title = commit.summary.clone();
-        let mut issue_id: Option<String> = None;
+    let sections = release.sections(opts);
+    let count = opts.count_suffix(&sections);
 
-        if let Some(cap) = RE_SQUASHED.captures(&title) {
-            if let Some(m) = cap.get(1) {
-                issue_id = Some(m.as_str().to_string());
-            }
-            title = RE_SQUASHED.replace(&title, "").into_owned();
+    let title = version_title(&release.version, opts);
+    let release_url = release.release_url(opts);
+    let hashes = opts.hashes(2);
+    let header = match (&opts.version_header_format, &release_url) {
+        _ if opts.unreleased.is_some() => {
+            let label = opts.unreleased.as_deref().unwrap_or_default();
+            format!("{}{count}\n", unreleased_heading(label, opts.base_level))
+        }
+        (Some(template), _) => {
+            let header = template
+                .replace("{version}", &release.version.to_string())
+                .replace("{tag}", &opts.tag(&release.version))
+                .replace("{date}", &date_str)
+                .replace("{link}", release_url.as_deref().unwrap_or_default());
+            format!("{}{count}\n", header.trim_end())
         }
+        (None, Some(url)) => format!("{hashes} [{title}]({url}) ({date_str}){count}\n"),
+        (None, None) => format!("{hashes} {title} ({date_str}){count}\n"),
+    };
+    out.push_str(&header);
 
-        if issue_id.is_none() {
-            if let Some(cap) = RE_TRAILING.captures(&title) {
-                if let Some(m) = cap.get(1) {
-                    issue_id = Some(m.as_str().to_string());
+    if let Some(line) = &release.summary {
+        let _ = writeln!(out, "\n{line}");
+    }
+
+    let compare_url = release.compare_url();
+
+    if opts.compact {
+        let _ = writeln!(out, "\n{}", compact_paragraph(&sections));
+    } else {
+        // Sections are independent, so render them concurrently; `collect` keeps the order.
+        let rendered: Vec<String> = sections
+            .par_iter()
+            .map(|(heading, list)| {
+                let (shown, hidden) = opts.limit_section(heading, list);
+                let mut section = match opts.list_format {
+                    ListFormat::Bullets => format_section(heading, shown, remote, opts),
+                    ListFormat::Table => format_section_table(heading, shown, remote, opts),
+                };
+                if hidden > 0 {
+                    // Insert before the blank line that closes the section.
+                    section.pop();
+                    let more = opts.and_more(hidden);
+                    let _ = match &compare_url {
+                        Some(url) => writeln!(section, "* [{more}]({url})\n"),
+                        None => writeln!(section, "* {more}\n"),
+                    };
                 }
-                title = RE_TRAILING.replace(&title, "").into_owned();
-            }
+                section
+            })
+            .collect();
+        for section in rendered {
+            out.push_str(&section);
         }
+    }
 
-        let issue_ref = if let (Some(r), Some(id)) = (remote, issue_id.as_ref()) {
-            format!(" ([#{id}]({}issues/{id}))", r.base_url)
-        } else if let Some(id) = issue_id {
-            format!(" (#{id})")
-        } else {
-            String::new()
-        };
+    if !release.known_issues.is_empty() {
+        let _ = writeln!(out, "\n{} {}", opts.hashes(3), opts.messages().known_issues);
+        for issue in &release.known_issues {
+            let _ = writeln!(out, "* {issue}");
+        }
+        out.push('\n');
+    }
 
-        let commit_ref = if let Some(r) = remote {
-            format!(
-                " [`{}`]({}commit/{})",
-                commit.short_id, r.base_url, commit.short_id
-            )
-        } else {
-            format!(" `{}`", commit.short_id)
-        };
+    if !release.contributors.is_empty() {
+        let line = contributors_line(
+            &release.contributors,
+            opts.contributors_count,
+            opts.messages(),
+        );
+        let _ = writeln!(
+            out,
+            "\n{} {}\n{line}\n",
+            opts.hashes(3),
+            opts.messages().contributors
+        );
+    }
 
-        out.push_str("* ");
-        out.push_str(&title);
-        out.push(':');
-        out.push_str(&commit_ref);
-        out.push_str(&issue_ref);
-        out.push('\n');
+    if !release.closed_issues.is_empty() {
+        let links: Vec<String> = release
+            .closed_issues
+            .iter()
+            .map(|issue| match opts.issue_link(issue, remote) {
+                Some(url) => format!("[{issue}]({url})"),
+                None => issue.to_string(),
+            })
+            .collect();
+        let heading = opts.messages().closed_issues;
+        let _ = writeln!(
+            out,
+            "\n{} {heading}\n{}\n",
+            opts.hashes(3),
+            links.join(", ")
+        );
+    }
+
+    match &compare_url {
+        Some(url) => {
+            let _ = write!(out, "\n[{}]({url})\n\n", opts.messages().full_changes);
+        }
+        _ => out.push('\n'),
     }
 
-    out.push('\n');
     out
 }
 
-/// Writes a new changelog section to a file.
+/// Renders a release in Confluence wiki markup.
 ///
-/// If the file exists and contains content, the new section is prepended.
-/// If the file doesn't exist or is empty, a new changelog is created with a footer.
+/// Produces the same content as [`render_markdown`], using Confluence syntax:
+/// `h2.`/`h3.` headings, `*` bullets and `[text|url]` links.
 ///
 /// # Arguments
 ///
-/// * `path` - The path to the changelog file
-/// * `new_section` - The new release section to add
+/// * `release` - The release, from [`build_release_section`]
+/// * `opts` - Rendering options, as given to [`build_release_section`]
 ///
-/// # Errors
+/// # Returns
 ///
-/// Returns an error if the file cannot be read or written.
-pub fn write_changelog(path: &str, new_section: &str) -> Result<()> {
-    let p = Path::new(path);
+/// A Confluence-formatted string containing the release section.
+pub fn render_confluence(release: &ReleaseSection, opts: &RenderOptions) -> String {
+    let date_str = release.date.format(opts.date_format()).to_string();
+    let remote = release.remote.as_ref();
+    let mut out = String::new();
+
+    let sections = release.sections(opts);
+    let count = opts.count_suffix(&sections);
 
-    let existing = if p.exists() {
-        fs::read_to_string(p)?
+    let title = version_title(&release.version, opts);
+    let header = if let Some(label) = &opts.unreleased {
+        format!("h2. {label}{count}\n")
+    } else if let Some(url) = release.release_url(opts) {
+        format!("h2. [{title}|{url}] ({date_str}){count}\n")
     } else {
-        String::new()
+        format!("h2. {title} ({date_str}){count}\n")
     };
+    out.push_str(&header);
+
+    if let Some(line) = &release.summary {
+        let _ = writeln!(out, "\n{line}");
+    }
+
+    let compare_url = release.compare_url();
 
-    let content = if existing.trim().is_empty() {
-        format!("{new_section}\n--- Generated by changelogger\n")
+    if opts.compact {
+        let _ = writeln!(out, "\n{}", compact_paragraph(&sections));
     } else {
-        format!("{new_section}\n\n{existing}")
-    };
+        for (heading, commits) in &sections {
+            let _ = writeln!(out, "\nh3. {heading}");
+            if commits.is_empty() && heading == opts.messages().security_fixes {
+                let _ = writeln!(out, "{}", opts.messages().none);
+            }
+            let (shown, hidden) = opts.limit_section(heading, commits);
+            for commit in shown {
+                let FormattedCommit {
+                    commit,
+                    title,
+                    refs,
+                    ..
+                } = commit;
 
-    fs::write(p, content)?;
-    Ok(())
-}
+                let commit_ref = if let Some(r) = remote {
+                    format!(
+                        " [{}|{}]",
+                        commit.link_text(),
+                        r.commit_url(&commit.short_id)
+                    )
+                } else {
+                    format!(" {{{{{}}}}}", commit.short_id)
+                };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use git2::Oid;
-    use std::collections::HashMap;
-    use tempfile::TempDir;
+                let issue_ref: String = refs
+                    .iter()
+                    .map(|issue| {
+                        let mark = opts.issue_mark(issue);
+                        match opts.issue_link(issue, remote) {
+                            Some(url) => format!(" ([{issue}|{url}]{mark})"),
+                            None => format!(" ({issue}{mark})"),
+                        }
+                    })
+                    .collect();
 
-    fn create_commit_info(short_id: &str, summary: &str) -> CommitInfo {
-        CommitInfo {
-            oid: Oid::zero(),
-            short_id: short_id.to_string(),
-            summary: summary.to_string(),
-            body: String::new(),
+                let badge = if commit.has_signature {
+                    " {{has signature}}"
+                } else {
+                    ""
+                };
+                let _ = writeln!(out, "* {title}:{commit_ref}{issue_ref}{badge}");
+                if let Some(note) = breaking_note(commit, heading, opts) {
+                    let _ = writeln!(out, "** {note}");
+                }
+                if let Some(link) = migration_link(commit, heading, opts) {
+                    let _ = writeln!(out, "** [{}|{link}]", opts.messages().migration_guide);
+                }
+            }
+            if hidden > 0 {
+                let _ = match &compare_url {
+                    Some(url) => writeln!(out, "* [{}|{url}]", opts.and_more(hidden)),
+                    None => writeln!(out, "* {}", opts.and_more(hidden)),
+                };
+            }
         }
     }
 
-    fn create_remote_info(base_url: &str) -> RemoteInfo {
-        RemoteInfo {
-            base_url: base_url.to_string(),
+    if !release.known_issues.is_empty() {
+        let _ = writeln!(out, "\nh3. {}", opts.messages().known_issues);
+        for issue in &release.known_issues {
+            let _ = writeln!(out, "* {issue}");
         }
     }
 
-    #[test]
-    fn test_build_release_section_no_remote() {
-        let new_version = Version::parse("1.2.3").unwrap();
-        let last_version = Version::parse("1.2.2").unwrap();
-        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
-        let mut grouped = HashMap::new();
-
-        grouped.insert(
-            CommitCategory::Patch,
-            vec![create_commit_info("abc1234", "fix: bug fix")],
+    if !release.contributors.is_empty() {
+        let line = contributors_line(
+            &release.contributors,
+            opts.contributors_count,
+            opts.messages(),
         );
+        let _ = writeln!(out, "\nh3. {}\n{line}", opts.messages().contributors);
+    }
 
-        let result = build_release_section(&new_version, &last_version, date, None, &grouped);
+    if !release.closed_issues.is_empty() {
+        let links: Vec<String> = release
+            .closed_issues
+            .iter()
+            .map(|issue| match opts.issue_link(issue, remote) {
+                Some(url) => format!("[{issue}|{url}]"),
+                None => issue.to_string(),
+            })
+            .collect();
+        let heading = opts.messages().closed_issues;
+        let _ = writeln!(out, "\nh3. {heading}\n{}", links.join(", "));
+    }
 
-        assert!(result.contains("## Version 1.2.3 (2024-01-15)"));
-        assert!(result.contains("### Bug fixes"));
-        assert!(result.contains("* fix: bug fix:"));
-        assert!(result.contains("`abc1234`"));
+    if let Some(url) = compare_url {
+        let text = opts.messages().full_changes;
+        let _ = writeln!(out, "\n[{text}|{url}]");
     }
+    out.push('\n');
 
-    #[test]
-    fn test_build_release_section_with_remote() {
-        let new_version = Version::parse("2.0.0").unwrap();
-        let last_version = Version::parse("1.9.9").unwrap();
-        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
-        let remote = create_remote_info("https://github.com/user/repo/");
-        let mut grouped = HashMap::new();
+    out
+}
 
-        grouped.insert(
-            CommitCategory::Major,
-            vec![create_commit_info("def5678", "breaking: remove old API")],
-        );
+/// Returns the Markdown heading of the unreleased section, e.g. "## [Unreleased]".
+///
+/// # Arguments
+///
+/// * `label` - The label of the section
+/// * `base_level` - Number of levels to shift the heading down by
+pub fn unreleased_heading(label: &str, base_level: usize) -> String {
+    format!("{} [{label}]", "#".repeat(2 + base_level))
+}
 
-        let result =
-            build_release_section(&new_version, &last_version, date, Some(&remote), &grouped);
+/// Returns the title of a release header, e.g. "Version 1.2.3" or its tag, "v1.2.3".
+fn version_title(version: &Version, opts: &RenderOptions) -> String {
+    if opts.tag_prefix_in_header {
+        opts.tag(version)
+    } else {
+        format!("{} {version}", opts.messages().version)
+    }
+}
 
-        assert!(
-            result.contains("## [Version 2.0.0](https://github.com/user/repo/releases/tag/v2.0.0)")
+/// Formats the tag name of a version.
+///
+/// # Arguments
+///
+/// * `format` - A template with `{version}`, `{major}`, `{minor}` and `{patch}`
+///   placeholders, e.g. `release-{version}`, or `None` for `v{version}`
+/// * `version` - The version
+pub fn tag_name(format: Option<&str>, version: &Version) -> String {
+    match format {
+        Some(format) => format
+            .replace("{version}", &version.to_string())
+            .replace("{major}", &version.major.to_string())
+            .replace("{minor}", &version.minor.to_string())
+            .replace("{patch}", &version.patch.to_string()),
+        None => format!("v{version}"),
+    }
+}
+
+/// Checks that every included commit is listed exactly once in the release sections, as a
+/// safeguard against grouping bugs.
+///
+/// The check covers the bullets the renderers list from the release, section by section
+/// and after `max_per_section`, not the rendered text: it catches commits lost or repeated
+/// on the way from the classification to the sections, not formatting mistakes.
+///
+/// Commits left out on purpose are not expected: those of categories excluded by `only`,
+/// those with a blank title unless `keep_empty_sections` is set, and those beyond
+/// `max_per_section`, which the notes only count.
+///
+/// # Arguments
+///
+/// * `included` - The commits classified into the release, with their category
+/// * `release` - The release prepared for rendering
+/// * `opts` - Rendering options
+///
+/// # Returns
+///
+/// The discrepancies found: the included and listed counts, then each missing, repeated
+/// or unexpected commit. Empty if the release is consistent.
+pub fn audit_sections(
+    included: &[(CommitInfo, CommitCategory)],
+    release: &ReleaseSection,
+    opts: &RenderOptions,
+) -> Vec<String> {
+    let mut listed: HashMap<String, usize> = HashMap::new();
+    let mut summaries: HashMap<String, String> = HashMap::new();
+    let mut hidden: HashSet<&str> = HashSet::new();
+    for (heading, commits) in release.sections(opts) {
+        let (shown, hidden_count) = opts.limit_section(&heading, commits);
+        for FormattedCommit { commit, .. } in shown {
+            *listed.entry(commit.short_id.clone()).or_default() += 1;
+            summaries.insert(commit.short_id.clone(), commit.summary.clone());
+        }
+        let rest = &commits[commits.len() - hidden_count..];
+        hidden.extend(
+            rest.iter()
+                .map(|formatted| formatted.commit.short_id.as_str()),
         );
-        assert!(result.contains("### Breaking changes"));
-        assert!(result.contains("[`def5678`](https://github.com/user/repo/commit/def5678)"));
-        assert!(result
-            .contains("[...full changes](https://github.com/user/repo/compare/v1.9.9...v2.0.0)"));
     }
 
-    #[test]
-    fn test_build_release_section_all_categories() {
-        let new_version = Version::parse("1.5.0").unwrap();
-        let last_version = Version::parse("1.4.0").unwrap();
-        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
-        let mut grouped = HashMap::new();
+    let expected: Vec<&CommitInfo> = included
+        .iter()
+        .filter(|(_, cat)| *cat != CommitCategory::Ignore)
+        .filter(|(commit, _)| !hidden.contains(commit.short_id.as_str()))
+        .filter(|(_, cat)| opts.only.as_ref().map_or(true, |only| only.contains(cat)))
+        .filter(|(commit, cat)| {
+            opts.keep_empty_sections
+                || !FormattedCommit::new(commit.clone(), *cat, opts)
+                    .title
+                    .trim()
+                    .is_empty()
+        })
+        .map(|(commit, _)| commit)
+        .collect();
 
-        grouped.insert(
-            CommitCategory::Major,
-            vec![create_commit_info("maj1", "breaking: change")],
-        );
-        grouped.insert(
-            CommitCategory::Minor,
-            vec![create_commit_info("min1", "feat: new feature")],
-        );
-        grouped.insert(
-            CommitCategory::Patch,
-            vec![create_commit_info("pat1", "fix: bug")],
+    let mut problems = Vec::new();
+    let total: usize = listed.values().sum();
+    if total != expected.len() {
+        problems.push(format!(
+            "{} commits included, {total} listed",
+            expected.len()
+        ));
+    }
+    for commit in &expected {
+        match listed.remove(&commit.short_id) {
+            None => problems.push(format!("{} {} is missing", commit.short_id, commit.summary)),
+            Some(1) => {}
+            Some(n) => problems.push(format!(
+                "{} {} is listed {n} times",
+                commit.short_id, commit.summary
+            )),
+        }
+    }
+    let mut unexpected: Vec<String> = listed.into_keys().collect();
+    unexpected.sort();
+    for short_id in unexpected {
+        problems.push(format!(
+            "{short_id} {} is listed but was not included",
+            summaries[&short_id]
+        ));
+    }
+    problems
+}
+
+/// Collects the `Known-issue:` trailers of all included commits.
+///
+/// # Arguments
+///
+/// * `grouped` - Commits grouped by category
+///
+/// # Returns
+///
+/// The distinct known issues, in category and commit order.
+fn known_issues(grouped: &HashMap<CommitCategory, Vec<CommitInfo>>) -> Vec<String> {
+    let mut issues: Vec<String> = Vec::new();
+    for cat in [
+        CommitCategory::Major,
+        CommitCategory::Minor,
+        CommitCategory::Patch,
+    ] {
+        for commit in grouped.get(&cat).into_iter().flatten() {
+            for (key, value) in parse_trailers(&commit.body) {
+                if key.eq_ignore_ascii_case("Known-issue") && !issues.contains(&value) {
+                    issues.push(value);
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Collects the authors and `Co-authored-by:` co-authors of all included commits.
+///
+/// People are deduplicated by email address, or by name when the address is missing,
+/// keeping the name they first appear with.
+///
+/// # Arguments
+///
+/// * `grouped` - Commits grouped by category
+/// * `exclude_bots` - Whether to leave out bot accounts
+///
+/// # Returns
+///
+/// The contributor names in alphabetical order.
+fn contributors(
+    grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+    exclude_bots: bool,
+) -> Vec<String> {
+    static RE_PERSON: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"^(.*?)\s*<([^>]*)>$").unwrap());
+
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let commits = [
+        CommitCategory::Major,
+        CommitCategory::Minor,
+        CommitCategory::Patch,
+    ]
+    .iter()
+    .filter_map(|cat| grouped.get(cat))
+    .flatten();
+    // Walk the categories in a fixed order so the first spelling of a name always wins.
+    for commit in commits {
+        let mut people = vec![(commit.author_name.clone(), commit.author_email.clone())];
+        for (key, value) in parse_trailers(&commit.body) {
+            if !key.eq_ignore_ascii_case("Co-authored-by") {
+                continue;
+            }
+            match RE_PERSON.captures(value.trim()) {
+                Some(cap) => people.push((cap[1].to_string(), cap[2].to_string())),
+                None => people.push((value.trim().to_string(), String::new())),
+            }
+        }
+
+        for (name, email) in people {
+            if name.is_empty()
+                || (exclude_bots && (name.ends_with("[bot]") || email.contains("[bot]")))
+            {
+                continue;
+            }
+            let key = if email.is_empty() { &name } else { &email };
+            seen.entry(key.to_lowercase()).or_insert(name);
+        }
+    }
+
+    let mut names: Vec<String> = seen.into_values().collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names
+}
+
+/// Formats the contributors line, e.g. "3 contributors: Alice, Bob, Carol".
+fn contributors_line(names: &[String], count: bool, messages: &Messages) -> String {
+    let list = names.join(", ");
+    if count {
+        let count = messages.count(names.len(), messages.contributor);
+        format!("{count}: {list}")
+    } else {
+        list
+    }
+}
+
+/// Collects the issues referenced by all included commits, for the closed issues index.
+///
+/// # Arguments
+///
+/// * `grouped` - Commits grouped by category
+/// * `syntax` - How issue references are recognized
+///
+/// # Returns
+///
+/// The distinct issue references in numeric order, followed by non-numeric ones.
+pub fn closed_issues(
+    grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+    syntax: &IssueSyntax,
+) -> Vec<IssueRef> {
+    let mut issues: Vec<IssueRef> = grouped
+        .values()
+        .flatten()
+        .flat_map(|commit| commit_refs(commit, syntax).1)
+        .filter(|issue| issue.sigil == '#')
+        .collect();
+    issues.sort_by_key(|issue| {
+        (
+            issue.id.parse::<u64>().unwrap_or(u64::MAX),
+            issue.id.clone(),
+        )
+    });
+    issues.dedup();
+    issues
+}
+
+/// Builds a sentence summarizing the number of changes in each category.
+///
+/// Empty categories are omitted and counts are pluralized, e.g.
+/// "This release includes 1 breaking change, 3 new features, and 5 fixes." Categories with
+/// a custom label are counted as "💥 Breaking (1)".
+///
+/// # Arguments
+///
+/// * `grouped` - Commits grouped by category
+/// * `opts` - Rendering options
+///
+/// # Returns
+///
+/// Returns `None` if there are no changes to summarize.
+fn summary_line(
+    grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+    opts: &RenderOptions,
+) -> Option<String> {
+    let messages = opts.messages();
+    let kinds = [
+        (CommitCategory::Major, messages.breaking_change),
+        (CommitCategory::Minor, messages.new_feature),
+        (CommitCategory::Patch, messages.fix),
+    ];
+
+    let parts: Vec<String> = kinds
+        .iter()
+        .filter_map(|(cat, noun)| {
+            let count = grouped.get(cat).map_or(0, Vec::len);
+            (count > 0).then(|| match opts.category_labels.get(cat) {
+                Some(label) => format!("{label} ({count})"),
+                None => messages.count(count, *noun),
+            })
+        })
+        .collect();
+
+    if parts.is_empty() {
+        return None;
+    }
+    Some(
+        messages
+            .release_includes
+            .replace("{list}", &messages.join(&parts)),
+    )
+}
+
+/// Formats a section of commits (e.g., "Breaking changes", "New features", "Bug fixes").
+///
+/// Formats the commits as markdown list items, with links to commits and issues when
+/// remote information is available.
+///
+/// # Arguments
+///
+/// * `heading` - The section heading (e.g., "Breaking changes")
+/// * `commits` - The list of commits to format
+/// * `remote` - Optional remote repository information for generating links
+/// * `opts` - Rendering options
+///
+/// # Returns
+///
+/// A markdown-formatted string containing the section.
+fn format_section(
+    heading: &str,
+    commits: &[FormattedCommit],
+    remote: Option<&RemoteInfo>,
+    opts: &RenderOptions,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "\n{} {heading}", opts.hashes(3));
+    if commits.is_empty() && heading == opts.messages().security_fixes {
+        let _ = writeln!(out, "{}", opts.messages().none);
+    }
+
+    let bullet = |commit: &FormattedCommit| {
+        let mut bullet = format_bullet(commit, remote, opts);
+        if let Some(note) = breaking_note(&commit.commit, heading, opts) {
+            let _ = writeln!(bullet, "  * {note}");
+        }
+        if let Some(link) = migration_link(&commit.commit, heading, opts) {
+            let _ = writeln!(bullet, "  * [{}]({link})", opts.messages().migration_guide);
+        }
+        bullet
+    };
+
+    if opts.by_date {
+        for (date, commits) in by_date(commits) {
+            let date = date.format(opts.date_format());
+            let _ = writeln!(out, "\n{} {date}", opts.hashes(4));
+            for commit in commits {
+                out.push_str(&bullet(commit));
+            }
+        }
+    } else {
+        for commit in commits {
+            out.push_str(&bullet(commit));
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+/// Lists the sections in a single paragraph, one sentence per section.
+///
+/// # Returns
+///
+/// E.g. "New features: add new API (#ghi7890). Bug fixes: fix null pointer (#abc1234)."
+fn compact_paragraph(sections: &[(String, &[FormattedCommit])]) -> String {
+    sections
+        .iter()
+        .filter(|(_, commits)| !commits.is_empty())
+        .map(|(heading, commits)| {
+            let items: Vec<String> = commits
+                .iter()
+                .map(|commit| format!("{} (#{})", commit.title, commit.commit.link_text()))
+                .collect();
+            format!("{heading}: {}.", items.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Groups commits by the day (UTC) they were authored, newest day first.
+///
+/// Commits keep their order within a day.
+fn by_date(commits: &[FormattedCommit]) -> Vec<(NaiveDate, Vec<&FormattedCommit>)> {
+    let mut days: Vec<(NaiveDate, Vec<&FormattedCommit>)> = Vec::new();
+    for commit in commits {
+        let date = DateTime::from_timestamp(commit.commit.time, 0)
+            .map(|time| time.date_naive())
+            .unwrap_or_default();
+        match days.iter_mut().find(|(day, _)| *day == date) {
+            Some((_, list)) => list.push(commit),
+            None => days.push((date, vec![commit])),
+        }
+    }
+    days.sort_by(|(a, _), (b, _)| b.cmp(a));
+    days
+}
+
+/// Returns the `Migration:` trailer (a URL or path to upgrade docs) of a commit listed
+/// under the breaking changes heading.
+fn migration_link(commit: &CommitInfo, heading: &str, opts: &RenderOptions) -> Option<String> {
+    if heading != opts.heading(CommitCategory::Major) {
+        return None;
+    }
+    find_trailer(&commit.body, "Migration").filter(|link| !link.trim().is_empty())
+}
+
+/// Returns the text of the `BREAKING CHANGE:` footer of a commit listed under the breaking
+/// changes heading, when `breaking_notes` is set.
+fn breaking_note(commit: &CommitInfo, heading: &str, opts: &RenderOptions) -> Option<String> {
+    if !opts.breaking_notes || heading != opts.heading(CommitCategory::Major) {
+        return None;
+    }
+    breaking_footer(&commit.body).filter(|note| !note.is_empty())
+}
+
+/// Formats a commit as a Markdown bullet, with links to the commit and its issues.
+fn format_bullet(
+    commit: &FormattedCommit,
+    remote: Option<&RemoteInfo>,
+    opts: &RenderOptions,
+) -> String {
+    let FormattedCommit {
+        commit,
+        title,
+        refs,
+        ..
+    } = commit;
+
+    let issue_ref: String = refs
+        .iter()
+        .map(|issue| {
+            let mark = opts.issue_mark(issue);
+            match opts.issue_link(issue, remote) {
+                Some(url) => format!(" ([{issue}]({url}){mark})"),
+                None => format!(" ({issue}{mark})"),
+            }
+        })
+        .collect();
+
+    let commit_ref = if let Some(r) = remote {
+        format!(
+            " [`{}`]({})",
+            commit.link_text(),
+            r.commit_url(&commit.short_id)
+        )
+    } else {
+        format!(" `{}`", commit.short_id)
+    };
+
+    let mut out = String::new();
+    out.push_str("* ");
+    out.push_str(title);
+    out.push(':');
+    out.push_str(&commit_ref);
+    out.push_str(&issue_ref);
+    if let Some((added, removed)) = commit.diff_stats {
+        let _ = write!(out, " *(+{added} \u{2212}{removed})*");
+    }
+    if commit.has_signature {
+        out.push_str(" `has signature`");
+    }
+    out.push('\n');
+    out
+}
+
+/// Formats a section of commits as a GitHub-flavoured Markdown table.
+///
+/// Lists the same content as [`format_section`] in `| SHA | Category | Summary | Issue |`
+/// columns, with the category each commit was classified in. Pipes in commit titles are
+/// escaped so they don't split cells.
+///
+/// # Arguments
+///
+/// * `heading` - The section heading (e.g., "Breaking changes")
+/// * `commits` - The list of commits to format
+/// * `remote` - Optional remote repository information for generating links
+/// * `opts` - Rendering options
+///
+/// # Returns
+///
+/// A markdown-formatted string containing the section.
+fn format_section_table(
+    heading: &str,
+    commits: &[FormattedCommit],
+    remote: Option<&RemoteInfo>,
+    opts: &RenderOptions,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "\n{} {heading}", opts.hashes(3));
+    if commits.is_empty() && heading == opts.messages().security_fixes {
+        let _ = writeln!(out, "{}", opts.messages().none);
+    }
+    if commits.is_empty() {
+        out.push('\n');
+        return out;
+    }
+
+    out.push_str("| SHA | Category | Summary | Issue |\n");
+    out.push_str("| :-- | :-: | :-- | :-- |\n");
+
+    for FormattedCommit {
+        commit,
+        category,
+        title,
+        refs,
+    } in commits
+    {
+        let title = title.replace('|', "\\|");
+
+        let sha = match remote {
+            Some(r) => format!(
+                "[`{}`]({})",
+                commit.link_text(),
+                r.commit_url(&commit.short_id)
+            ),
+            None => format!("`{}`", commit.short_id),
+        };
+
+        let issues: Vec<String> = refs
+            .iter()
+            .map(|issue| {
+                let mark = opts.issue_mark(issue);
+                match opts.issue_link(issue, remote) {
+                    Some(url) => format!("[{issue}]({url}){mark}"),
+                    None => format!("{issue}{mark}"),
+                }
+            })
+            .collect();
+
+        let _ = writeln!(
+            out,
+            "| {sha} | {} | {title} | {} |",
+            category.name(),
+            issues.join(", ")
+        );
+    }
+
+    out.push('\n');
+    out
+}
+
+/// A reference to an issue (`#12`) or merge request (`!34`) found in a commit title.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueRef {
+    /// `#` for issues and pull requests, `!` for merge requests.
+    pub sigil: char,
+    /// The issue or merge request number.
+    pub id: String,
+}
+
+impl IssueRef {
+    /// Creates an issue (`#`) reference.
+    fn issue(id: impl Into<String>) -> Self {
+        Self {
+            sigil: '#',
+            id: id.into(),
+        }
+    }
+
+    /// The URL of the referenced item on the forge of the remote.
+    fn url(&self, remote: &RemoteInfo) -> String {
+        match self.sigil {
+            '!' => remote.merge_request_url(&self.id),
+            _ => remote.issue_url(&self.id),
+        }
+    }
+}
+
+impl std::fmt::Display for IssueRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.sigil, self.id)
+    }
+}
+
+/// Pattern of the issue ids recognized by default: issue and merge request numbers.
+const DEFAULT_ISSUE_ID_PATTERN: &str = r"\d+";
+
+/// Maximum compiled size of a custom issue id pattern, in bytes.
+const ISSUE_ID_PATTERN_SIZE_LIMIT: usize = 1 << 20;
+
+/// How issue references are recognized in commit titles and linked.
+#[derive(Debug, Clone)]
+pub struct IssueSyntax {
+    /// Squash-merge references, e.g. " (#42)".
+    squashed: Regex,
+    /// A single reference at the end of the title, e.g. " #42".
+    trailing: Regex,
+    /// URL of an issue, with `{id}` replaced by its id, used instead of the remote's
+    /// issue tracker.
+    url_template: Option<String>,
+}
+
+impl IssueSyntax {
+    /// Creates an issue syntax.
+    ///
+    /// The `regex` crate matches in linear time, so no id pattern can backtrack
+    /// catastrophically; the compiled size of the pattern is limited as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `id_pattern` - Regex of the ids following `#` or `!`, e.g. `[A-Z]+-\d+`, or `None`
+    ///   for numbers
+    /// * `url_template` - URL of an issue with an `{id}` placeholder, if issues are not
+    ///   tracked on the forge
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is not a valid regex or is too large.
+    pub fn new(id_pattern: Option<&str>, url_template: Option<String>) -> Result<Self> {
+        let id = id_pattern.unwrap_or(DEFAULT_ISSUE_ID_PATTERN);
+        let build = |pattern: String| {
+            RegexBuilder::new(&pattern)
+                .size_limit(ISSUE_ID_PATTERN_SIZE_LIMIT)
+                .build()
+                .map_err(|err| anyhow!("Invalid issue id pattern {id}: {err}"))
+        };
+        Ok(Self {
+            squashed: build(format!(r"\s+\((?P<sigil>[#!])(?P<id>{id})\)"))?,
+            trailing: build(format!(r"\s+(?P<sigil>[#!])(?P<id>{id})$"))?,
+            url_template,
+        })
+    }
+
+    /// Extracts issue and merge request references from a commit title.
+    ///
+    /// Recognizes squash-merge references ("title (#42)", possibly several like
+    /// "title (#12) (!34)") and a single trailing reference ("title #42").
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - The commit title
+    ///
+    /// # Returns
+    ///
+    /// The title with the references removed, and the references in order of appearance.
+    pub fn extract(&self, summary: &str) -> (String, Vec<IssueRef>) {
+        let to_ref = |cap: regex::Captures| IssueRef {
+            sigil: cap["sigil"].chars().next().unwrap_or('#'),
+            id: cap["id"].to_string(),
+        };
+
+        let refs: Vec<IssueRef> = self.squashed.captures_iter(summary).map(to_ref).collect();
+        if !refs.is_empty() {
+            return (self.squashed.replace_all(summary, "").into_owned(), refs);
+        }
+
+        if let Some(cap) = self.trailing.captures(summary) {
+            let title = self.trailing.replace(summary, "").into_owned();
+            return (title, vec![to_ref(cap)]);
+        }
+
+        (summary.to_string(), Vec::new())
+    }
+}
+
+impl Default for IssueSyntax {
+    fn default() -> Self {
+        static DEFAULT: once_cell::sync::Lazy<IssueSyntax> =
+            once_cell::sync::Lazy::new(|| IssueSyntax::new(None, None).unwrap());
+        DEFAULT.clone()
+    }
+}
+
+/// Extracts the references of a commit, falling back to the pull request it was
+/// merged through.
+fn commit_refs(commit: &CommitInfo, syntax: &IssueSyntax) -> (String, Vec<IssueRef>) {
+    let (title, mut refs) = syntax.extract(&commit.summary);
+    if refs.is_empty() {
+        refs.extend(commit.pr_number.as_deref().map(IssueRef::issue));
+    }
+    (title, refs)
+}
+
+/// Checks whether a commit references an issue or pull request, in its subject or through
+/// the pull request it was merged through.
+pub fn has_issue_ref(commit: &CommitInfo, syntax: &IssueSyntax) -> bool {
+    !commit_refs(commit, syntax).1.is_empty()
+}
+
+/// Applies the title clean-ups requested in the rendering options.
+///
+/// # Arguments
+///
+/// * `title` - The bullet title, with issue references already removed
+/// * `opts` - Rendering options
+///
+/// # Returns
+///
+/// The cleaned title.
+fn clean_title(title: String, opts: &RenderOptions) -> String {
+    if opts.strip_trailing_period && title.ends_with('.') && !title.ends_with("..") {
+        let mut title = title;
+        title.pop();
+        return title;
+    }
+    title
+}
+
+/// Finds issue numbers referenced by more than one commit.
+///
+/// The same issue appearing twice in a release can point to duplicate credit or an
+/// incorrectly applied cherry-pick.
+///
+/// # Arguments
+///
+/// * `grouped` - Commits grouped by category
+/// * `syntax` - How issue references are recognized
+///
+/// # Returns
+///
+/// A list of `(issue_id, short_ids)` pairs in numeric issue order.
+pub fn find_duplicate_issue_refs(
+    grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+    syntax: &IssueSyntax,
+) -> Vec<(String, Vec<String>)> {
+    let mut refs: HashMap<String, Vec<String>> = HashMap::new();
+    for cat in [
+        CommitCategory::Major,
+        CommitCategory::Minor,
+        CommitCategory::Patch,
+    ] {
+        for commit in grouped.get(&cat).into_iter().flatten() {
+            let (_, issues) = syntax.extract(&commit.summary);
+            for issue in issues.into_iter().filter(|issue| issue.sigil == '#') {
+                refs.entry(issue.id)
+                    .or_default()
+                    .push(commit.short_id.clone());
+            }
+        }
+    }
+
+    let mut duplicates: Vec<(String, Vec<String>)> = refs
+        .into_iter()
+        .filter(|(_, commits)| commits.len() > 1)
+        .collect();
+    duplicates.sort_by_key(|(id, _)| id.parse::<u64>().unwrap_or(u64::MAX));
+    duplicates
+}
+
+/// Options controlling how the changelog file is written.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Start newly created files with an encoding declaration comment.
+    pub encoding_declaration: bool,
+    /// Leave the format version comment out, for formats that would show a Markdown
+    /// comment as text (Confluence wiki markup).
+    pub no_format_version: bool,
+    /// Heading of a section to remove from the existing changelog before the new one is
+    /// prepended, so that regenerating the unreleased section replaces it.
+    pub replace_heading: Option<String>,
+    /// Add the new section at the end of the changelog (oldest release first) instead of
+    /// at the top. It goes before the generated footer, if there is one.
+    pub append: bool,
+    /// Closing text of the changelog, e.g. a note on the commit convention. It is kept
+    /// once at the end, before the generated footer, however many releases are added.
+    pub footer: Option<String>,
+}
+
+/// Markdown comment declaring the file encoding, for tools that require one.
+const ENCODING_DECLARATION: &str = "[//]: # (encoding: utf-8)";
+
+/// Last line of a changelog started by changelogger.
+const GENERATED_FOOTER: &str = "--- Generated by changelogger";
+
+/// Version of the changelog layout written by this release of changelogger.
+pub const FORMAT_VERSION: &str = "1.0";
+
+/// Prefix of the Markdown comment recording the format version of a changelog.
+const FORMAT_VERSION_PREFIX: &str = "[//]: # (changelogger format version: ";
+
+/// Reads the format version comment of a changelog.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content
+///
+/// # Returns
+///
+/// The version in `[//]: # (changelogger format version: 1.0)`, or `None` if the changelog
+/// has no such comment, e.g. because it was not started by changelogger.
+pub fn format_version(content: &str) -> Option<&str> {
+    content.lines().find_map(|line| {
+        line.trim_end()
+            .strip_prefix(FORMAT_VERSION_PREFIX)?
+            .strip_suffix(')')
+    })
+}
+
+/// Tells whether a changelog was started by changelogger: it has a format version comment
+/// or, like the changelogs of releases before those comments and Confluence changelogs,
+/// ends with the generated footer.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content
+pub fn is_generated(content: &str) -> bool {
+    format_version(content).is_some() || split_generated_footer(content).1
+}
+
+/// Splits the comments that changelogger puts at the top of a new changelog from the rest.
+///
+/// # Returns
+///
+/// The header, up to and including the last comment line, and the remaining content.
+/// The header is empty if the changelog does not start with such comments.
+fn split_header(content: &str) -> (&str, &str) {
+    let mut end = 0;
+    let mut pos = 0;
+    for line in content.split_inclusive('\n') {
+        pos += line.len();
+        let line = line.trim_end();
+        if line == ENCODING_DECLARATION || line.starts_with(FORMAT_VERSION_PREFIX) {
+            end = pos;
+        } else if !line.is_empty() {
+            break;
+        }
+    }
+    if end == 0 {
+        ("", content)
+    } else {
+        (&content[..end], content[end..].trim_start())
+    }
+}
+
+/// Removes the section starting at `heading` from a changelog.
+///
+/// The section ends at the next heading of the same level or the generated footer.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content
+/// * `heading` - The exact heading line of the section (e.g. "## [Unreleased]")
+///
+/// # Returns
+///
+/// The content without the section, or unchanged if there is no such heading.
+fn remove_section<'a>(content: &'a str, heading: &str) -> Cow<'a, str> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let Some(start) = lines.iter().position(|line| line.trim_end() == heading) else {
+        return Cow::Borrowed(content);
+    };
+    let level = format!("{} ", heading.split(' ').next().unwrap_or_default());
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.starts_with(&level) || line.starts_with(GENERATED_FOOTER))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    Cow::Owned([&lines[..start], &lines[end..]].concat().concat())
+}
+
+/// Splits the generated footer off the end of a changelog.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content
+///
+/// # Returns
+///
+/// The content before the generated footer, without trailing whitespace, and whether there
+/// was a generated footer.
+fn split_generated_footer(content: &str) -> (&str, bool) {
+    let body = content.trim_end();
+    match body.strip_suffix(GENERATED_FOOTER) {
+        Some(body) => (body.trim_end(), true),
+        None => (body, false),
+    }
+}
+
+/// Removes the closing text of a changelog, leaving the generated footer in place.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content
+/// * `footer` - The closing text, as given in [`WriteOptions::footer`]
+///
+/// # Returns
+///
+/// The content without the closing text, or unchanged if it does not end with it.
+fn remove_footer<'a>(content: &'a str, footer: &str) -> Cow<'a, str> {
+    let (body, generated) = split_generated_footer(content);
+    let Some(body) = body.strip_suffix(footer.trim()) else {
+        return Cow::Borrowed(content);
+    };
+    let body = body.trim_end();
+    Cow::Owned(match (body.is_empty(), generated) {
+        (true, true) => format!("{GENERATED_FOOTER}\n"),
+        (true, false) => String::new(),
+        (false, true) => format!("{body}\n\n{GENERATED_FOOTER}\n"),
+        (false, false) => format!("{body}\n"),
+    })
+}
+
+/// Adds the closing text at the end of a changelog, before the generated footer.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content, without the closing text
+/// * `footer` - The closing text, as given in [`WriteOptions::footer`]
+///
+/// # Returns
+///
+/// The content ending with the closing text (and the generated footer, if there is one).
+fn add_footer(content: &str, footer: &str) -> String {
+    let footer = footer.trim();
+    match split_generated_footer(content) {
+        (body, true) => format!("{body}\n\n{footer}\n\n{GENERATED_FOOTER}\n"),
+        (body, false) => format!("{body}\n\n{footer}\n"),
+    }
+}
+
+/// Merges a new release section into existing changelog content.
+///
+/// If the existing content is non-empty, the new section is prepended (or appended), after
+/// removing the section to replace, if any. A prepended section goes below the header
+/// comments. Otherwise a new changelog is started with a format version comment and a footer
+/// (and an encoding declaration, if requested). The closing text, if any, is moved to the
+/// end so that it appears only once.
+///
+/// # Arguments
+///
+/// * `existing` - The current changelog content (may be empty)
+/// * `new_section` - The new release section to add
+/// * `opts` - Write options
+///
+/// # Returns
+///
+/// The full changelog content.
+fn merge_changelog(existing: &str, new_section: &str, opts: &WriteOptions) -> String {
+    let merged = merge_section(existing, new_section, opts);
+    match opts
+        .footer
+        .as_deref()
+        .filter(|footer| !footer.trim().is_empty())
+    {
+        Some(footer) => add_footer(&merged, footer),
+        None => merged,
+    }
+}
+
+/// Merges a new release section into existing changelog content, without the closing text.
+///
+/// # Arguments
+///
+/// * `existing` - The current changelog content (may be empty)
+/// * `new_section` - The new release section to add
+/// * `opts` - Write options
+///
+/// # Returns
+///
+/// The full changelog content, without [`WriteOptions::footer`].
+fn merge_section(existing: &str, new_section: &str, opts: &WriteOptions) -> String {
+    let existing = match opts
+        .footer
+        .as_deref()
+        .filter(|footer| !footer.trim().is_empty())
+    {
+        Some(footer) => remove_footer(existing, footer),
+        None => Cow::Borrowed(existing),
+    };
+    let existing = match &opts.replace_heading {
+        Some(heading) => Cow::Owned(remove_section(&existing, heading).into_owned()),
+        None => existing,
+    };
+    if existing.trim().is_empty() {
+        let declaration = if opts.encoding_declaration {
+            format!("{ENCODING_DECLARATION}\n\n")
+        } else {
+            String::new()
+        };
+        let version = if opts.no_format_version {
+            String::new()
+        } else {
+            format!("{FORMAT_VERSION_PREFIX}{FORMAT_VERSION})\n\n")
+        };
+        format!("{declaration}{version}{new_section}\n{GENERATED_FOOTER}\n")
+    } else if format_version(&existing).is_none()
+        && split_generated_footer(&existing).1
+        && !opts.no_format_version
+    {
+        // Started by a release of changelogger from before the format version comment.
+        let stamped = match split_header(&existing) {
+            ("", rest) => format!("{FORMAT_VERSION_PREFIX}{FORMAT_VERSION})\n\n{rest}"),
+            (header, rest) => format!(
+                "{}\n\n{FORMAT_VERSION_PREFIX}{FORMAT_VERSION})\n\n{rest}",
+                header.trim_end()
+            ),
+        };
+        merge_section(&stamped, new_section, opts)
+    } else if opts.append {
+        match split_generated_footer(&existing) {
+            (body, true) => format!("{body}\n\n{new_section}\n{GENERATED_FOOTER}\n"),
+            (body, false) => format!("{body}\n\n{new_section}"),
+        }
+    } else {
+        match split_header(&existing) {
+            ("", existing) => format!("{new_section}\n\n{existing}"),
+            (header, existing) => format!("{header}\n{new_section}\n\n{existing}"),
+        }
+    }
+}
+
+/// Release data written by `--output-toml`.
+#[derive(Serialize)]
+struct ReleaseData {
+    release: ReleaseTable,
+}
+
+/// The `[release]` table.
+#[derive(Serialize)]
+struct ReleaseTable {
+    version: String,
+    date: String,
+    categories: Vec<CategoryData>,
+}
+
+/// A section of the release, named after its heading.
+#[derive(Serialize)]
+struct CategoryData {
+    name: String,
+    commits: Vec<CommitData>,
+}
+
+/// A commit of a section.
+#[derive(Serialize)]
+struct CommitData {
+    short_id: String,
+    summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue_id: Option<String>,
+}
+
+/// Serializes the release as TOML, with the sections and commits of the rendered notes.
+///
+/// # Arguments
+///
+/// * `release` - The release, from [`build_release_section`]
+/// * `opts` - Rendering options, as given to [`build_release_section`]
+///
+/// # Returns
+///
+/// A `[release]` table with `version`, `date` and
+/// `categories = [{ name, commits = [{ short_id, summary, issue_id }] }]`.
+///
+/// # Errors
+///
+/// Returns an error if the data cannot be serialized.
+pub fn release_toml(release: &ReleaseSection, opts: &RenderOptions) -> Result<String> {
+    let categories = release
+        .sections(opts)
+        .into_iter()
+        .map(|(name, commits)| CategoryData {
+            name,
+            commits: commits
+                .iter()
+                .map(|commit| CommitData {
+                    short_id: commit.commit.short_id.clone(),
+                    summary: commit.title.clone(),
+                    issue_id: commit.refs.first().map(|issue| issue.id.clone()),
+                })
+                .collect(),
+        })
+        .collect();
+    let data = ReleaseData {
+        release: ReleaseTable {
+            version: release.version.to_string(),
+            date: release.date.format("%Y-%m-%d").to_string(),
+            categories,
+        },
+    };
+    Ok(toml::to_string(&data)?)
+}
+
+/// A release as written by `--format json`.
+#[derive(Serialize)]
+struct ReleaseJson {
+    version: String,
+    date: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compare_url: Option<String>,
+    breaking: Vec<CommitJson>,
+    features: Vec<CommitJson>,
+    fixes: Vec<CommitJson>,
+    sections: Vec<SectionJson>,
+}
+
+/// A section of the release other than breaking changes, new features and bug fixes.
+#[derive(Serialize)]
+struct SectionJson {
+    name: String,
+    commits: Vec<CommitJson>,
+}
+
+/// A commit of the release.
+#[derive(Serialize)]
+struct CommitJson {
+    short_id: String,
+    title: String,
+    category: &'static str,
+    issues: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+}
+
+/// Renders a release as JSON, for CI pipelines that post release notes elsewhere.
+///
+/// # Arguments
+///
+/// * `release` - The release, from [`build_release_section`]
+/// * `opts` - Rendering options, as given to [`build_release_section`]
+///
+/// # Returns
+///
+/// An object with `version`, `date` (ISO 8601), `url` and `compare_url` (if there is a
+/// remote), the `breaking`, `features` and `fixes` commits, and the other `sections` as
+/// `[{ name, commits }]`. Each commit has a `short_id`, `title`, `category`, the `issues`
+/// it references (e.g. `#12`) and a `url` if there is a remote.
+pub fn render_json(release: &ReleaseSection, opts: &RenderOptions) -> String {
+    let remote = release.remote.as_ref();
+    let commits = |list: &[FormattedCommit]| -> Vec<CommitJson> {
+        list.iter()
+            .map(|commit| CommitJson {
+                short_id: commit.commit.short_id.clone(),
+                title: commit.title.clone(),
+                category: commit.category.name(),
+                issues: commit.refs.iter().map(ToString::to_string).collect(),
+                url: remote.map(|r| r.commit_url(&commit.commit.short_id)),
+            })
+            .collect()
+    };
+    let data = ReleaseJson {
+        version: release.version.to_string(),
+        date: release.date.format("%Y-%m-%d").to_string(),
+        url: release.release_url(opts),
+        compare_url: release.compare_url(),
+        breaking: commits(&release.breaking),
+        features: commits(&release.features),
+        fixes: commits(&release.fixes),
+        sections: release
+            .other
+            .iter()
+            .map(|(name, list)| SectionJson {
+                name: name.clone(),
+                commits: commits(list),
+            })
+            .collect(),
+    };
+    let mut out = serde_json::to_string_pretty(&data).expect("release data has only string keys");
+    out.push('\n');
+    out
+}
+
+/// Strips the trailing whitespace of every line, keeping the final newline.
+///
+/// # Arguments
+///
+/// * `text` - The generated section
+pub fn trim_trailing_whitespace(text: &str) -> String {
+    let mut out = text
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Hard-wraps the bullets of a Markdown section at a column width.
+///
+/// Continuation lines are indented to align under the bullet text. Links are never broken,
+/// so a line holding a single long word or link may exceed the width. Other lines are left
+/// as they are.
+///
+/// # Arguments
+///
+/// * `text` - The generated section
+/// * `width` - The maximum line width, in characters
+pub fn wrap_bullets(text: &str, width: usize) -> String {
+    static RE_BULLET: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"^ *[*-] ").unwrap());
+
+    let mut out = text
+        .lines()
+        .map(|line| match RE_BULLET.find(line) {
+            Some(marker) if line.chars().count() > width => {
+                wrap_line(marker.as_str(), &line[marker.end()..], width)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Wraps the text of one bullet after its marker (e.g. `"  * "`).
+fn wrap_line(marker: &str, text: &str, width: usize) -> String {
+    let indent = " ".repeat(marker.len());
+    let mut out = marker.to_string();
+    let mut len = marker.len();
+    let mut line_start = true;
+    for word in split_words(text) {
+        let word_len = word.chars().count();
+        if !line_start && len + 1 + word_len > width {
+            out.push('\n');
+            out.push_str(&indent);
+            len = indent.len();
+            line_start = true;
+        }
+        if !line_start {
+            out.push(' ');
+            len += 1;
+        }
+        out.push_str(word);
+        len += word_len;
+        line_start = false;
+    }
+    out
+}
+
+/// Splits text at spaces, keeping `[text](url)` links whole.
+fn split_words(text: &str) -> Vec<&str> {
+    static RE_LINK: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"\[[^\]]*\]\([^)]*\)").unwrap());
+
+    let links: Vec<_> = RE_LINK.find_iter(text).map(|m| m.range()).collect();
+    let mut words = Vec::new();
+    let mut start = 0;
+    for (i, _) in text.match_indices(' ') {
+        if links.iter().any(|link| link.contains(&i)) {
+            continue;
+        }
+        words.push(&text[start..i]);
+        start = i + 1;
+    }
+    words.push(&text[start..]);
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+/// Quotes text as a double-quoted Nix string.
+///
+/// Backslashes, quotes, interpolations (`${`) and control characters are escaped, so the
+/// string evaluates to the text unchanged.
+fn nix_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Builds a Nix attribute set with the release metadata and notes.
+///
+/// The notes are embedded as a double-quoted string, see [`nix_string`].
+///
+/// # Arguments
+///
+/// * `version` - The new version
+/// * `date` - The release date
+/// * `section` - The generated changelog section
+///
+/// # Returns
+///
+/// The Nix expression, e.g. `{ version = "1.2.3"; date = "2024-01-15"; changelog = "..."; }`.
+pub fn nix_manifest(version: &Version, date: NaiveDate, section: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{{");
+    let _ = writeln!(out, "  version = \"{version}\";");
+    let _ = writeln!(out, "  date = \"{}\";", date.format("%Y-%m-%d"));
+    let changelog = format!("{}\n", section.trim_end());
+    let _ = writeln!(out, "  changelog = {};", nix_string(&changelog));
+    let _ = writeln!(out, "}}");
+    out
+}
+
+/// Finds the release with the highest version in an existing changelog.
+///
+/// # Arguments
+///
+/// * `content` - The changelog content
+///
+/// # Returns
+///
+/// The highest version found in a Markdown (`##`) or Confluence (`h2.`) heading, with that
+/// heading line, if any.
+pub fn latest_release_heading(content: &str) -> Option<(Version, String)> {
+    static RE_VERSION: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+        Regex::new(
+            r"^(?:#+|h[1-6]\.)\s.*?\bv?(\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?(?:\+[0-9A-Za-z.-]+)?)",
+        )
+        .unwrap()
+    });
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let cap = RE_VERSION.captures(line)?;
+            let version = Version::parse(&cap[1]).ok()?;
+            Some((version, line.trim_end().to_string()))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+/// Reads a changelog file, returning an empty string if it doesn't exist.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read.
+pub fn read_existing(path: &Path) -> Result<String> {
+    if path.exists() {
+        Ok(fs::read_to_string(path)?)
+    } else {
+        Ok(String::new())
+    }
+}
+
+/// Writes a new changelog section to a file.
+///
+/// If the file exists and contains content, the new section is prepended.
+/// If the file doesn't exist or is empty, a new changelog is created with a format version
+/// comment and a footer (and an encoding declaration, if requested).
+///
+/// # Arguments
+///
+/// * `path` - The path to the changelog file
+/// * `new_section` - The new release section to add
+/// * `opts` - Write options
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or written.
+pub fn write_changelog(path: &str, new_section: &str, opts: &WriteOptions) -> Result<()> {
+    let p = Path::new(path);
+    let existing = read_existing(p)?;
+    fs::write(p, merge_changelog(&existing, new_section, opts))?;
+    Ok(())
+}
+
+/// Writes the merged changelog to `out` instead of updating a file.
+///
+/// The existing content is read from `existing_path` (if present) and left untouched.
+///
+/// # Arguments
+///
+/// * `out` - Where to write the merged changelog (e.g. stdout)
+/// * `existing_path` - The changelog to merge the new section into
+/// * `new_section` - The new release section to add
+/// * `opts` - Write options
+///
+/// # Errors
+///
+/// Returns an error if the existing file cannot be read or `out` cannot be written.
+pub fn print_changelog<W: io::Write>(
+    out: &mut W,
+    existing_path: &str,
+    new_section: &str,
+    opts: &WriteOptions,
+) -> Result<()> {
+    let existing = read_existing(Path::new(existing_path))?;
+    out.write_all(merge_changelog(&existing, new_section, opts).as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Opens an inherited file descriptor (e.g. a pipe set up by the caller) for writing.
+///
+/// Takes ownership of the descriptor, which is closed once the returned file is dropped.
+///
+/// # Arguments
+///
+/// * `fd` - The file descriptor number
+///
+/// # Errors
+///
+/// Returns an error if the descriptor is not open, or on platforms without file
+/// descriptors.
+#[cfg(unix)]
+pub fn open_output_fd(fd: i32) -> Result<fs::File> {
+    use std::os::unix::io::FromRawFd;
+
+    // SAFETY: fcntl(F_GETFD) only queries the descriptor flags; it fails with EBADF
+    // if `fd` is not an open descriptor, in which case we don't take ownership.
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(anyhow!(
+            "File descriptor {fd} is not open: {}",
+            io::Error::last_os_error()
+        ));
+    }
+    // SAFETY: `fd` is open, and the caller hands it over for us to write to and close.
+    Ok(unsafe { fs::File::from_raw_fd(fd) })
+}
+
+/// Opens an inherited file descriptor for writing.
+///
+/// # Errors
+///
+/// Always returns an error, writing to file descriptors is only supported on Unix.
+#[cfg(not(unix))]
+pub fn open_output_fd(fd: i32) -> Result<fs::File> {
+    Err(anyhow!(
+        "Writing to file descriptor {fd} is only supported on Unix"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::ENGLISH;
+    use git2::Oid;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn markdown(
+        new_version: &Version,
+        last_version: &Version,
+        date: NaiveDate,
+        remote: Option<&RemoteInfo>,
+        grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+        opts: &RenderOptions,
+    ) -> String {
+        let release = build_release_section(new_version, last_version, date, remote, grouped, opts);
+        render_markdown(&release, opts)
+    }
+
+    fn confluence(
+        new_version: &Version,
+        last_version: &Version,
+        date: NaiveDate,
+        remote: Option<&RemoteInfo>,
+        grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+        opts: &RenderOptions,
+    ) -> String {
+        let release = build_release_section(new_version, last_version, date, remote, grouped, opts);
+        render_confluence(&release, opts)
+    }
+
+    fn formatted(commits: &[CommitInfo], opts: &RenderOptions) -> Vec<FormattedCommit> {
+        commits
+            .iter()
+            .map(|commit| FormattedCommit::new(commit.clone(), CommitCategory::Patch, opts))
+            .collect()
+    }
+
+    fn create_commit_info(short_id: &str, summary: &str) -> CommitInfo {
+        CommitInfo {
+            oid: Oid::zero(),
+            short_id: short_id.to_string(),
+            abbrev_id: short_id.to_string(),
+            summary: summary.to_string(),
+            body: String::new(),
+            section: None,
+            parent_count: 1,
+            pr_number: None,
+            diff_stats: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            has_signature: false,
+            internal: false,
+            time: 0,
+            ignore_reason: None,
+        }
+    }
+
+    fn create_remote_info(base_url: &str) -> RemoteInfo {
+        RemoteInfo::new(base_url.to_string())
+    }
+
+    #[test]
+    fn test_build_release_section_no_remote() {
+        let new_version = Version::parse("1.2.3").unwrap();
+        let last_version = Version::parse("1.2.2").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("abc1234", "fix: bug fix")],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains("## Version 1.2.3 (2024-01-15)"));
+        assert!(result.contains("### Bug fixes"));
+        assert!(result.contains("* fix: bug fix:"));
+        assert!(result.contains("`abc1234`"));
+    }
+
+    #[test]
+    fn test_build_release_section_with_remote() {
+        let new_version = Version::parse("2.0.0").unwrap();
+        let last_version = Version::parse("1.9.9").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("def5678", "breaking: remove old API")],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        assert!(
+            result.contains("## [Version 2.0.0](https://github.com/user/repo/releases/tag/v2.0.0)")
+        );
+        assert!(result.contains("### Breaking changes"));
+        assert!(result.contains("[`def5678`](https://github.com/user/repo/commit/def5678)"));
+        assert!(result
+            .contains("[...full changes](https://github.com/user/repo/compare/v1.9.9...v2.0.0)"));
+    }
+
+    #[test]
+    fn test_build_release_section_no_release_link() {
+        let new_version = Version::parse("2.0.0").unwrap();
+        let last_version = Version::parse("1.9.9").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("abc1234", "fix: bug fix")],
+        );
+        let opts = RenderOptions {
+            no_release_link: true,
+            ..Default::default()
+        };
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+
+        assert!(result.starts_with("## Version 2.0.0 (2024-02-20)\n"));
+        assert!(!result.contains("releases/tag"));
+        assert!(result
+            .contains("[...full changes](https://github.com/user/repo/compare/v1.9.9...v2.0.0)"));
+    }
+
+    #[test]
+    fn test_build_release_section_issues_index() {
+        let new_version = Version::parse("1.1.0").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("abc1234", "feat: add thing (#12)")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("def5678", "fix: one bug (#5)"),
+                create_commit_info("0123abc", "fix: same bug again #5"),
+            ],
+        );
+        let opts = RenderOptions {
+            issues_index: true,
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.contains("\n### Closed issues\n#5, #12\n"));
+
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let linked = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        assert!(linked.contains(
+            "[#5](https://github.com/user/repo/issues/5), [#12](https://github.com/user/repo/issues/12)"
+        ));
+    }
+
+    #[test]
+    fn test_build_release_section_compare_by_shas() {
+        let new_version = Version::parse("2.0.0").unwrap();
+        let last_version = Version::parse("1.9.9").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("abc1234", "fix: bug fix")],
+        );
+        let opts = RenderOptions {
+            compare_range: Some(("1111111".to_string(), "2222222".to_string())),
+            ..Default::default()
+        };
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+
+        assert!(result
+            .contains("[...full changes](https://github.com/user/repo/compare/1111111...2222222)"));
+        assert!(!result.contains("compare/v1.9.9"));
+    }
+
+    #[test]
+    fn test_build_release_section_max_per_section() {
+        let new_version = Version::parse("1.0.1").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            (1..=5)
+                .map(|i| create_commit_info(&format!("abc000{i}"), &format!("fix: bug {i}")))
+                .collect(),
+        );
+        grouped.insert(
+            CommitCategory::Major,
+            (1..=5)
+                .map(|i| create_commit_info(&format!("def000{i}"), &format!("breaking: api {i}")))
+                .collect(),
+        );
+        let opts = RenderOptions {
+            max_per_section: Some(3),
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+
+        let fixes = result.split("### Bug fixes\n").nth(1).unwrap();
+        assert_eq!(fixes.matches("* fix: bug").count(), 3);
+        assert!(fixes.contains("`abc0003`\n* ...and 2 more\n\n"));
+        assert_eq!(result.matches("* breaking: api").count(), 5);
+    }
+
+    #[test]
+    fn test_contributors_merges_authors_and_co_authors() {
+        let mut alice = create_commit_info("abc1234", "fix: one");
+        alice.author_name = "Alice".to_string();
+        alice.author_email = "alice@example.com".to_string();
+        alice.body = "Co-authored-by: Bob <bob@example.com>\n\
+                      Co-authored-by: dependabot[bot] <support@github.com>"
+            .to_string();
+        let mut bob = create_commit_info("def5678", "feat: two");
+        bob.author_name = "Bob B.".to_string();
+        bob.author_email = "BOB@example.com".to_string();
+        bob.body = "Co-authored-by: Alice <alice@example.com>\n\
+                    Co-authored-by: Carol <carol@example.com>"
+            .to_string();
+        let mut grouped = HashMap::new();
+        grouped.insert(CommitCategory::Patch, vec![alice]);
+        grouped.insert(CommitCategory::Minor, vec![bob]);
+
+        let names = contributors(&grouped, true);
+        assert_eq!(names.len(), 3);
+        assert!(contributors_line(&names, true, &ENGLISH).starts_with("3 contributors: Alice, "));
+        assert_eq!(contributors(&grouped, false).len(), 4);
+    }
+
+    #[test]
+    fn test_build_release_section_group_security_separate() {
+        let new_version = Version::parse("1.0.1").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let opts = RenderOptions {
+            group_security_separate: true,
+            ..Default::default()
+        };
+
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("abc1234", "fix: bug fix")],
+        );
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.contains("\n### Security fixes\nNone.\n"));
+
+        let mut cookie = create_commit_info("def5678", "fix: set SameSite on cookies");
+        cookie.section = Some("Security".to_string());
+        grouped.insert(CommitCategory::Patch, vec![cookie]);
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.contains("\n### Security fixes\n* fix: set SameSite on cookies:"));
+        assert!(!result.contains("None."));
+        assert!(!result.contains("### Security\n"));
+    }
+
+    #[test]
+    fn test_build_release_section_only() {
+        let new_version = Version::parse("1.1.0").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("abc1234", "feat: add thing")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("def5678", "fix: bug fix")],
+        );
+        let opts = RenderOptions {
+            only: Some(vec![CommitCategory::Minor]),
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+
+        assert!(result.contains("### New features\n* feat: add thing:"));
+        assert!(!result.contains("Bug fixes"));
+        assert!(!result.contains("bug fix"));
+    }
+
+    #[test]
+    fn test_build_release_section_lang_fr() {
+        let new_version = Version::parse("2.0.0").unwrap();
+        let last_version = Version::parse("1.9.9").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("abc1234", "breaking: drop old API")],
+        );
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("def5678", "feat: add thing")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("0123abc", "fix: one"),
+                create_commit_info("4567def", "fix: two"),
+            ],
+        );
+        let opts = RenderOptions {
+            lang: Lang::Fr,
+            summary_line: true,
+            ..Default::default()
+        };
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+
+        assert!(result.contains("### Changements incompatibles\n"));
+        assert!(result.contains("### Nouvelles fonctionnalités\n"));
+        assert!(result.contains("### Corrections de bugs\n"));
+        assert!(result.contains(
+            "Cette version comprend 1 changement incompatible, 1 nouvelle fonctionnalité et 2 corrections."
+        ));
+        assert!(result.contains("[...tous les changements](https://github.com/user/repo/compare/"));
+        assert!(!result.contains("Bug fixes"));
+    }
+
+    #[test]
+    fn test_build_release_section_filter_empty_sections() {
+        let new_version = Version::new(1, 0, 1);
+        let last_version = Version::new(1, 0, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("def5678", "  ")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("0123abc", "fix: one")],
+        );
+
+        let opts = RenderOptions {
+            keep_empty_sections: true,
+            ..Default::default()
+        };
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.contains("### New features\n"));
+
+        let opts = RenderOptions {
+            group_security_separate: true,
+            ..Default::default()
+        };
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(!result.contains("### New features"));
+        assert!(!result.contains("def5678"));
+        assert!(result.contains("### Bug fixes\n"));
+        assert!(result.contains("### Security fixes\nNone.\n"));
+    }
+
+    #[test]
+    fn test_build_release_section_version_header_format() {
+        let new_version = Version::new(1, 2, 0);
+        let last_version = Version::new(1, 1, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("def5678", "feat: add thing")],
+        );
+
+        let opts = RenderOptions {
+            version_header_format: Some("## [{version}]({link}) - {date}".to_string()),
+            ..Default::default()
+        };
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        assert!(result.starts_with(
+            "## [1.2.0](https://github.com/user/repo/releases/tag/v1.2.0) - 2024-01-15\n"
+        ));
+
+        let opts = RenderOptions {
+            version_header_format: Some("## Release {tag} {link}".to_string()),
+            ..Default::default()
+        };
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.starts_with("## Release v1.2.0\n"));
+    }
+
+    #[test]
+    fn test_build_release_section_base_level() {
+        let new_version = Version::new(1, 1, 0);
+        let last_version = Version::new(1, 0, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("def5678", "feat: add thing")],
+        );
+        let opts = RenderOptions {
+            base_level: 1,
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.starts_with("### Version 1.1.0 (2024-01-15)\n"));
+        assert!(result.contains("\n#### New features\n"));
+        assert!(!result.contains("\n### New features"));
+    }
+
+    #[test]
+    fn test_build_release_section_reverts_after_bug_fixes() {
+        let new_version = Version::new(1, 0, 1);
+        let last_version = Version::new(1, 0, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut revert = create_commit_info("def5678", "add export");
+        revert.section = Some(REVERTS_SECTION.to_string());
+        let mut changed = create_commit_info("0a1b2c3", "tune cache");
+        changed.section = Some("Changed".to_string());
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![changed, create_commit_info("0123abc", "fix: one"), revert],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+        let fixes = result.find("### Bug fixes").unwrap();
+        let reverts = result.find("### Reverts\n* add export").unwrap();
+        let changed = result.find("### Changed").unwrap();
+        assert!(fixes < reverts && reverts < changed);
+    }
+
+    #[test]
+    fn test_build_release_section_commit_count() {
+        let new_version = Version::new(1, 2, 0);
+        let last_version = Version::new(1, 1, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("def5678", "feat: add thing")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("0123abc", "fix: one"),
+                create_commit_info("4567def", "fix: two"),
+            ],
+        );
+        grouped.insert(
+            CommitCategory::Ignore,
+            vec![create_commit_info("89abcde", "docs: typo")],
+        );
+        let opts = RenderOptions {
+            commit_count: true,
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.starts_with("## Version 1.2.0 (2024-01-15) · 3 commits\n"));
+
+        let result = confluence(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.starts_with("h2. Version 1.2.0 (2024-01-15) · 3 commits\n"));
+    }
+
+    #[test]
+    fn test_build_release_section_by_date() {
+        let new_version = Version::new(1, 0, 1);
+        let last_version = Version::new(1, 0, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 16).unwrap();
+        let mut older = create_commit_info("0123abc", "fix: older");
+        older.time = 1_705_312_800; // 2024-01-15 10:00 UTC
+        let mut newer = create_commit_info("4567def", "fix: newer");
+        newer.time = 1_705_399_200; // 2024-01-16 10:00 UTC
+        let mut same_day = create_commit_info("89abcde", "fix: same day");
+        same_day.time = 1_705_316_400; // 2024-01-15 11:00 UTC
+        let mut grouped = HashMap::new();
+        grouped.insert(CommitCategory::Patch, vec![older, newer, same_day]);
+        let opts = RenderOptions {
+            by_date: true,
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.contains(
+            "### Bug fixes\n\n\
+             #### 2024-01-16\n\
+             * fix: newer: `4567def`\n\n\
+             #### 2024-01-15\n\
+             * fix: older: `0123abc`\n\
+             * fix: same day: `89abcde`\n"
+        ));
+    }
+
+    #[test]
+    fn test_release_toml() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("abc1234", "fix null pointer (#12)"),
+                create_commit_info("def5678", "fix typo"),
+            ],
+        );
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("0a1b2c3", "add new API")],
+        );
+
+        let opts = RenderOptions::default();
+        let release = build_release_section(
+            &Version::new(1, 2, 0),
+            &Version::new(1, 1, 0),
+            date,
+            None,
+            &grouped,
+            &opts,
+        );
+        let text = release_toml(&release, &opts).unwrap();
+        let value: toml::Table = text.parse().unwrap();
+        let release = value["release"].as_table().unwrap();
+        assert_eq!(release["version"].as_str(), Some("1.2.0"));
+        assert_eq!(release["date"].as_str(), Some("2024-01-15"));
+
+        let categories = release["categories"].as_array().unwrap();
+        let names: Vec<&str> = categories
+            .iter()
+            .map(|cat| cat["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["New features", "Bug fixes"]);
+        let fixes = categories[1]["commits"].as_array().unwrap();
+        assert_eq!(fixes[0]["short_id"].as_str(), Some("abc1234"));
+        assert_eq!(fixes[0]["summary"].as_str(), Some("fix null pointer"));
+        assert_eq!(fixes[0]["issue_id"].as_str(), Some("12"));
+        assert!(fixes[1].get("issue_id").is_none());
+        assert!(release.get("url").is_none());
+    }
+
+    #[test]
+    fn test_render_json() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut revert = create_commit_info("def5678", "feat: export");
+        revert.section = Some(REVERTS_SECTION.to_string());
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("0a1b2c3", "drop the v1 API")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("abc1234", "fix null pointer (#12)"),
+                revert,
+            ],
+        );
+        let opts = RenderOptions::default();
+
+        let release = build_release_section(
+            &Version::new(2, 0, 0),
+            &Version::new(1, 4, 0),
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        let value: serde_json::Value = serde_json::from_str(&render_json(&release, &opts)).unwrap();
+        assert_eq!(value["version"], "2.0.0");
+        assert_eq!(value["date"], "2024-01-15");
+        assert_eq!(
+            value["url"],
+            "https://github.com/user/repo/releases/tag/v2.0.0"
+        );
+        assert_eq!(
+            value["compare_url"],
+            "https://github.com/user/repo/compare/v1.4.0...v2.0.0"
+        );
+        assert_eq!(value["breaking"][0]["title"], "drop the v1 API");
+        assert_eq!(value["breaking"][0]["category"], "major");
+        assert_eq!(value["features"], serde_json::json!([]));
+        let fix = &value["fixes"][0];
+        assert_eq!(fix["short_id"], "abc1234");
+        assert_eq!(fix["title"], "fix null pointer");
+        assert_eq!(fix["issues"], serde_json::json!(["#12"]));
+        assert_eq!(fix["url"], "https://github.com/user/repo/commit/abc1234");
+        assert_eq!(value["fixes"].as_array().unwrap().len(), 1);
+        assert_eq!(value["sections"][0]["name"], "Reverts");
+        assert_eq!(value["sections"][0]["commits"][0]["short_id"], "def5678");
+
+        // Without a remote there are no links.
+        let release = build_release_section(
+            &Version::new(2, 0, 0),
+            &Version::new(1, 4, 0),
+            date,
+            None,
+            &grouped,
+            &opts,
+        );
+        let value: serde_json::Value = serde_json::from_str(&render_json(&release, &opts)).unwrap();
+        assert!(value.get("url").is_none());
+        assert!(value["fixes"][0].get("url").is_none());
+    }
+
+    #[test]
+    fn test_wrap_bullets() {
+        let section = "### Bug fixes\n\
+                       * fix the parser when the input holds very long lines of text: \
+                       [`abc1234`](https://github.com/o/r/commit/abc1234) \
+                       ([#12](https://github.com/o/r/issues/12))\n\
+                       * short: `def5678`\n\n";
+        let wrapped = wrap_bullets(section, 40);
+        assert_eq!(
+            wrapped,
+            "### Bug fixes\n\
+             * fix the parser when the input holds\n\
+             \x20 very long lines of text:\n\
+             \x20 [`abc1234`](https://github.com/o/r/commit/abc1234)\n\
+             \x20 ([#12](https://github.com/o/r/issues/12))\n\
+             * short: `def5678`\n\n"
+        );
+
+        // Continuation lines of nested bullets align under their text, and link texts
+        // with spaces are not split.
+        let nested = "  * [Migration guide](https://example.com/guide) for the new config\n";
+        assert_eq!(
+            wrap_bullets(nested, 30),
+            "  * [Migration guide](https://example.com/guide)\n    for the new config\n"
+        );
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace() {
+        assert_eq!(
+            trim_trailing_whitespace("## Title  \n\n* a: `abc` \t\n\n"),
+            "## Title\n\n* a: `abc`\n\n"
+        );
+
+        // A commit without issue reference has an empty issue_ref.
+        let commits = vec![create_commit_info("abc123", "fix bug")];
+        let section = format_section(
+            "Bug fixes",
+            &formatted(&commits, &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
+        let trimmed = trim_trailing_whitespace(&section);
+        assert!(trimmed.lines().all(|line| line == line.trim_end()));
+        assert!(trimmed.contains("* fix bug: `abc123`\n"));
+        assert!(trimmed.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_nix_manifest() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let section = "## Version 1.2.3\n\n* use ''quotes'' and ${vars}\n";
+        assert_eq!(
+            nix_manifest(&Version::new(1, 2, 3), date, section),
+            "{\n  version = \"1.2.3\";\n  date = \"2024-01-15\";\n  \
+             changelog = \"## Version 1.2.3\\n\\n* use ''quotes'' and \\${vars}\\n\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_nix_string() {
+        assert_eq!(nix_string("quote '${HOME}'"), r#""quote '\${HOME}'""#);
+        assert_eq!(nix_string("''${HOME}"), r#""''\${HOME}""#);
+        assert_eq!(nix_string("say \"hi\"\\n"), r#""say \"hi\"\\n""#);
+        assert_eq!(nix_string("a\nb\tc"), r#""a\nb\tc""#);
+        // A lone `$` or `$$` does not start an interpolation.
+        assert_eq!(nix_string("costs $5, $$"), r#""costs $5, $$""#);
+        assert_eq!(nix_string("$${x}"), r#""$\${x}""#);
+    }
+
+    #[test]
+    fn test_build_release_section_compact() {
+        let new_version = Version::new(1, 1, 0);
+        let last_version = Version::new(1, 0, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("abc1234", "fix null pointer"),
+                create_commit_info("def5678", "fix typo."),
+            ],
+        );
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("0a1b2c3", "add new API")],
+        );
+        let opts = RenderOptions {
+            compact: true,
+            strip_trailing_period: true,
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert_eq!(
+            result,
+            "## Version 1.1.0 (2024-01-15)\n\n\
+             New features: add new API (#0a1b2c3). \
+             Bug fixes: fix null pointer (#abc1234), fix typo (#def5678).\n\n"
+        );
+        assert!(!result.contains("###"));
+    }
+
+    #[test]
+    fn test_build_release_section_all_categories() {
+        let new_version = Version::parse("1.5.0").unwrap();
+        let last_version = Version::parse("1.4.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let mut grouped = HashMap::new();
+
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("maj1", "breaking: change")],
+        );
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("min1", "feat: new feature")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("pat1", "fix: bug")],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains("### Breaking changes"));
+        assert!(result.contains("### New features"));
+        assert!(result.contains("### Bug fixes"));
+        assert!(result.contains("breaking: change"));
+        assert!(result.contains("feat: new feature"));
+        assert!(result.contains("fix: bug"));
+    }
+
+    #[test]
+    fn test_build_release_section_order_is_deterministic() {
+        let new_version = Version::parse("2.0.0").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let mut grouped = HashMap::new();
+
+        let many = |prefix: &str| -> Vec<CommitInfo> {
+            (0..200)
+                .map(|i| create_commit_info(&format!("{prefix}{i}"), &format!("{prefix} {i}")))
+                .collect()
+        };
+        grouped.insert(CommitCategory::Patch, many("pat"));
+        grouped.insert(CommitCategory::Minor, many("min"));
+        grouped.insert(CommitCategory::Major, many("maj"));
+
+        let opts = RenderOptions::default();
+        let first = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        for _ in 0..20 {
+            let again = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+            assert_eq!(first, again);
+        }
+
+        let breaking = first.find("### Breaking changes").unwrap();
+        let features = first.find("### New features").unwrap();
+        let fixes = first.find("### Bug fixes").unwrap();
+        assert!(breaking < features && features < fixes);
+        assert!(first.find("maj 199").unwrap() < features);
+        assert!(first.find("min 199").unwrap() < fixes);
+    }
+
+    #[test]
+    fn test_build_release_section_custom_sections() {
+        let new_version = Version::parse("1.1.0").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let mut grouped = HashMap::new();
+
+        let mut api = create_commit_info("api1", "add endpoint");
+        api.section = Some("api".to_string());
+        grouped.insert(CommitCategory::Minor, vec![api]);
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("pat1", "fix bug")],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains("### api\n* add endpoint: `api1`"));
+        assert!(!result.contains("### New features"));
+        assert!(result.find("### Bug fixes").unwrap() < result.find("### api").unwrap());
+    }
+
+    #[test]
+    fn test_build_release_section_tag_prefix_in_header() {
+        let new_version = Version::parse("1.2.3").unwrap();
+        let last_version = Version::parse("1.2.2").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("abc1234", "fix bug")],
+        );
+        let opts = RenderOptions {
+            tag_prefix_in_header: true,
+            ..Default::default()
+        };
+
+        let linked = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        assert!(linked.starts_with(
+            "## [v1.2.3](https://github.com/user/repo/releases/tag/v1.2.3) (2024-01-15)\n"
+        ));
+
+        let plain = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(plain.starts_with("## v1.2.3 (2024-01-15)\n"));
+        assert!(!plain.contains("Version"));
+    }
+
+    #[test]
+    fn test_tag_name() {
+        let version = Version::parse("1.2.3-rc.1").unwrap();
+        assert_eq!(tag_name(None, &version), "v1.2.3-rc.1");
+        assert_eq!(
+            tag_name(Some("release-{version}"), &version),
+            "release-1.2.3-rc.1"
+        );
+        assert_eq!(tag_name(Some("{major}.{minor}"), &version), "1.2");
+        assert_eq!(
+            tag_name(Some("r{major}_{minor}_{patch}"), &version),
+            "r1_2_3"
+        );
+    }
+
+    #[test]
+    fn test_build_release_section_tag_name_format() {
+        let new_version = Version::parse("1.3.0").unwrap();
+        let last_version = Version::parse("1.2.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("abc1234", "add thing")],
+        );
+        let opts = RenderOptions {
+            tag_name_format: Some("release-{version}".to_string()),
+            tag_prefix_in_header: true,
+            ..Default::default()
+        };
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        assert!(result.starts_with(
+            "## [release-1.3.0](https://github.com/user/repo/releases/tag/release-1.3.0)"
+        ));
+        assert!(result.contains("compare/release-1.2.0...release-1.3.0"));
+
+        let confluence = confluence(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        assert!(confluence.contains("releases/tag/release-1.3.0"));
+        assert!(confluence.contains("compare/release-1.2.0...release-1.3.0"));
+    }
+
+    #[test]
+    fn test_new_tag_links() {
+        let new_version = Version::new(1, 3, 0);
+        let last_version = Version::new(1, 2, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("abc1234", "feat: add thing")],
+        );
+        let release = |opts: &RenderOptions| {
+            build_release_section(
+                &new_version,
+                &last_version,
+                date,
+                Some(&remote),
+                &grouped,
+                opts,
+            )
+        };
+
+        let opts = RenderOptions::default();
+        assert_eq!(
+            release(&opts).new_tag_links(&opts),
+            [
+                "https://github.com/user/repo/releases/tag/v1.3.0",
+                "https://github.com/user/repo/compare/v1.2.0...v1.3.0",
+            ]
+        );
+
+        // A comparison of commit ids resolves before the tag is pushed.
+        let opts = RenderOptions {
+            compare_range: Some(("1111111".to_string(), "2222222".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            release(&opts).new_tag_links(&opts),
+            ["https://github.com/user/repo/releases/tag/v1.3.0"]
+        );
+    }
+
+    #[test]
+    fn test_build_release_section_known_issues() {
+        let new_version = Version::parse("1.1.0").unwrap();
+        let last_version = Version::parse("1.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+
+        let mut feature = create_commit_info("min1", "add export");
+        feature.body = "Exports to PDF.\n\nKnown-issue: large exports are slow\n".to_string();
+        let mut fix = create_commit_info("pat1", "fix import");
+        fix.body = "Known-issue: Windows paths with spaces fail to import".to_string();
+        grouped.insert(CommitCategory::Minor, vec![feature]);
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![fix, create_commit_info("pat2", "fix typo")],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains(
+            "\n### Known issues\n* large exports are slow\n* Windows paths with spaces fail to import\n"
+        ));
+        assert!(result.find("### Bug fixes").unwrap() < result.find("### Known issues").unwrap());
+        assert!(
+            result.find("### Known issues").unwrap() < result.find("[...full changes]").unwrap()
+        );
+
+        grouped.clear();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("pat2", "fix typo")],
+        );
+        let without = markdown(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+        assert!(!without.contains("Known issues"));
+    }
+
+    #[test]
+    fn test_build_release_section_date_format() {
+        let new_version = Version::parse("1.2.3").unwrap();
+        let last_version = Version::parse("1.2.2").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("abc1234", "fix bug")],
+        );
+        let opts = RenderOptions {
+            date_format: Some("%d %B %Y".to_string()),
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.starts_with("## Version 1.2.3 (15 January 2024)\n"));
+    }
+
+    #[test]
+    fn test_check_date_format() {
+        assert!(check_date_format("%Y-%m-%d").unwrap().is_empty());
+        assert!(check_date_format("%d %B %Y").unwrap().is_empty());
+        assert!(check_date_format("%v").unwrap().is_empty());
+        assert_eq!(check_date_format("%B %Y").unwrap(), vec!["day"]);
+        assert_eq!(
+            check_date_format("week %V").unwrap(),
+            vec!["year", "month", "day"]
+        );
+        assert!(check_date_format("%Q").is_err());
+        assert!(check_date_format("").is_err());
+        assert!(check_date_format("   ").is_err());
+    }
+
+    #[test]
+    fn test_build_release_section_initial_version() {
+        let new_version = Version::parse("1.0.0").unwrap();
+        let last_version = Version::parse("0.0.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("init1", "feat: initial release")],
+        );
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        // Should not include compare link for 0.0.0
+        assert!(!result.contains("compare/v0.0.0"));
+    }
+
+    #[test]
+    fn test_build_release_section_summary_line() {
+        let new_version = Version::parse("2.0.0").unwrap();
+        let last_version = Version::parse("1.4.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let mut grouped = HashMap::new();
+
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("maj1", "change")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("pat1", "bug"),
+                create_commit_info("pat2", "other bug"),
+            ],
+        );
+
+        let opts = RenderOptions {
+            summary_line: true,
+            ..Default::default()
+        };
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+
+        assert!(result.contains("\nThis release includes 1 breaking change and 2 fixes.\n"));
+        assert!(!result.contains("new feature"));
+
+        let without = markdown(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+        assert!(!without.contains("This release includes"));
+    }
+
+    #[test]
+    fn test_summary_line_pluralization() {
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("pat1", "bug")],
+        );
+        assert_eq!(
+            summary_line(&grouped, &RenderOptions::default()).unwrap(),
+            "This release includes 1 fix."
+        );
+
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![
+                create_commit_info("min1", "a"),
+                create_commit_info("min2", "b"),
+                create_commit_info("min3", "c"),
+            ],
+        );
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("maj1", "change")],
+        );
+        grouped
+            .get_mut(&CommitCategory::Patch)
+            .unwrap()
+            .push(create_commit_info("pat2", "bug 2"));
+        assert_eq!(
+            summary_line(&grouped, &RenderOptions::default()).unwrap(),
+            "This release includes 1 breaking change, 3 new features, and 2 fixes."
+        );
+
+        assert_eq!(
+            summary_line(&HashMap::new(), &RenderOptions::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_category_labels() {
+        let new_version = Version::new(2, 0, 0);
+        let last_version = Version::new(1, 4, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Major,
+            vec![create_commit_info("maj1", "change")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("pat1", "bug"),
+                create_commit_info("pat2", "other bug"),
+            ],
+        );
+        let opts = RenderOptions {
+            summary_line: true,
+            max_per_section: Some(0),
+            category_labels: HashMap::from([(
+                CommitCategory::Major,
+                CategoryLabel {
+                    emoji: Some("💥".to_string()),
+                    label: "Breaking".to_string(),
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let result = markdown(&new_version, &last_version, date, None, &grouped, &opts);
+        assert!(result.contains("\nThis release includes 💥 Breaking (1) and 2 fixes.\n"));
+        assert!(result.contains("\n### 💥 Breaking\n* change: `maj1`\n"));
+        assert!(result.contains("\n### Bug fixes\n"));
+        assert!(!result.contains("Breaking changes"));
+    }
+
+    #[test]
+    fn test_confluence() {
+        let new_version = Version::parse("1.2.0").unwrap();
+        let last_version = Version::parse("1.1.0").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("abc1234", "add login (#42)")],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![create_commit_info("def5678", "fix crash")],
+        );
+
+        let result = confluence(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &RenderOptions::default(),
+        );
+
+        assert!(result.starts_with(
+            "h2. [Version 1.2.0|https://github.com/user/repo/releases/tag/v1.2.0] (2024-01-15)\n"
+        ));
+        assert!(result.contains("\nh3. New features\n* add login: [abc1234|https://github.com/user/repo/commit/abc1234] ([#42|https://github.com/user/repo/issues/42])\n"));
+        assert!(result.contains(
+            "\nh3. Bug fixes\n* fix crash: [def5678|https://github.com/user/repo/commit/def5678]\n"
+        ));
+        assert!(result
+            .contains("[...full changes|https://github.com/user/repo/compare/v1.1.0...v1.2.0]"));
+        assert!(!result.contains("##"));
+
+        let plain = confluence(
+            &new_version,
+            &last_version,
+            date,
+            None,
+            &grouped,
+            &RenderOptions::default(),
+        );
+        assert!(plain.starts_with("h2. Version 1.2.0 (2024-01-15)\n"));
+        assert!(plain.contains("* add login: {{abc1234}} (#42)\n"));
+    }
+
+    #[test]
+    fn test_format_section_with_issue_references() {
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let commits = vec![
+            create_commit_info("abc123", "fix: bug (#42)"),
+            create_commit_info("def456", "feat: feature (#123)"),
+            create_commit_info("ghi789", "fix: another bug"),
+        ];
+
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&commits, &RenderOptions::default()),
+            Some(&remote),
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains("### Bug fixes"));
+        assert!(result.contains("fix: bug:"));
+        assert!(result.contains("([#42](https://github.com/user/repo/issues/42))"));
+        assert!(result.contains("([#123](https://github.com/user/repo/issues/123))"));
+        assert!(result.contains("fix: another bug:"));
+        assert!(!result.contains("(#"));
+    }
+
+    #[test]
+    fn test_format_section_migration_link() {
+        let mut commit = create_commit_info("abc123", "drop the v1 API");
+        commit.body = "Migration: https://example.com/upgrade-to-v2".to_string();
+        let plain = create_commit_info("def456", "rename the config file");
+        let commits = vec![commit, plain];
+        let opts = RenderOptions::default();
+
+        let result = format_section("Breaking changes", &formatted(&commits, &opts), None, &opts);
+        assert!(result.contains(
+            "* drop the v1 API: `abc123`\n  \
+             * [Migration guide](https://example.com/upgrade-to-v2)\n\
+             * rename the config file: `def456`\n"
+        ));
+
+        let result = format_section("Bug fixes", &formatted(&commits, &opts), None, &opts);
+        assert!(!result.contains("Migration guide"));
+    }
+
+    #[test]
+    fn test_audit_sections() {
+        let included = vec![
+            (
+                create_commit_info("abc1234", "add export"),
+                CommitCategory::Minor,
+            ),
+            (
+                create_commit_info("def5678", "fix crash"),
+                CommitCategory::Patch,
+            ),
+            (
+                create_commit_info("0a1b2c3", "bump deps"),
+                CommitCategory::Ignore,
+            ),
+        ];
+        let mut grouped = HashMap::new();
+        for (commit, cat) in &included {
+            if *cat != CommitCategory::Ignore {
+                grouped
+                    .entry(*cat)
+                    .or_insert_with(Vec::new)
+                    .push(commit.clone());
+            }
+        }
+        let audit = |grouped: &HashMap<CommitCategory, Vec<CommitInfo>>, opts: &RenderOptions| {
+            let release = build_release_section(
+                &Version::new(1, 1, 0),
+                &Version::new(1, 0, 0),
+                NaiveDate::default(),
+                None,
+                grouped,
+                opts,
+            );
+            audit_sections(&included, &release, opts)
+        };
+        let opts = RenderOptions::default();
+        assert!(audit(&grouped, &opts).is_empty());
+
+        // Leaving out a category on purpose is not a discrepancy.
+        let only = RenderOptions {
+            only: Some(vec![CommitCategory::Minor]),
+            ..Default::default()
+        };
+        assert!(audit(&grouped, &only).is_empty());
+
+        // Neither is hiding bullets beyond the limit, but they do not count as listed.
+        let mut long = grouped.clone();
+        long.get_mut(&CommitCategory::Patch)
+            .unwrap()
+            .push(create_commit_info("fedcba9", "fix typo"));
+        let mut included_long = included.clone();
+        included_long.push((
+            create_commit_info("fedcba9", "fix typo"),
+            CommitCategory::Patch,
+        ));
+        let limited = RenderOptions {
+            max_per_section: Some(1),
+            ..Default::default()
+        };
+        let release = build_release_section(
+            &Version::new(1, 1, 0),
+            &Version::new(1, 0, 0),
+            NaiveDate::default(),
+            None,
+            &long,
+            &limited,
+        );
+        assert!(audit_sections(&included_long, &release, &limited).is_empty());
+        assert!(!render_markdown(&release, &limited).contains("fix typo"));
+        // Only the commits actually beyond the limit are excused.
+        let mut dropped = release.clone();
+        dropped.fixes.truncate(1);
+        assert_eq!(
+            audit_sections(&included_long, &dropped, &limited),
+            [
+                "3 commits included, 2 listed",
+                "fedcba9 fix typo is missing"
+            ]
+        );
+
+        // A commit dropped by the grouping is reported.
+        let mut dropped = grouped.clone();
+        dropped.remove(&CommitCategory::Patch);
+        assert_eq!(
+            audit(&dropped, &opts),
+            [
+                "2 commits included, 1 listed",
+                "def5678 fix crash is missing"
+            ]
         );
 
-        let result = build_release_section(&new_version, &last_version, date, None, &grouped);
+        // So is a commit grouped twice.
+        let mut doubled = grouped.clone();
+        doubled
+            .get_mut(&CommitCategory::Minor)
+            .unwrap()
+            .push(included[0].0.clone());
+        assert_eq!(
+            audit(&doubled, &opts),
+            [
+                "2 commits included, 3 listed",
+                "abc1234 add export is listed 2 times"
+            ]
+        );
+    }
 
-        assert!(result.contains("### Breaking changes"));
-        assert!(result.contains("### New features"));
-        assert!(result.contains("### Bug fixes"));
-        assert!(result.contains("breaking: change"));
-        assert!(result.contains("feat: new feature"));
-        assert!(result.contains("fix: bug"));
+    #[test]
+    fn test_has_issue_ref() {
+        let syntax = IssueSyntax::default();
+        assert!(has_issue_ref(
+            &create_commit_info("abc123", "fix: bug (#12)"),
+            &syntax
+        ));
+        assert!(!has_issue_ref(
+            &create_commit_info("abc123", "fix: bug"),
+            &syntax
+        ));
+
+        let mut merged = create_commit_info("abc123", "fix: bug");
+        merged.pr_number = Some("34".to_string());
+        assert!(has_issue_ref(&merged, &syntax));
     }
 
     #[test]
-    fn test_build_release_section_initial_version() {
-        let new_version = Version::parse("1.0.0").unwrap();
-        let last_version = Version::parse("0.0.0").unwrap();
-        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
-        let remote = create_remote_info("https://github.com/user/repo/");
-        let mut grouped = HashMap::new();
+    fn test_custom_issue_id_pattern() {
+        let syntax = IssueSyntax::new(
+            Some(r"[A-Z][A-Z0-9]*-\d+"),
+            Some("https://tracker.example.com/browse/{id}".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            syntax.extract("fix: crash on login (#ABC-1)"),
+            (
+                "fix: crash on login".to_string(),
+                vec![IssueRef::issue("ABC-1")]
+            )
+        );
+        assert_eq!(
+            syntax.extract("fix: crash (#12)"),
+            ("fix: crash (#12)".to_string(), vec![])
+        );
 
-        grouped.insert(
-            CommitCategory::Minor,
-            vec![create_commit_info("init1", "feat: initial release")],
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let commits = vec![create_commit_info("abc123", "fix crash on login (#ABC-1)")];
+        let opts = RenderOptions {
+            issue_syntax: syntax,
+            ..Default::default()
+        };
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&commits, &opts),
+            Some(&remote),
+            &opts,
         );
+        assert!(result.contains(
+            "* fix crash on login: [`abc123`](https://github.com/user/repo/commit/abc123) \
+             ([#ABC-1](https://tracker.example.com/browse/ABC-1))\n"
+        ));
 
-        let result =
-            build_release_section(&new_version, &last_version, date, Some(&remote), &grouped);
+        // The template links references even without a remote.
+        let result = format_section("Bug fixes", &formatted(&commits, &opts), None, &opts);
+        assert!(result.contains("([#ABC-1](https://tracker.example.com/browse/ABC-1))"));
 
-        // Should not include compare link for 0.0.0
-        assert!(!result.contains("compare/v0.0.0"));
+        assert!(IssueSyntax::new(Some("[A-Z"), None).is_err());
+        // Very long numbers are still plain references.
+        let long = "9".repeat(40);
+        let (_, refs) = IssueSyntax::default().extract(&format!("fix: overflow (#{long})"));
+        assert_eq!(refs, [IssueRef::issue(long)]);
     }
 
     #[test]
-    fn test_format_section_with_issue_references() {
+    fn test_format_section_breaking_notes() {
+        let mut commit = create_commit_info("abc123", "reject empty tokens");
+        commit.body = "BREAKING CHANGE: clients sending an empty token get a 401.".to_string();
+        let commits = vec![commit];
+        let opts = RenderOptions {
+            breaking_notes: true,
+            ..Default::default()
+        };
+
+        let result = format_section("Breaking changes", &formatted(&commits, &opts), None, &opts);
+        assert!(result.contains(
+            "* reject empty tokens: `abc123`\n  \
+             * clients sending an empty token get a 401.\n"
+        ));
+
+        let result = format_section("Bug fixes", &formatted(&commits, &opts), None, &opts);
+        assert!(!result.contains("401"));
+        let result = format_section(
+            "Breaking changes",
+            &formatted(&commits, &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
+        assert!(!result.contains("401"));
+    }
+
+    #[test]
+    fn test_format_section_marks_closed_issues() {
         let remote = create_remote_info("https://github.com/user/repo/");
         let commits = vec![
             create_commit_info("abc123", "fix: bug (#42)"),
-            create_commit_info("def456", "feat: feature (#123)"),
-            create_commit_info("ghi789", "fix: another bug"),
+            create_commit_info("def456", "fix: other bug (#43)"),
         ];
+        let opts = RenderOptions {
+            closed_issue_ids: HashSet::from(["42".to_string()]),
+            ..Default::default()
+        };
 
-        let result = format_section("Bug fixes", &commits, Some(&remote));
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&commits, &opts),
+            Some(&remote),
+            &opts,
+        );
 
-        assert!(result.contains("### Bug fixes"));
-        assert!(result.contains("fix: bug:"));
-        assert!(result.contains("([#42](https://github.com/user/repo/issues/42))"));
-        assert!(result.contains("([#123](https://github.com/user/repo/issues/123))"));
-        assert!(result.contains("fix: another bug:"));
-        assert!(!result.contains("(#"));
+        assert!(result.contains("([#42](https://github.com/user/repo/issues/42) \u{2705})"));
+        assert!(result.contains("([#43](https://github.com/user/repo/issues/43))"));
     }
 
     #[test]
@@ -327,12 +3711,94 @@ mod tests {
         let remote = create_remote_info("https://github.com/user/repo/");
         let commits = vec![create_commit_info("abc123", "fix: bug (#99)")];
 
-        let result = format_section("Bug fixes", &commits, Some(&remote));
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&commits, &RenderOptions::default()),
+            Some(&remote),
+            &RenderOptions::default(),
+        );
 
         assert!(result.contains("fix: bug:"));
         assert!(result.contains("([#99](https://github.com/user/repo/issues/99))"));
     }
 
+    #[test]
+    fn test_format_section_merged_pr_reference() {
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut merged = create_commit_info("abc123", "part one");
+        merged.pr_number = Some("12".to_string());
+        let mut own_ref = create_commit_info("def456", "part two (#13)");
+        own_ref.pr_number = Some("12".to_string());
+
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&[merged, own_ref], &RenderOptions::default()),
+            Some(&remote),
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains("* part one: [`abc123`](https://github.com/user/repo/commit/abc123) ([#12](https://github.com/user/repo/issues/12))"));
+        assert!(result.contains("* part two: [`def456`](https://github.com/user/repo/commit/def456) ([#13](https://github.com/user/repo/issues/13))"));
+    }
+
+    #[test]
+    fn test_format_section_signature_badge() {
+        let mut signed = create_commit_info("abc123", "fix: signed change");
+        signed.has_signature = true;
+        let unsigned = create_commit_info("def456", "fix: unsigned change");
+
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&[signed, unsigned], &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
+        assert!(result.contains("* fix: signed change: `abc123` `has signature`\n"));
+        assert!(result.contains("* fix: unsigned change: `def456`\n"));
+    }
+
+    #[test]
+    fn test_format_section_diff_stats() {
+        let mut commit = create_commit_info("abc1234", "Fix bug");
+        commit.diff_stats = Some((15, 3));
+        let plain = create_commit_info("def5678", "Other fix");
+
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&[commit, plain], &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
+
+        assert!(result.contains("* Fix bug: `abc1234` *(+15 \u{2212}3)*\n"));
+        assert!(result.contains("* Other fix: `def5678`\n"));
+    }
+
+    #[test]
+    fn test_format_section_strip_trailing_period() {
+        let commits = vec![
+            create_commit_info("abc123", "add feature."),
+            create_commit_info("def456", "more..."),
+            create_commit_info("ghi789", "fix bug (#42)."),
+        ];
+        let opts = RenderOptions {
+            strip_trailing_period: true,
+            ..Default::default()
+        };
+
+        let result = format_section("Changes", &formatted(&commits, &opts), None, &opts);
+        assert!(result.contains("* add feature: `abc123`"));
+        assert!(result.contains("* more...: `def456`"));
+
+        let untouched = format_section(
+            "Changes",
+            &formatted(&commits, &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
+        assert!(untouched.contains("* add feature.: `abc123`"));
+    }
+
     #[test]
     fn test_format_section_no_remote() {
         let commits = vec![
@@ -340,7 +3806,12 @@ mod tests {
             create_commit_info("def456", "feat: feature"),
         ];
 
-        let result = format_section("Changes", &commits, None);
+        let result = format_section(
+            "Changes",
+            &formatted(&commits, &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
 
         assert!(result.contains("### Changes"));
         assert!(result.contains("fix: bug:"));
@@ -349,13 +3820,164 @@ mod tests {
         assert!(result.contains("feat: feature:"));
     }
 
+    #[test]
+    fn test_format_section_table() {
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let commits = vec![
+            create_commit_info("abc123", "fix: handle a | b (#42)"),
+            create_commit_info("def456", "fix: plain"),
+        ];
+        let opts = RenderOptions::default();
+
+        let result = format_section_table(
+            "Bug fixes",
+            &formatted(&commits, &opts),
+            Some(&remote),
+            &opts,
+        );
+        assert_eq!(
+            result,
+            "\n### Bug fixes\n\
+             | SHA | Category | Summary | Issue |\n\
+             | :-- | :-: | :-- | :-- |\n\
+             | [`abc123`](https://github.com/user/repo/commit/abc123) | patch | fix: handle a \\| b | [#42](https://github.com/user/repo/issues/42) |\n\
+             | [`def456`](https://github.com/user/repo/commit/def456) | patch | fix: plain |  |\n\n"
+        );
+    }
+
+    #[test]
+    fn test_format_section_table_mixed_categories() {
+        // A custom section lists commits of several categories, each with its own.
+        let mut feature = create_commit_info("abc123", "add search");
+        feature.section = Some("Search".to_string());
+        let mut fix = create_commit_info("def456", "fix search ranking");
+        fix.section = Some("Search".to_string());
+        let mut grouped = HashMap::new();
+        grouped.insert(CommitCategory::Minor, vec![feature]);
+        grouped.insert(CommitCategory::Patch, vec![fix]);
+        let opts = RenderOptions {
+            list_format: ListFormat::Table,
+            ..Default::default()
+        };
+
+        let result = markdown(
+            &Version::new(1, 1, 0),
+            &Version::new(1, 0, 0),
+            NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            None,
+            &grouped,
+            &opts,
+        );
+        assert!(result.contains(
+            "### Search\n\
+             | SHA | Category | Summary | Issue |\n\
+             | :-- | :-: | :-- | :-- |\n\
+             | `abc123` | minor | add search |  |\n\
+             | `def456` | patch | fix search ranking |  |\n"
+        ));
+    }
+
+    #[test]
+    fn test_extract_refs() {
+        assert_eq!(
+            IssueSyntax::default().extract("fix bug (#42)"),
+            ("fix bug".to_string(), vec![IssueRef::issue("42")])
+        );
+        assert_eq!(
+            IssueSyntax::default().extract("fix bug #7"),
+            ("fix bug".to_string(), vec![IssueRef::issue("7")])
+        );
+        assert_eq!(
+            IssueSyntax::default().extract("fix: thing (#12) (!34)"),
+            (
+                "fix: thing".to_string(),
+                vec![
+                    IssueRef::issue("12"),
+                    IssueRef {
+                        sigil: '!',
+                        id: "34".to_string()
+                    }
+                ]
+            )
+        );
+        assert_eq!(
+            IssueSyntax::default().extract("fix bug"),
+            ("fix bug".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_format_section_issue_and_merge_request() {
+        let remote = create_remote_info("https://gitlab.com/group/project/");
+        let commits = vec![create_commit_info("abc123", "fix: thing (#12) (!34)")];
+
+        let result = format_section(
+            "Bug fixes",
+            &formatted(&commits, &RenderOptions::default()),
+            Some(&remote),
+            &RenderOptions::default(),
+        );
+        assert!(result.contains(
+            "* fix: thing: [`abc123`](https://gitlab.com/group/project/-/commit/abc123) ([#12](https://gitlab.com/group/project/-/issues/12)) ([!34](https://gitlab.com/group/project/-/merge_requests/34))\n"
+        ));
+
+        let plain = format_section(
+            "Bug fixes",
+            &formatted(&commits, &RenderOptions::default()),
+            None,
+            &RenderOptions::default(),
+        );
+        assert!(plain.contains("* fix: thing: `abc123` (#12) (!34)\n"));
+    }
+
+    #[test]
+    fn test_find_duplicate_issue_refs() {
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![
+                create_commit_info("aaa1111", "add thing (#42)"),
+                create_commit_info("bbb2222", "add other (#7)"),
+            ],
+        );
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![
+                create_commit_info("ccc3333", "fix thing #42"),
+                create_commit_info("ddd4444", "fix other (#100)"),
+                create_commit_info("eee5555", "fix more (#7)"),
+                create_commit_info("fff6666", "no reference"),
+            ],
+        );
+
+        let duplicates = find_duplicate_issue_refs(&grouped, &IssueSyntax::default());
+        assert_eq!(
+            duplicates,
+            vec![
+                (
+                    "7".to_string(),
+                    vec!["bbb2222".to_string(), "eee5555".to_string()]
+                ),
+                (
+                    "42".to_string(),
+                    vec!["aaa1111".to_string(), "ccc3333".to_string()]
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_write_changelog_new_file() {
         let temp_dir = TempDir::new().unwrap();
         let file_path = temp_dir.path().join("CHANGELOG.md");
         let section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* fix: bug\n\n";
 
-        write_changelog(file_path.to_str().unwrap(), section).unwrap();
+        write_changelog(
+            file_path.to_str().unwrap(),
+            section,
+            &WriteOptions::default(),
+        )
+        .unwrap();
 
         let content = fs::read_to_string(&file_path).unwrap();
         assert!(content.contains(section));
@@ -370,7 +3992,12 @@ mod tests {
         fs::write(&file_path, existing).unwrap();
 
         let new_section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* new fix\n\n";
-        write_changelog(file_path.to_str().unwrap(), new_section).unwrap();
+        write_changelog(
+            file_path.to_str().unwrap(),
+            new_section,
+            &WriteOptions::default(),
+        )
+        .unwrap();
 
         let content = fs::read_to_string(&file_path).unwrap();
         assert!(content.starts_with(new_section.trim()));
@@ -384,10 +4011,377 @@ mod tests {
         fs::write(&file_path, "   \n\n  ").unwrap(); // Whitespace only
 
         let section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* fix\n\n";
-        write_changelog(file_path.to_str().unwrap(), section).unwrap();
+        write_changelog(
+            file_path.to_str().unwrap(),
+            section,
+            &WriteOptions::default(),
+        )
+        .unwrap();
 
         let content = fs::read_to_string(&file_path).unwrap();
         assert!(content.contains(section));
         assert!(content.contains("--- Generated by changelogger"));
     }
+
+    #[test]
+    fn test_write_changelog_encoding_declaration() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let opts = WriteOptions {
+            encoding_declaration: true,
+            ..Default::default()
+        };
+
+        let section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* fix\n\n";
+        write_changelog(file_path.to_str().unwrap(), section, &opts).unwrap();
+
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(content.lines().next(), Some(ENCODING_DECLARATION));
+        assert!(content.contains(section));
+
+        // Only newly created files get the declaration, and it stays at the top.
+        let next = "## Version 1.0.1 (2024-01-02)\n\n### Bug fixes\n* fix\n\n";
+        write_changelog(file_path.to_str().unwrap(), next, &opts).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(content.lines().next(), Some(ENCODING_DECLARATION));
+        assert!(split_header(&content).1.starts_with(next.trim()));
+        assert_eq!(content.matches(ENCODING_DECLARATION).count(), 1);
+    }
+
+    #[test]
+    fn test_write_changelog_format_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let path = file_path.to_str().unwrap();
+
+        let first = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* fix\n\n";
+        write_changelog(path, first, &WriteOptions::default()).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(
+            content.lines().next(),
+            Some("[//]: # (changelogger format version: 1.0)")
+        );
+        assert_eq!(format_version(&content), Some(FORMAT_VERSION));
+
+        let next = "## Version 1.0.1 (2024-01-02)\n\n### Bug fixes\n* fix\n\n";
+        write_changelog(path, next, &WriteOptions::default()).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(content.starts_with(&format!(
+            "[//]: # (changelogger format version: 1.0)\n\n{next}\n"
+        )));
+        assert_eq!(content.matches(FORMAT_VERSION_PREFIX).count(), 1);
+
+        assert_eq!(format_version("# Changelog\n\n## 1.0.0\n"), None);
+    }
+
+    #[test]
+    fn test_write_changelog_without_format_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.txt");
+        let path = file_path.to_str().unwrap();
+        let opts = WriteOptions {
+            no_format_version: true,
+            ..Default::default()
+        };
+
+        let first = "h2. Version 1.0.0 (2024-01-01)\n\nh3. Bug fixes\n* fix\n\n";
+        write_changelog(path, first, &opts).unwrap();
+        let next = "h2. Version 1.0.1 (2024-01-02)\n\nh3. Bug fixes\n* fix\n\n";
+        write_changelog(path, next, &opts).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(!content.contains("[//]"));
+        assert!(content.starts_with(next));
+        assert!(is_generated(&content));
+        assert!(!is_generated("# Changelog\n\n## 1.0.0\n"));
+    }
+
+    #[test]
+    fn test_write_changelog_stamps_older_changelog() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let path = file_path.to_str().unwrap();
+        let first = "## Version 1.0.0 (2024-01-01)\n\n* fix\n\n";
+        fs::write(&file_path, format!("{first}\n{GENERATED_FOOTER}\n")).unwrap();
+        assert!(is_generated(&fs::read_to_string(&file_path).unwrap()));
+
+        let next = "## Version 1.0.1 (2024-01-02)\n\n* fix\n\n";
+        write_changelog(path, next, &WriteOptions::default()).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(content.starts_with(&format!(
+            "[//]: # (changelogger format version: 1.0)\n\n{next}"
+        )));
+        assert_eq!(format_version(&content), Some(FORMAT_VERSION));
+
+        // A changelog from another tool is left without the comment.
+        fs::write(&file_path, first).unwrap();
+        write_changelog(path, next, &WriteOptions::default()).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(format_version(&content), None);
+    }
+
+    #[test]
+    fn test_write_changelog_replaces_unreleased_section() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let released = "## Version 0.9.0 (2023-12-01)\n\n### Bug fixes\n* old fix\n\n";
+        let opts = WriteOptions {
+            replace_heading: Some(unreleased_heading("Upcoming", 0)),
+            ..Default::default()
+        };
+
+        write_changelog(file_path.to_str().unwrap(), released, &opts).unwrap();
+        let first = "## [Upcoming]\n\n### Bug fixes\n* first fix\n\n";
+        write_changelog(file_path.to_str().unwrap(), first, &opts).unwrap();
+        let second = "## [Upcoming]\n\n### Bug fixes\n* first fix\n* second fix\n\n";
+        write_changelog(file_path.to_str().unwrap(), second, &opts).unwrap();
+
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(split_header(&content).1.starts_with(second.trim()));
+        assert_eq!(content.matches("## [Upcoming]").count(), 1);
+        assert!(content.contains(released.trim()));
+        assert_eq!(content.matches(GENERATED_FOOTER).count(), 1);
+    }
+
+    #[test]
+    fn test_build_release_section_unreleased() {
+        let new_version = Version::new(1, 1, 0);
+        let last_version = Version::new(1, 0, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let remote = create_remote_info("https://github.com/user/repo/");
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![create_commit_info("def5678", "feat: add thing")],
+        );
+        let opts = RenderOptions {
+            unreleased: Some("Next Release".to_string()),
+            ..Default::default()
+        };
+
+        let result = markdown(
+            &new_version,
+            &last_version,
+            date,
+            Some(&remote),
+            &grouped,
+            &opts,
+        );
+        assert!(result.starts_with("## [Next Release]\n"));
+        assert!(result.contains("compare/v1.0.0...HEAD"));
+        assert!(!result.contains("1.1.0"));
+    }
+
+    #[test]
+    fn test_write_changelog_append() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let path = file_path.to_str().unwrap();
+        let opts = WriteOptions {
+            append: true,
+            ..Default::default()
+        };
+
+        let first = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* first fix\n\n";
+        let second = "## Version 1.0.1 (2024-01-02)\n\n### Bug fixes\n* second fix\n\n";
+        let third = "## Version 1.0.2 (2024-01-03)\n\n### Bug fixes\n* third fix\n\n";
+        for section in [first, second, third] {
+            write_changelog(path, section, &opts).unwrap();
+        }
+
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(split_header(&content).1.starts_with(first.trim()));
+        let positions: Vec<usize> = ["1.0.0", "1.0.1", "1.0.2"]
+            .iter()
+            .map(|version| content.find(version).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(content.matches(GENERATED_FOOTER).count(), 1);
+        assert!(content.ends_with(&format!("* third fix\n\n\n{GENERATED_FOOTER}\n")));
+
+        // Without a footer, the section simply goes at the end.
+        fs::write(&file_path, first).unwrap();
+        write_changelog(path, second, &opts).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(content, format!("{}\n\n{second}", first.trim_end()));
+    }
+
+    #[test]
+    fn test_write_changelog_footer_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let path = file_path.to_str().unwrap();
+        let footer = "Follows the conventions.";
+        let opts = WriteOptions {
+            footer: Some(footer.to_string()),
+            ..Default::default()
+        };
+
+        let first = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* first fix\n\n";
+        let second = "## Version 1.0.1 (2024-01-02)\n\n### Bug fixes\n* second fix\n\n";
+        write_changelog(path, first, &opts).unwrap();
+        write_changelog(path, second, &opts).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(content.matches(footer).count(), 1);
+        assert!(content.ends_with(&format!("* first fix\n\n{footer}\n\n{GENERATED_FOOTER}\n")));
+        assert!(content.find("1.0.1").unwrap() < content.find("1.0.0").unwrap());
+
+        // Appended releases go before the closing text.
+        let append = WriteOptions {
+            append: true,
+            ..opts.clone()
+        };
+        let third = "## Version 1.0.2 (2024-01-03)\n\n### Bug fixes\n* third fix\n\n";
+        write_changelog(path, third, &append).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(content.matches(footer).count(), 1);
+        assert!(content.ends_with(&format!("* third fix\n\n{footer}\n\n{GENERATED_FOOTER}\n")));
+
+        // Replacing a section keeps the closing text, also without a generated footer.
+        fs::write(
+            &file_path,
+            format!("## [Unreleased]\n\n* old\n\n## Version 1.0.0\n\n* first\n\n{footer}\n"),
+        )
+        .unwrap();
+        let replace = WriteOptions {
+            replace_heading: Some("## [Unreleased]".to_string()),
+            ..opts.clone()
+        };
+        write_changelog(path, "## [Unreleased]\n\n* new\n\n", &replace).unwrap();
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(content.starts_with("## [Unreleased]\n\n* new\n"));
+        assert!(!content.contains("* old"));
+        assert!(content.ends_with(&format!("* first\n\n{footer}\n")));
+    }
+
+    #[test]
+    fn test_latest_release_heading() {
+        let content = "## [1.3.0](https://example.com/releases/tag/v1.3.0) (2024-02-01)\n\n\
+                       ### Bug fixes\n* fix 2.0.0 parsing\n\n\
+                       ## Version 1.10.0 (2024-01-01)\n\n\
+                       ## v1.2.0-rc.1 (2023-12-01)\n";
+        assert_eq!(
+            latest_release_heading(content),
+            Some((
+                Version::new(1, 10, 0),
+                "## Version 1.10.0 (2024-01-01)".to_string()
+            ))
+        );
+        assert_eq!(latest_release_heading("## [Unreleased]\n* thing\n"), None);
+
+        let content =
+            "h2. [Version 1.3.0|https://example.com/releases/tag/v1.3.0] (2024-02-01)\n\n\
+                       h3. Bug fixes\n* fix 2.0.0 parsing\n\n\
+                       h2. Version 1.2.0 (2024-01-01)\n";
+        assert_eq!(
+            latest_release_heading(content).map(|(version, _)| version),
+            Some(Version::new(1, 3, 0))
+        );
+    }
+
+    #[test]
+    fn test_write_changelog_consolidates_same_release() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let released = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* old fix\n\n";
+        let first = "## Version 1.0.1 (2024-01-02)\n\n### Bug fixes\n* first fix\n\n";
+        let path = file_path.to_str().unwrap();
+        write_changelog(path, released, &WriteOptions::default()).unwrap();
+        write_changelog(path, first, &WriteOptions::default()).unwrap();
+
+        let existing = fs::read_to_string(&file_path).unwrap();
+        let (version, heading) = latest_release_heading(&existing).unwrap();
+        assert_eq!(version, Version::new(1, 0, 1));
+        let second =
+            "## Version 1.0.1 (2024-01-03)\n\n### Bug fixes\n* first fix\n* second fix\n\n";
+        let opts = WriteOptions {
+            replace_heading: Some(heading),
+            ..Default::default()
+        };
+        write_changelog(path, second, &opts).unwrap();
+
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert!(split_header(&content).1.starts_with(second.trim()));
+        assert_eq!(content.matches("1.0.1").count(), 1);
+        assert!(content.contains(released.trim()));
+    }
+
+    #[test]
+    fn test_print_changelog_merges_without_writing() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let existing = "## Version 0.9.0 (2023-12-01)\n\n### Bug fixes\n* old fix\n\n";
+        fs::write(&file_path, existing).unwrap();
+
+        let new_section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* new fix\n\n";
+        let mut out = Vec::new();
+        print_changelog(
+            &mut out,
+            file_path.to_str().unwrap(),
+            new_section,
+            &WriteOptions::default(),
+        )
+        .unwrap();
+
+        let printed = String::from_utf8(out).unwrap();
+        assert_eq!(printed, format!("{new_section}\n\n{existing}"));
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), existing);
+    }
+
+    #[test]
+    fn test_print_changelog_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+
+        let section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* fix\n\n";
+        let mut out = Vec::new();
+        print_changelog(
+            &mut out,
+            file_path.to_str().unwrap(),
+            section,
+            &WriteOptions::default(),
+        )
+        .unwrap();
+
+        let printed = String::from_utf8(out).unwrap();
+        assert!(split_header(&printed).1.starts_with(section));
+        assert!(printed.contains("--- Generated by changelogger"));
+        assert!(!file_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_print_changelog_to_fd() {
+        use std::io::Read;
+        use std::os::unix::io::IntoRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("CHANGELOG.md");
+        let (writer, mut reader) = UnixStream::pair().unwrap();
+
+        let section = "## Version 1.0.0 (2024-01-01)\n\n### Bug fixes\n* fix\n\n";
+        let mut out = open_output_fd(writer.into_raw_fd()).unwrap();
+        print_changelog(
+            &mut out,
+            file_path.to_str().unwrap(),
+            section,
+            &WriteOptions::default(),
+        )
+        .unwrap();
+        drop(out);
+
+        let mut received = String::new();
+        reader.read_to_string(&mut received).unwrap();
+        assert!(split_header(&received).1.starts_with(section));
+        assert!(received.contains("--- Generated by changelogger"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_output_fd_closed() {
+        let err = open_output_fd(987_654).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("File descriptor 987654 is not open"));
+    }
 }
@@ -0,0 +1,98 @@
+//! Saving and restoring interactive classification progress.
+//!
+//! A checkpoint is a JSON object mapping commit ids to the category chosen for them,
+//! e.g. `{"4f1c…": "minor"}`, so an interrupted session can resume where it stopped.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::classify::CommitCategory;
+
+/// Categories chosen interactively, keyed by full commit id.
+pub type Checkpoint = BTreeMap<String, CommitCategory>;
+
+/// Loads the decisions saved in a checkpoint file.
+///
+/// # Arguments
+///
+/// * `path` - The checkpoint file
+///
+/// # Returns
+///
+/// The saved decisions, or an empty checkpoint if the file doesn't exist yet.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a valid checkpoint.
+pub fn load_checkpoint(path: &Path) -> Result<Checkpoint> {
+    if !path.exists() {
+        return Ok(Checkpoint::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Could not read checkpoint {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Could not parse checkpoint {}", path.display()))
+}
+
+/// Saves the decisions made so far, replacing the checkpoint file.
+///
+/// The decisions are written to a temporary file next to it, which is then renamed over
+/// it, so that an interruption never leaves a truncated checkpoint behind.
+///
+/// # Arguments
+///
+/// * `path` - The checkpoint file
+/// * `checkpoint` - The decisions to save
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save_checkpoint(path: &Path, checkpoint: &Checkpoint) -> Result<()> {
+    let content = serde_json::to_string_pretty(checkpoint)?;
+    let temp = temp_path(path);
+    fs::write(&temp, content + "\n")
+        .with_context(|| format!("Could not write checkpoint {}", temp.display()))?;
+    fs::rename(&temp, path)
+        .with_context(|| format!("Could not write checkpoint {}", path.display()))
+}
+
+/// Returns the temporary file a checkpoint is written to before replacing `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        assert!(load_checkpoint(&path).unwrap().is_empty());
+
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.insert("abc".to_string(), CommitCategory::Minor);
+        checkpoint.insert("def".to_string(), CommitCategory::Ignore);
+        save_checkpoint(&path, &checkpoint).unwrap();
+
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"abc\": \"minor\""));
+        assert_eq!(load_checkpoint(&path).unwrap(), checkpoint);
+        assert!(!temp_path(&path).exists());
+
+        checkpoint.remove("def");
+        save_checkpoint(&path, &checkpoint).unwrap();
+        assert_eq!(load_checkpoint(&path).unwrap(), checkpoint);
+
+        fs::write(&path, "{\"abc\": \"huge\"}").unwrap();
+        assert!(load_checkpoint(&path).is_err());
+    }
+}
@@ -3,8 +3,12 @@
 //! This module provides functions to automatically classify commits into categories
 //! (Major, Minor, Patch, Ignore) based on commit message conventions and patterns.
 
+use std::collections::HashMap;
+
+use clap::ValueEnum;
 use regex::Regex;
 use semver::Version;
+use serde::{Deserialize, Serialize};
 
 use crate::git::CommitInfo;
 
@@ -15,7 +19,8 @@ use crate::git::CommitInfo;
 /// - `Minor`: New features that require a minor version bump
 /// - `Patch`: Bug fixes and small changes that require a patch version bump
 /// - `Ignore`: Commits that should not appear in the changelog (docs, style, etc.)
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CommitCategory {
     Major,
     Minor,
@@ -24,123 +29,577 @@ pub enum CommitCategory {
     Ignore,
 }
 
+impl CommitCategory {
+    /// The lowercase name of the category, as accepted on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            CommitCategory::Major => "major",
+            CommitCategory::Minor => "minor",
+            CommitCategory::Patch => "patch",
+            CommitCategory::Ignore => "ignore",
+        }
+    }
+}
+
 /// Checks if a commit message is a release message.
 ///
-/// Release messages follow the format "-> v1.2.3" or "-> 1.2.3".
+/// Release messages follow the format "-> <tag>", e.g. "-> v1.2.3", or "-> 1.2.3".
 /// These commits are typically used to mark releases and should be ignored.
 ///
 /// # Arguments
 ///
 /// * `subject` - The commit message subject line
+/// * `tag_format` - The template of the release tag names, with `{version}`, `{major}`,
+///   `{minor}` and `{patch}` placeholders, or `None` for `v{version}`
 ///
 /// # Returns
 ///
 /// Returns `Some(Version)` if the message is a release message, or `None` otherwise.
-pub fn is_release_message(subject: &str) -> Option<Version> {
-    if let Some(rest) = subject.strip_prefix("-> ") {
-        if let Ok(v) = Version::parse(rest.trim_start_matches('v')) {
-            return Some(v);
+pub fn is_release_message(subject: &str, tag_format: Option<&str>) -> Option<Version> {
+    let rest = subject.strip_prefix("-> ")?;
+    if let Ok(version) = Version::parse(rest) {
+        return Some(version);
+    }
+    let format = tag_format.unwrap_or("v{version}");
+    let pattern = regex::escape(format)
+        .replace(r"\{version\}", r"(?P<version>\S+)")
+        .replace(r"\{major\}", r"(?P<major>\d+)")
+        .replace(r"\{minor\}", r"(?P<minor>\d+)")
+        .replace(r"\{patch\}", r"(?P<patch>\d+)");
+    let caps = Regex::new(&format!("^{pattern}$")).ok()?.captures(rest)?;
+    if let Some(version) = caps.name("version") {
+        return Version::parse(version.as_str()).ok();
+    }
+    let part = |name: &str| caps.name(name)?.as_str().parse().ok();
+    Some(Version::new(part("major")?, part("minor")?, part("patch")?))
+}
+
+/// A table of commit message prefixes and the category each one maps to.
+///
+/// The default set contains the English conventional commit prefixes. Sets for other
+/// languages extend it so that alternative-language prefixes (e.g. "corrige") are
+/// recognized alongside the English ones.
+#[derive(Debug, Clone)]
+pub struct KeywordSet {
+    /// Lowercase prefixes and their categories.
+    entries: Vec<(String, CommitCategory)>,
+}
+
+impl KeywordSet {
+    /// Creates a keyword set from `(prefix, category)` pairs.
+    pub fn new<'a>(entries: impl IntoIterator<Item = (&'a str, CommitCategory)>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|(prefix, cat)| (prefix.to_lowercase(), cat))
+                .collect(),
         }
     }
-    None
+
+    /// The built-in English conventional commit prefixes.
+    pub fn english() -> Self {
+        use CommitCategory::*;
+        Self::new([
+            ("docs", Ignore),
+            ("doc", Ignore),
+            ("style", Ignore),
+            ("chore", Ignore),
+            ("test", Ignore),
+            ("tweak", Patch),
+            ("tweaks", Patch),
+            ("fix", Patch),
+            ("fixes", Patch),
+            ("perf", Patch),
+            ("refactor", Patch),
+            ("patch", Patch),
+            ("feat", Minor),
+            ("minor", Minor),
+            ("breaking", Major),
+            ("major", Major),
+        ])
+    }
+
+    /// French prefixes, on top of the English ones.
+    pub fn french() -> Self {
+        use CommitCategory::*;
+        let mut set = Self::english();
+        set.extend(Self::new([
+            ("documentation", Ignore),
+            ("tâche", Ignore),
+            ("tache", Ignore),
+            ("corrige", Patch),
+            ("correction", Patch),
+            ("correctif", Patch),
+            ("fonctionnalité", Minor),
+            ("fonctionnalite", Minor),
+            ("nouveauté", Minor),
+            ("cassant", Major),
+            ("rupture", Major),
+        ]));
+        set
+    }
+
+    /// Returns the keyword set for a locale (e.g. "en", "fr"), if one is available.
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        match locale.to_lowercase().as_str() {
+            "en" => Some(Self::english()),
+            "fr" => Some(Self::french()),
+            _ => None,
+        }
+    }
+
+    /// Adds the entries of `other`, which take precedence over existing ones.
+    pub fn extend(&mut self, other: KeywordSet) {
+        self.entries
+            .retain(|(prefix, _)| !other.entries.iter().any(|(p, _)| p == prefix));
+        self.entries.extend(other.entries);
+    }
+
+    /// Looks up the category of a prefix (case-insensitive).
+    pub fn lookup(&self, prefix: &str) -> Option<CommitCategory> {
+        let prefix = prefix.to_lowercase();
+        self.entries
+            .iter()
+            .find(|(p, _)| *p == prefix)
+            .map(|(_, cat)| *cat)
+    }
+}
+
+impl Default for KeywordSet {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+/// Options controlling automatic commit classification.
+#[derive(Debug, Clone, Default)]
+pub struct ClassifyOptions {
+    /// Prefixes recognized as conventional commit types.
+    pub keywords: KeywordSet,
+    /// Route commits with an unknown, scope-like prefix (e.g. "api:") to a section
+    /// named after it. The version bump is still left to the user or the default.
+    pub scope_as_section: bool,
+    /// Ignore merge commits, leaving their changes to the merged commits themselves.
+    pub ignore_merges: bool,
+    /// Route commits with a `Changelog: <section>` trailer (Added, Changed, Deprecated,
+    /// Removed, Fixed, Security) to that Keep a Changelog section, overriding the prefix.
+    pub categories_from_labels: bool,
+    /// Route reverts (`Revert "feat: X"`) to a [`REVERTS_SECTION`] with this version bump,
+    /// instead of classifying them by the reverted subject.
+    pub reverts_section: Option<CommitCategory>,
+    /// Classify release commits ("-> v1.2.3") like any other commit instead of ignoring
+    /// them, so that releases can be listed in the changelog.
+    pub keep_release_commits: bool,
+    /// Template of the release tag names that release commits refer to, as in
+    /// [`is_release_message`].
+    pub tag_name_format: Option<String>,
+    /// Classify commits with an unrecognized prefix (e.g. "ci:") as patches instead of
+    /// leaving them to the user.
+    pub lax: bool,
+    /// Classify `fixup!`/`squash!` commits by the subject they refer to instead of
+    /// ignoring them.
+    pub fold_fixups: bool,
+    /// Split combined types such as "feat/fix:" on `/` and classify by the highest-impact
+    /// recognized one.
+    pub split_slash_types: bool,
+    /// Default categories of scopes (lowercase), for commits whose type is not recognized,
+    /// e.g. "deps" → ignore for "build(deps): bump serde".
+    pub scope_categories: HashMap<String, CommitCategory>,
+}
+
+/// Extracts the subject a `git commit --fixup`/`--squash` commit refers to.
+///
+/// # Arguments
+///
+/// * `summary` - The commit summary, e.g. "fixup! feat: add thing"
+///
+/// # Returns
+///
+/// The referenced subject, or `None` if the commit is not a fixup.
+pub fn fixup_target(summary: &str) -> Option<&str> {
+    let mut target = summary;
+    while let Some(rest) = ["fixup! ", "squash! ", "amend! "]
+        .iter()
+        .find_map(|prefix| target.strip_prefix(prefix))
+    {
+        target = rest.trim_start();
+    }
+    (target.len() < summary.len()).then_some(target)
+}
+
+/// Name of the custom section that reverts are routed to.
+pub const REVERTS_SECTION: &str = "Reverts";
+
+/// Extracts the reverted subject from a revert summary, as written by `git revert`.
+///
+/// # Arguments
+///
+/// * `summary` - The commit summary, e.g. `Revert "feat: add thing"`
+///
+/// # Returns
+///
+/// The subject of the reverted commit, or `None` if the commit is not a revert.
+pub fn reverted_subject(summary: &str) -> Option<&str> {
+    summary
+        .strip_prefix("Revert \"")
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|subject| !subject.is_empty())
 }
 
 /// Maps a commit message prefix to a commit category.
 ///
 /// Supports conventional commit prefixes like "feat", "fix", "docs", etc.
-/// and maps them to appropriate categories.
+/// and maps them to appropriate categories using the given keyword set.
 ///
 /// # Arguments
 ///
 /// * `prefix` - The commit message prefix (case-insensitive)
+/// * `keywords` - The keyword set to look the prefix up in
 ///
 /// # Returns
 ///
 /// Returns `Some(CommitCategory)` if the prefix is recognized, or `None` otherwise.
-fn prefix_mapping(prefix: &str) -> Option<CommitCategory> {
-    let cat = match () {
-        _ if prefix.eq_ignore_ascii_case("docs")
-            || prefix.eq_ignore_ascii_case("doc")
-            || prefix.eq_ignore_ascii_case("style")
-            || prefix.eq_ignore_ascii_case("chore")
-            || prefix.eq_ignore_ascii_case("test") =>
-        {
-            CommitCategory::Ignore
-        }
-        _ if prefix.eq_ignore_ascii_case("tweak") || prefix.eq_ignore_ascii_case("tweaks") => {
-            CommitCategory::Patch
-        }
-        _ if prefix.eq_ignore_ascii_case("fix")
-            || prefix.eq_ignore_ascii_case("fixes")
-            || prefix.eq_ignore_ascii_case("perf")
-            || prefix.eq_ignore_ascii_case("refactor")
-            || prefix.eq_ignore_ascii_case("patch") =>
-        {
-            CommitCategory::Patch
-        }
-        _ if prefix.eq_ignore_ascii_case("feat") || prefix.eq_ignore_ascii_case("minor") => {
-            CommitCategory::Minor
-        }
-        _ if prefix.eq_ignore_ascii_case("breaking") || prefix.eq_ignore_ascii_case("major") => {
-            CommitCategory::Major
-        }
+fn prefix_mapping(prefix: &str, keywords: &KeywordSet) -> Option<CommitCategory> {
+    keywords.lookup(prefix)
+}
+
+/// Parses the trailers (e.g. "Changelog: Added") at the end of a commit body.
+///
+/// # Arguments
+///
+/// * `body` - The commit message body
+///
+/// # Returns
+///
+/// The `(key, value)` pairs of the trailer block, in order.
+pub fn parse_trailers(body: &str) -> Vec<(String, String)> {
+    // The parser expects a full message and skips its subject, which the body lacks.
+    let message = format!("subject\n\n{body}");
+    match git2::message_trailers_strs(&message) {
+        Ok(trailers) => trailers
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Returns the value of the first trailer named `key` (case-insensitive), if any.
+pub fn find_trailer(body: &str, key: &str) -> Option<String> {
+    parse_trailers(body)
+        .into_iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v)
+}
+
+/// Maps a [Keep a Changelog](https://keepachangelog.com) section name to its canonical
+/// spelling and the category that determines the version bump.
+///
+/// # Arguments
+///
+/// * `label` - The section name (case-insensitive), e.g. from a `Changelog:` trailer
+///
+/// # Returns
+///
+/// Returns `Some((section, category))` if the label is a Keep a Changelog section.
+fn keep_a_changelog_section(label: &str) -> Option<(&'static str, CommitCategory)> {
+    let section = match label.trim().to_lowercase().as_str() {
+        "added" => ("Added", CommitCategory::Minor),
+        "changed" => ("Changed", CommitCategory::Patch),
+        "deprecated" => ("Deprecated", CommitCategory::Minor),
+        "removed" => ("Removed", CommitCategory::Major),
+        "fixed" => ("Fixed", CommitCategory::Patch),
+        "security" => ("Security", CommitCategory::Patch),
         _ => return None,
     };
-    Some(cat)
+    Some(section)
+}
+
+/// The parts of a conventional commit subject, e.g. "feat(api)!: add thing".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedSubject {
+    /// The commit type, e.g. "feat", if the subject has a prefix.
+    pub kind: Option<String>,
+    /// The scope in parentheses, if any.
+    pub scope: Option<String>,
+    /// Whether the subject carries the `!` breaking change marker.
+    pub breaking: bool,
+    /// The subject without its prefix.
+    pub title: String,
+}
+
+/// Splits a commit subject into its conventional commit parts.
+///
+/// # Arguments
+///
+/// * `summary` - The commit subject line
+///
+/// # Returns
+///
+/// The parsed subject; without a prefix, only the title is set.
+pub fn parse_subject(summary: &str) -> ParsedSubject {
+    static RE_SUBJECT: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+        Regex::new(r"^([^():!\s]+)(?:\(([^)]+)\))?(!)?:\s+(.*)$").unwrap()
+    });
+
+    match RE_SUBJECT.captures(summary) {
+        Some(cap) => ParsedSubject {
+            kind: Some(cap[1].to_string()),
+            scope: cap.get(2).map(|m| m.as_str().to_string()),
+            breaking: cap.get(3).is_some(),
+            title: cap[4].to_string(),
+        },
+        None => ParsedSubject {
+            title: summary.to_string(),
+            ..Default::default()
+        },
+    }
 }
 
 /// Automatically classifies a commit based on its message.
 ///
 /// Analyzes the commit summary to determine its category. Supports:
 /// - Conventional commit format: "type: subject" or "type(scope): subject"
-/// - Release messages: "-> v1.2.3"
+/// - The breaking change marker "type!: subject" or "type(scope)!: subject", which makes
+///   any recognized type major
+/// - Release messages: "-> v1.2.3" (per `tag_name_format`), unless `keep_release_commits`
+///   is set
+/// - Merge commits, when `ignore_merges` is set
+/// - `fixup!`/`squash!` commits, ignored unless `fold_fixups` is set
 /// - Simple keywords: "tweak", "tweaks"
+/// - Breaking change footers on the subject line: "BREAKING CHANGE: removed X"
+/// - `BREAKING CHANGE:` footers in the body, which make a classified commit major
+/// - Reverts, when `reverts_section` is set
+/// - `Changelog: <section>` trailers, when `categories_from_labels` is set
+/// - `Semver: major|minor|patch|none` trailers, which override the category
+///
+/// Commits with an `internal` scope or an `Audience: internal` trailer are also marked as
+/// internal, whatever their category.
 ///
 /// If a prefix is found and recognized, it is removed from the commit summary.
 ///
 /// # Arguments
 ///
 /// * `commit` - The commit to classify (summary may be modified)
+/// * `opts` - Classification options
 ///
 /// # Returns
 ///
 /// Returns `Some(CommitCategory)` if the commit can be automatically classified,
 /// or `None` if manual classification is needed.
-pub fn auto_classify(commit: &mut CommitInfo) -> Option<CommitCategory> {
-    if is_release_message(&commit.summary).is_some() {
-        return Some(CommitCategory::Ignore);
+pub fn auto_classify(commit: &mut CommitInfo, opts: &ClassifyOptions) -> Option<CommitCategory> {
+    commit.internal = is_internal(commit);
+    commit.ignore_reason = None;
+
+    if !opts.keep_release_commits
+        && is_release_message(&commit.summary, opts.tag_name_format.as_deref()).is_some()
+    {
+        return ignore(commit, "release message");
+    }
+
+    if opts.ignore_merges && commit.is_merge() {
+        return ignore(commit, "merge commit");
+    }
+
+    if let Some(target) = fixup_target(&commit.summary) {
+        if !opts.fold_fixups {
+            return ignore(commit, "fixup commit");
+        }
+        commit.summary = target.to_string();
+    }
+
+    let mut cat = match (opts.reverts_section, reverted_subject(&commit.summary)) {
+        (Some(bump), Some(subject)) => {
+            // List what was rolled back, without the prefix of the reverted commit.
+            commit.summary = parse_subject(subject).title;
+            commit.section = Some(REVERTS_SECTION.to_string());
+            Some(bump)
+        }
+        _ => classify_subject(commit, opts),
+    };
+    if cat.is_some_and(|cat| cat != CommitCategory::Major) && has_breaking_footer(&commit.body) {
+        cat = Some(CommitCategory::Major);
+    }
+
+    if opts.categories_from_labels {
+        let label = find_trailer(&commit.body, "Changelog");
+        if let Some((section, label_cat)) = label.as_deref().and_then(keep_a_changelog_section) {
+            commit.section = Some(section.to_string());
+            cat = Some(label_cat);
+        }
     }
 
+    if let Some(impact) = find_trailer(&commit.body, "Semver")
+        .as_deref()
+        .and_then(semver_impact)
+    {
+        cat = Some(impact);
+        if impact == CommitCategory::Ignore {
+            commit.ignore_reason = Some("Semver: none trailer".to_string());
+        }
+    }
+
+    if cat != Some(CommitCategory::Ignore) {
+        commit.ignore_reason = None;
+    }
+    cat
+}
+
+/// Marks a commit as ignored for the given reason.
+fn ignore(commit: &mut CommitInfo, reason: &str) -> Option<CommitCategory> {
+    commit.ignore_reason = Some(reason.to_string());
+    Some(CommitCategory::Ignore)
+}
+
+/// Checks whether a commit body has a `BREAKING CHANGE:` (or `BREAKING-CHANGE:`) footer.
+///
+/// # Arguments
+///
+/// * `body` - The commit message body
+pub fn has_breaking_footer(body: &str) -> bool {
+    breaking_footer(body).is_some()
+}
+
+/// Reads the text of the `BREAKING CHANGE:` (or `BREAKING-CHANGE:`) footer of a commit.
+///
+/// The text continues on the following lines up to a blank line or the next trailer.
+///
+/// # Arguments
+///
+/// * `body` - The commit message body
+///
+/// # Returns
+///
+/// The footer text, with its lines joined by spaces, or `None` if the body has no such
+/// footer. The text is empty if the footer has none.
+pub fn breaking_footer(body: &str) -> Option<String> {
+    static RE_TRAILER: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"^[\w-]+:\s").unwrap());
+
+    let mut lines = body.lines();
+    let first = lines.find_map(|line| {
+        line.strip_prefix("BREAKING CHANGE:")
+            .or_else(|| line.strip_prefix("BREAKING-CHANGE:"))
+    })?;
+    let mut text = first.trim().to_string();
+    for line in lines.take_while(|line| !line.trim().is_empty() && !RE_TRAILER.is_match(line)) {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(line.trim());
+    }
+    Some(text)
+}
+
+/// Checks whether a commit is meant for the internal changelog only, from an `internal`
+/// scope (e.g. "fix(internal): ...") or an `Audience: internal` trailer.
+fn is_internal(commit: &CommitInfo) -> bool {
+    let scope = parse_subject(&commit.summary).scope;
+    scope.is_some_and(|scope| scope.eq_ignore_ascii_case("internal"))
+        || find_trailer(&commit.body, "Audience")
+            .is_some_and(|audience| audience.trim().eq_ignore_ascii_case("internal"))
+}
+
+/// Maps the value of a `Semver:` trailer to a category, `none` meaning the commit is ignored.
+fn semver_impact(value: &str) -> Option<CommitCategory> {
+    match value.trim().to_lowercase().as_str() {
+        "major" => Some(CommitCategory::Major),
+        "minor" => Some(CommitCategory::Minor),
+        "patch" => Some(CommitCategory::Patch),
+        "none" => Some(CommitCategory::Ignore),
+        _ => None,
+    }
+}
+
+/// Classifies a commit from its summary line alone, stripping a recognized prefix.
+fn classify_subject(commit: &mut CommitInfo, opts: &ClassifyOptions) -> Option<CommitCategory> {
     if commit.summary.eq_ignore_ascii_case("tweak") || commit.summary.eq_ignore_ascii_case("tweaks")
     {
         return Some(CommitCategory::Patch);
     }
 
+    // A breaking change footer written as the subject, e.g. "BREAKING CHANGE: removed X"
+    static RE_BREAKING: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"^BREAKING[ -]CHANGE:\s+").unwrap());
+    if RE_BREAKING.is_match(&commit.summary) {
+        commit.summary = RE_BREAKING.replace(&commit.summary, "").into_owned();
+        return Some(CommitCategory::Major);
+    }
+
     // type: subject
     // or type(scope): subject
-    // Check scoped format first to avoid matching it with the simple format
-    static RE_SCOPE: once_cell::sync::Lazy<Regex> =
-        once_cell::sync::Lazy::new(|| Regex::new(r"^([^(]+)\([^)]+\):\s+").unwrap());
-    static RE: once_cell::sync::Lazy<Regex> =
-        once_cell::sync::Lazy::new(|| Regex::new(r"^([^:]+):\s+").unwrap());
-
-    if let Some(cap) = RE_SCOPE.captures(&commit.summary) {
-        if let Some(ty) = cap.get(1) {
-            if let Some(cat) = prefix_mapping(ty.as_str()) {
-                commit.summary = RE_SCOPE.replace(&commit.summary, "").into_owned();
+    // either with a "!" before the colon for a breaking change
+    let parsed = parse_subject(&commit.summary);
+    let ty = parsed.kind.as_deref()?;
+
+    let type_mapping = |ty: &str| {
+        if opts.split_slash_types && ty.contains('/') {
+            // Categories are declared from the highest impact down.
+            ty.split('/')
+                .filter_map(|part| prefix_mapping(part.trim(), &opts.keywords))
+                .min_by_key(|cat| *cat as u8)
+        } else {
+            prefix_mapping(ty, &opts.keywords)
+        }
+    };
+
+    if let Some(cat) = type_mapping(ty) {
+        if parsed.breaking {
+            commit.summary = parsed.title;
+            return Some(CommitCategory::Major);
+        }
+        commit.ignore_reason = (cat == CommitCategory::Ignore).then(|| format!("{ty} prefix"));
+        commit.summary = parsed.title;
+        return Some(cat);
+    }
+
+    match &parsed.scope {
+        Some(scope) => {
+            if let Some(&cat) = opts.scope_categories.get(&scope.to_lowercase()) {
+                commit.ignore_reason =
+                    (cat == CommitCategory::Ignore).then(|| format!("{scope} scope"));
+                commit.summary = parsed.title;
                 return Some(cat);
             }
         }
-    } else if let Some(cap) = RE.captures(&commit.summary) {
-        if let Some(ty) = cap.get(1) {
-            if let Some(cat) = prefix_mapping(ty.as_str()) {
-                commit.summary = RE.replace(&commit.summary, "").into_owned();
-                return Some(cat);
+        None => {
+            static RE_SCOPE_LIKE: once_cell::sync::Lazy<Regex> =
+                once_cell::sync::Lazy::new(|| Regex::new(r"^[\w.-]+$").unwrap());
+            if opts.scope_as_section && RE_SCOPE_LIKE.is_match(ty) {
+                commit.section = Some(ty.to_string());
+                commit.summary = parsed.title;
             }
         }
     }
 
-    None
+    opts.lax.then_some(CommitCategory::Patch)
+}
+
+/// Finds pairs of commits whose summaries are nearly identical.
+///
+/// Summaries are compared case-insensitively by normalized Levenshtein similarity, so
+/// "Fix typo in README" and "fix typo in readme" are a perfect match.
+///
+/// # Arguments
+///
+/// * `commits` - The commits to compare
+/// * `threshold` - Minimum similarity (0.0 to 1.0) above which two summaries are flagged
+///
+/// # Returns
+///
+/// The `(first, second, similarity)` index pairs, in commit order.
+pub fn find_near_duplicates(commits: &[&CommitInfo], threshold: f64) -> Vec<(usize, usize, f64)> {
+    let summaries: Vec<String> = commits.iter().map(|c| c.summary.to_lowercase()).collect();
+    let mut pairs = Vec::new();
+    for (i, a) in summaries.iter().enumerate() {
+        for (j, b) in summaries.iter().enumerate().skip(i + 1) {
+            let similarity = strsim::normalized_levenshtein(a, b);
+            if similarity > threshold {
+                pairs.push((i, j, similarity));
+            }
+        }
+    }
+    pairs
 }
 
 #[cfg(test)]
@@ -148,139 +607,768 @@ mod tests {
     use super::*;
     use git2::Oid;
 
+    #[test]
+    fn test_parse_subject() {
+        assert_eq!(
+            parse_subject("feat(api)!: thing (#9)"),
+            ParsedSubject {
+                kind: Some("feat".to_string()),
+                scope: Some("api".to_string()),
+                breaking: true,
+                title: "thing (#9)".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_subject("fix: bug"),
+            ParsedSubject {
+                kind: Some("fix".to_string()),
+                scope: None,
+                breaking: false,
+                title: "bug".to_string(),
+            }
+        );
+        assert_eq!(parse_subject("Update readme").title, "Update readme");
+        assert_eq!(parse_subject("Update readme").kind, None);
+    }
+
+    #[test]
+    fn test_auto_classify_reverts_section() {
+        let opts = ClassifyOptions {
+            reverts_section: Some(CommitCategory::Patch),
+            ..Default::default()
+        };
+        let mut commit = create_commit_info("Revert \"feat: add export\"");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+        assert_eq!(commit.section.as_deref(), Some(REVERTS_SECTION));
+        assert_eq!(commit.summary, "add export");
+
+        let mut commit = create_commit_info("Revert \"feat: add export\"");
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            None
+        );
+        assert_eq!(commit.section, None);
+
+        assert_eq!(reverted_subject("Revert \"\""), None);
+        assert_eq!(reverted_subject("Reverting the cache"), None);
+    }
+
+    #[test]
+    fn test_auto_classify_marks_internal() {
+        let opts = ClassifyOptions::default();
+        let mut commit = create_commit_info("fix(internal): retry the deploy script");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+        assert!(commit.internal);
+
+        let mut commit = create_commit_info("feat: admin dashboard");
+        commit.body = "Audience: Internal".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Minor)
+        );
+        assert!(commit.internal);
+
+        let mut commit = create_commit_info("feat(api): public endpoint");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Minor)
+        );
+        assert!(!commit.internal);
+    }
+
+    #[test]
+    fn test_auto_classify_semver_trailer() {
+        let mut commit = create_commit_info("chore: expose retry settings");
+        commit.body = "Semver: minor".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Minor)
+        );
+        assert_eq!(commit.summary, "expose retry settings");
+
+        let mut commit = create_commit_info("feat: internal only");
+        commit.body = "Details.\n\nSemver: none\n".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
+
+        let mut commit = create_commit_info("fix: typo");
+        commit.body = "Semver: huge".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
+    }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let a = create_commit_info("Fix typo in README");
+        let b = create_commit_info("fix typo in readme");
+        let c = create_commit_info("Add retry settings");
+        let d = create_commit_info("fix typos in readme");
+
+        let pairs = find_near_duplicates(&[&a, &b, &c, &d], 0.9);
+        let indices: Vec<(usize, usize)> = pairs.iter().map(|(i, j, _)| (*i, *j)).collect();
+        assert_eq!(indices, vec![(0, 1), (0, 3), (1, 3)]);
+        assert_eq!(pairs[0].2, 1.0);
+
+        assert!(find_near_duplicates(&[&a, &d], 1.0).is_empty());
+    }
+
     fn create_commit_info(summary: &str) -> CommitInfo {
         CommitInfo {
             oid: Oid::zero(),
             short_id: "abc1234".to_string(),
+            abbrev_id: "abc1234".to_string(),
             summary: summary.to_string(),
             body: String::new(),
+            section: None,
+            parent_count: 1,
+            pr_number: None,
+            diff_stats: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            has_signature: false,
+            internal: false,
+            time: 0,
+            ignore_reason: None,
         }
     }
 
     #[test]
     fn test_is_release_message() {
         assert_eq!(
-            is_release_message("-> v1.2.3"),
+            is_release_message("-> v1.2.3", None),
             Some(Version::parse("1.2.3").unwrap())
         );
         assert_eq!(
-            is_release_message("-> 1.2.3"),
+            is_release_message("-> 1.2.3", None),
             Some(Version::parse("1.2.3").unwrap())
         );
         assert_eq!(
-            is_release_message("-> v0.1.0"),
+            is_release_message("-> v0.1.0", None),
             Some(Version::parse("0.1.0").unwrap())
         );
-        assert_eq!(is_release_message("-> invalid"), None);
-        assert_eq!(is_release_message("not a release"), None);
-        assert_eq!(is_release_message("->"), None);
+        assert_eq!(is_release_message("-> invalid", None), None);
+        assert_eq!(is_release_message("not a release", None), None);
+        assert_eq!(is_release_message("->", None), None);
+    }
+
+    #[test]
+    fn test_is_release_message_tag_name_format() {
+        let format = Some("release-{version}");
+        assert_eq!(
+            is_release_message("-> release-1.2.3", format),
+            Some(Version::new(1, 2, 3))
+        );
+        assert_eq!(is_release_message("-> v1.2.3", format), None);
+        assert_eq!(
+            is_release_message("-> 1.2.3", format),
+            Some(Version::new(1, 2, 3))
+        );
+        assert_eq!(
+            is_release_message("-> r1_2_3", Some("r{major}_{minor}_{patch}")),
+            Some(Version::new(1, 2, 3))
+        );
+        assert_eq!(
+            is_release_message("-> r1_2", Some("r{major}_{minor}_{patch}")),
+            None
+        );
     }
 
     #[test]
     fn test_auto_classify_release_message() {
         let mut commit = create_commit_info("-> v1.2.3");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Ignore));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
+    }
+
+    #[test]
+    fn test_auto_classify_keep_release_commits() {
+        let mut commit = create_commit_info("-> v1.2.3");
+        let opts = ClassifyOptions {
+            keep_release_commits: true,
+            ..Default::default()
+        };
+        assert_eq!(auto_classify(&mut commit, &opts), None);
+        assert_eq!(commit.summary, "-> v1.2.3");
+
+        commit.body = "Semver: patch".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+    }
+
+    #[test]
+    fn test_auto_classify_fixups() {
+        let mut commit = create_commit_info("fixup! feat: add export");
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
+
+        let opts = ClassifyOptions {
+            fold_fixups: true,
+            ..Default::default()
+        };
+        let mut commit = create_commit_info("squash! fixup! feat: add export");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Minor)
+        );
+        assert_eq!(commit.summary, "add export");
+
+        assert_eq!(fixup_target("fixup! fix: typo"), Some("fix: typo"));
+        assert_eq!(fixup_target("fix: fixup! handling"), None);
+    }
+
+    #[test]
+    fn test_auto_classify_split_slash_types() {
+        let opts = ClassifyOptions {
+            split_slash_types: true,
+            ..Default::default()
+        };
+        let mut commit = create_commit_info("feat/fix: thing");
+        assert_eq!(
+            auto_classify(&mut commit.clone(), &ClassifyOptions::default()),
+            None
+        );
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Minor)
+        );
+        assert_eq!(commit.summary, "thing");
+
+        let mut commit = create_commit_info("docs/fix(api): thing");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+        assert_eq!(commit.summary, "thing");
+    }
+
+    #[test]
+    fn test_auto_classify_ignore_reason() {
+        let opts = ClassifyOptions::default();
+        for (summary, body, reason) in [
+            ("docs: update readme", "", Some("docs prefix")),
+            ("chore(deps): bump serde", "", Some("chore prefix")),
+            ("-> v1.2.3", "", Some("release message")),
+            ("fixup! feat: add thing", "", Some("fixup commit")),
+            (
+                "feat: add thing",
+                "Semver: none",
+                Some("Semver: none trailer"),
+            ),
+            ("docs: add thing", "Semver: minor", None),
+            ("feat: add thing", "", None),
+        ] {
+            let mut commit = create_commit_info(summary);
+            commit.body = body.to_string();
+            auto_classify(&mut commit, &opts);
+            assert_eq!(commit.ignore_reason.as_deref(), reason, "{summary}");
+        }
+    }
+
+    #[test]
+    fn test_auto_classify_scope_categories() {
+        let opts = ClassifyOptions {
+            scope_categories: HashMap::from([("deps".to_string(), CommitCategory::Ignore)]),
+            ..Default::default()
+        };
+        let mut commit = create_commit_info("unknown(deps): bump X");
+        assert_eq!(
+            auto_classify(&mut commit.clone(), &ClassifyOptions::default()),
+            None
+        );
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Ignore)
+        );
+        assert_eq!(commit.summary, "bump X");
+        assert_eq!(commit.ignore_reason.as_deref(), Some("deps scope"));
+
+        // A recognized type takes precedence over the scope.
+        let mut commit = create_commit_info("fix(Deps): pin serde");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+    }
+
+    #[test]
+    fn test_auto_classify_breaking_footer() {
+        let opts = ClassifyOptions::default();
+        for (body, expected) in [
+            ("", CommitCategory::Minor),
+            (
+                "Moves the login flow to the new service.",
+                CommitCategory::Minor,
+            ),
+            (
+                "Moves the login flow.\n\nBREAKING CHANGE: removed the /v1/login route",
+                CommitCategory::Major,
+            ),
+            (
+                "BREAKING-CHANGE: removed the /v1/login route",
+                CommitCategory::Major,
+            ),
+        ] {
+            let mut commit = create_commit_info("feat: migrate auth API");
+            commit.body = body.to_string();
+            assert_eq!(auto_classify(&mut commit, &opts), Some(expected), "{body}");
+            assert_eq!(commit.summary, "migrate auth API");
+        }
+
+        // Commits left to the user stay unclassified.
+        let mut commit = create_commit_info("Migrate auth API");
+        commit.body = "BREAKING CHANGE: removed the /v1/login route".to_string();
+        assert_eq!(auto_classify(&mut commit, &opts), None);
+    }
+
+    #[test]
+    fn test_auto_classify_fix_with_breaking_footer() {
+        let mut commit = create_commit_info("fix: reject empty tokens");
+        commit.body = "Empty tokens used to be accepted.\n\n\
+                       BREAKING CHANGE: clients sending an empty token\n\
+                       now get a 401.\n\
+                       Reviewed-by: Jane Doe <jane@example.com>"
+            .to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Major)
+        );
+        assert_eq!(commit.summary, "reject empty tokens");
+        assert_eq!(
+            breaking_footer(&commit.body).as_deref(),
+            Some("clients sending an empty token now get a 401.")
+        );
+    }
+
+    #[test]
+    fn test_breaking_footer() {
+        assert_eq!(breaking_footer("Some text."), None);
+        assert_eq!(
+            breaking_footer("BREAKING-CHANGE: removed X\n\nMore prose.").as_deref(),
+            Some("removed X")
+        );
+        assert_eq!(breaking_footer("BREAKING CHANGE:").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_auto_classify_breaking_change_subject() {
+        for summary in ["BREAKING CHANGE: removed X", "BREAKING-CHANGE: removed X"] {
+            let mut commit = create_commit_info(summary);
+            assert_eq!(
+                auto_classify(&mut commit, &ClassifyOptions::default()),
+                Some(CommitCategory::Major)
+            );
+            assert_eq!(commit.summary, "removed X");
+        }
+    }
+
+    #[test]
+    fn test_auto_classify_breaking_marker() {
+        for (summary, title) in [
+            ("feat!: drop old config", "drop old config"),
+            ("fix(api)!: rename field", "rename field"),
+            ("chore!: require Rust 1.70", "require Rust 1.70"),
+        ] {
+            let mut commit = create_commit_info(summary);
+            assert_eq!(
+                auto_classify(&mut commit, &ClassifyOptions::default()),
+                Some(CommitCategory::Major),
+                "{summary}"
+            );
+            assert_eq!(commit.summary, title);
+            assert_eq!(commit.ignore_reason, None);
+        }
+
+        // Free text before "!:" is not a type, so no bump is forced.
+        for summary in [
+            "Hotfix for prod!: restore login",
+            "Update docs (readme)!: x",
+        ] {
+            let mut commit = create_commit_info(summary);
+            assert_eq!(
+                auto_classify(&mut commit, &ClassifyOptions::default()),
+                None,
+                "{summary}"
+            );
+            assert_eq!(commit.summary, summary);
+        }
+
+        // Without the marker, the type decides as usual.
+        let mut commit = create_commit_info("chore: require Rust 1.70");
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
+    }
+
+    #[test]
+    fn test_auto_classify_lax() {
+        let opts = ClassifyOptions {
+            lax: true,
+            ..Default::default()
+        };
+        for summary in ["ci: cache the toolchain", "build(deps): bump serde"] {
+            let mut commit = create_commit_info(summary);
+            assert_eq!(
+                auto_classify(&mut commit, &ClassifyOptions::default()),
+                None
+            );
+            assert_eq!(
+                auto_classify(&mut commit, &opts),
+                Some(CommitCategory::Patch)
+            );
+            assert_eq!(commit.summary, summary);
+        }
+
+        let mut commit = create_commit_info("Update readme");
+        assert_eq!(auto_classify(&mut commit, &opts), None);
     }
 
     #[test]
     fn test_auto_classify_tweak() {
         let mut commit = create_commit_info("tweak");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
         assert_eq!(commit.summary, "tweak");
 
         let mut commit = create_commit_info("Tweaks");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
     }
 
     #[test]
     fn test_auto_classify_conventional_commits() {
         // Major
         let mut commit = create_commit_info("breaking: remove deprecated API");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Major));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Major)
+        );
         assert_eq!(commit.summary, "remove deprecated API");
 
         let mut commit = create_commit_info("major: breaking change");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Major));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Major)
+        );
 
         // Minor
         let mut commit = create_commit_info("feat: add new feature");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Minor));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Minor)
+        );
         assert_eq!(commit.summary, "add new feature");
 
         let mut commit = create_commit_info("minor: add something");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Minor));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Minor)
+        );
 
         // Patch
         let mut commit = create_commit_info("fix: resolve bug");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
         assert_eq!(commit.summary, "resolve bug");
 
         let mut commit = create_commit_info("perf: improve performance");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
 
         let mut commit = create_commit_info("refactor: clean up code");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
 
         // Ignore
         let mut commit = create_commit_info("docs: update README");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Ignore));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
         assert_eq!(commit.summary, "update README");
 
         let mut commit = create_commit_info("style: format code");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Ignore));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
 
         let mut commit = create_commit_info("chore: update dependencies");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Ignore));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
 
         let mut commit = create_commit_info("test: add unit tests");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Ignore));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
     }
 
     #[test]
     fn test_auto_classify_with_scope() {
         let mut commit = create_commit_info("feat(api): add new endpoint");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Minor));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Minor)
+        );
         assert_eq!(commit.summary, "add new endpoint");
 
         let mut commit = create_commit_info("fix(parser): handle edge case");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
         assert_eq!(commit.summary, "handle edge case");
 
         let mut commit = create_commit_info("breaking(api): remove old method");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Major));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Major)
+        );
         assert_eq!(commit.summary, "remove old method");
     }
 
     #[test]
     fn test_auto_classify_case_insensitive() {
         let mut commit = create_commit_info("FEAT: uppercase");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Minor));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Minor)
+        );
 
         let mut commit = create_commit_info("Fix: mixed case");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
 
         let mut commit = create_commit_info("DOCS: documentation");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Ignore));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
     }
 
     #[test]
     fn test_auto_classify_unknown_prefix() {
         let mut commit = create_commit_info("unknown: something");
-        assert_eq!(auto_classify(&mut commit), None);
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            None
+        );
         assert_eq!(commit.summary, "unknown: something");
     }
 
     #[test]
     fn test_auto_classify_no_prefix() {
         let mut commit = create_commit_info("just a regular commit message");
-        assert_eq!(auto_classify(&mut commit), None);
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            None
+        );
         assert_eq!(commit.summary, "just a regular commit message");
     }
 
     #[test]
     fn test_auto_classify_multiple_colons() {
         let mut commit = create_commit_info("fix: handle error: invalid input");
-        assert_eq!(auto_classify(&mut commit), Some(CommitCategory::Patch));
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Patch)
+        );
         assert_eq!(commit.summary, "handle error: invalid input");
     }
+
+    #[test]
+    fn test_auto_classify_french_keywords() {
+        let french = ClassifyOptions {
+            keywords: KeywordSet::for_locale("fr").unwrap(),
+            ..Default::default()
+        };
+
+        let mut commit = create_commit_info("corrige: plantage au démarrage");
+        assert_eq!(
+            auto_classify(&mut commit, &french),
+            Some(CommitCategory::Patch)
+        );
+        assert_eq!(commit.summary, "plantage au démarrage");
+
+        let mut commit = create_commit_info("Fonctionnalité: export PDF");
+        assert_eq!(
+            auto_classify(&mut commit, &french),
+            Some(CommitCategory::Minor)
+        );
+
+        // English prefixes keep working with the French set.
+        let mut commit = create_commit_info("feat: add export");
+        assert_eq!(
+            auto_classify(&mut commit, &french),
+            Some(CommitCategory::Minor)
+        );
+
+        // The default English set doesn't know French prefixes.
+        let mut commit = create_commit_info("corrige: plantage");
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_keyword_set_unknown_locale() {
+        assert!(KeywordSet::for_locale("xx").is_none());
+        assert!(KeywordSet::for_locale("EN").is_some());
+    }
+
+    #[test]
+    fn test_auto_classify_scope_as_section() {
+        let opts = ClassifyOptions {
+            scope_as_section: true,
+            ..Default::default()
+        };
+
+        let mut commit = create_commit_info("api: add endpoint");
+        assert_eq!(auto_classify(&mut commit, &opts), None);
+        assert_eq!(commit.section.as_deref(), Some("api"));
+        assert_eq!(commit.summary, "add endpoint");
+
+        // Known types are unaffected.
+        let mut commit = create_commit_info("feat: add endpoint");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Minor)
+        );
+        assert_eq!(commit.section, None);
+
+        // Prefixes that don't look like a scope are left alone.
+        let mut commit = create_commit_info("see the notes: it works");
+        assert_eq!(auto_classify(&mut commit, &opts), None);
+        assert_eq!(commit.section, None);
+
+        // Without the flag, nothing changes.
+        let mut commit = create_commit_info("api: add endpoint");
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            None
+        );
+        assert_eq!(commit.section, None);
+        assert_eq!(commit.summary, "api: add endpoint");
+    }
+
+    #[test]
+    fn test_auto_classify_ignore_merges() {
+        let opts = ClassifyOptions {
+            ignore_merges: true,
+            ..Default::default()
+        };
+
+        let mut commit = create_commit_info("Merge pull request #12 from user/feature");
+        commit.parent_count = 2;
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Ignore)
+        );
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            None
+        );
+
+        let mut commit = create_commit_info("feat: regular commit");
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Minor)
+        );
+    }
+
+    #[test]
+    fn test_parse_trailers() {
+        let body = "Some details.\n\nChangelog: Added\nSigned-off-by: Jane <jane@example.com>\n";
+        assert_eq!(
+            parse_trailers(body),
+            vec![
+                ("Changelog".to_string(), "Added".to_string()),
+                (
+                    "Signed-off-by".to_string(),
+                    "Jane <jane@example.com>".to_string()
+                ),
+            ]
+        );
+        assert!(parse_trailers("").is_empty());
+        assert!(parse_trailers("Just a body without trailers.").is_empty());
+        assert_eq!(
+            parse_trailers("Changelog: Fixed"),
+            vec![("Changelog".to_string(), "Fixed".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_auto_classify_categories_from_labels() {
+        let opts = ClassifyOptions {
+            categories_from_labels: true,
+            ..Default::default()
+        };
+
+        let mut commit = create_commit_info("feat: harden session cookies");
+        commit.body = "Set SameSite=strict.\n\nChangelog: Security\n".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+        assert_eq!(commit.section.as_deref(), Some("Security"));
+        assert_eq!(commit.summary, "harden session cookies");
+
+        let mut commit = create_commit_info("drop the v1 API");
+        commit.body = "changelog: removed".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Major)
+        );
+        assert_eq!(commit.section.as_deref(), Some("Removed"));
+
+        // Unknown labels fall back to prefix detection.
+        let mut commit = create_commit_info("fix: typo");
+        commit.body = "Changelog: Misc".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &opts),
+            Some(CommitCategory::Patch)
+        );
+        assert_eq!(commit.section, None);
+
+        // Without the flag, trailers are ignored.
+        let mut commit = create_commit_info("feat: harden session cookies");
+        commit.body = "Changelog: Security".to_string();
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Minor)
+        );
+        assert_eq!(commit.section, None);
+    }
 }
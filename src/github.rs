@@ -0,0 +1,155 @@
+//! Queries of the GitHub REST API.
+//!
+//! Requests are made with the `curl` command line tool, so that no HTTP client has to be
+//! built into the binary. The token is passed on stdin rather than as an argument, where
+//! other users could see it in the process list.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::git::RemoteInfo;
+
+/// Base URL of the GitHub REST API.
+const API_URL: &str = "https://api.github.com";
+
+/// Extracts the owner and name of a repository hosted on github.com.
+///
+/// # Arguments
+///
+/// * `remote` - The remote repository information
+///
+/// # Returns
+///
+/// `(owner, repo)`, or `None` if the remote is not a github.com repository.
+pub fn github_repo(remote: &RemoteInfo) -> Option<(String, String)> {
+    let path = remote.base_url.strip_prefix("https://github.com/")?;
+    let (owner, repo) = path.trim_end_matches('/').split_once('/')?;
+    (!owner.is_empty() && !repo.is_empty() && !repo.contains('/'))
+        .then(|| (owner.to_string(), repo.to_string()))
+}
+
+/// Checks whether an issue is closed in the GitHub issue tracker.
+///
+/// # Arguments
+///
+/// * `token` - A GitHub token with read access to the repository's issues
+/// * `owner` - The owner of the repository
+/// * `repo` - The name of the repository
+/// * `issue_id` - The issue number
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be run, the request fails, or the response is not
+/// an issue.
+pub fn is_issue_closed(token: &str, owner: &str, repo: &str, issue_id: u64) -> Result<bool> {
+    let url = format!("{API_URL}/repos/{owner}/{repo}/issues/{issue_id}");
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--header", "@-"])
+        .args(["--header", "Accept: application/vnd.github+json"])
+        .args(["--user-agent", "changelogger"])
+        .arg(&url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Could not run curl to query the GitHub API")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "Authorization: Bearer {token}")?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Could not fetch {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    parse_issue_state(&String::from_utf8_lossy(&output.stdout))
+        .with_context(|| format!("Unexpected response from {url}"))
+}
+
+/// Reads the state of an issue from a GitHub API response.
+///
+/// # Returns
+///
+/// `true` if the issue is closed.
+fn parse_issue_state(body: &str) -> Result<bool> {
+    let issue: serde_json::Value = serde_json::from_str(body)?;
+    match issue.get("state").and_then(|state| state.as_str()) {
+        Some(state) => Ok(state == "closed"),
+        None => Err(anyhow!("The response has no issue state")),
+    }
+}
+
+/// Remembers the state of the issues of a repository, so that each issue is only
+/// fetched once.
+pub struct IssueStateCache {
+    token: String,
+    owner: String,
+    repo: String,
+    closed: HashMap<u64, bool>,
+}
+
+impl IssueStateCache {
+    /// Creates an empty cache for a repository.
+    pub fn new(token: &str, owner: &str, repo: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            closed: HashMap::new(),
+        }
+    }
+
+    /// Checks whether an issue is closed, querying the API on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the issue state cannot be fetched; failures are not cached.
+    pub fn is_closed(&mut self, issue_id: u64) -> Result<bool> {
+        if let Some(&closed) = self.closed.get(&issue_id) {
+            return Ok(closed);
+        }
+        let closed = is_issue_closed(&self.token, &self.owner, &self.repo, issue_id)?;
+        self.closed.insert(issue_id, closed);
+        Ok(closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(base_url: &str) -> RemoteInfo {
+        RemoteInfo::new(base_url.to_string())
+    }
+
+    #[test]
+    fn test_github_repo() {
+        assert_eq!(
+            github_repo(&remote("https://github.com/owner/repo/")),
+            Some(("owner".to_string(), "repo".to_string()))
+        );
+        assert_eq!(github_repo(&remote("https://gitlab.com/owner/repo/")), None);
+        assert_eq!(github_repo(&remote("https://github.com/owner/")), None);
+    }
+
+    #[test]
+    fn test_parse_issue_state() {
+        assert!(parse_issue_state(r#"{"number": 12, "state": "closed"}"#).unwrap());
+        assert!(!parse_issue_state(r#"{"number": 12, "state": "open"}"#).unwrap());
+        assert!(parse_issue_state(r#"{"message": "Not Found"}"#).is_err());
+        assert!(parse_issue_state("not json").is_err());
+    }
+
+    #[test]
+    fn test_issue_state_cache_uses_cached_state() {
+        let mut cache = IssueStateCache::new("token", "owner", "repo");
+        cache.closed.insert(12, true);
+        assert!(cache.is_closed(12).unwrap());
+    }
+}
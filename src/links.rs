@@ -0,0 +1,168 @@
+//! Verification of the links in generated release notes.
+//!
+//! Like the GitHub API queries, requests are made with the `curl` command line tool.
+//! They are spaced out by [`REQUEST_INTERVAL`] so that forges do not throttle them.
+
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+/// Delay between two link checks.
+pub const REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Extracts the distinct URLs of the links in a Markdown or Confluence section.
+///
+/// # Arguments
+///
+/// * `section` - The generated section
+///
+/// # Returns
+///
+/// The `http(s)` URLs of `[text](url)` and `[text|url]` links, in order of appearance.
+pub fn extract_links(section: &str) -> Vec<String> {
+    static RE_LINK: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+        Regex::new(r"\]\((https?://[^)\s]+)\)|\|(https?://[^\]\s]+)\]").unwrap()
+    });
+    let mut urls: Vec<String> = Vec::new();
+    for cap in RE_LINK.captures_iter(section) {
+        let url = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+        if !urls.iter().any(|seen| seen == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+/// Where curl discards the response bodies.
+const NULL_DEVICE: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+/// Requests a URL, following redirects. The response body is discarded as it arrives.
+///
+/// # Arguments
+///
+/// * `curl` - The curl program to run
+/// * `url` - The URL to request
+///
+/// # Returns
+///
+/// The HTTP status code of the final response.
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be run, in which case it holds the [`std::io::Error`],
+/// or the server cannot be reached.
+fn fetch_status(curl: &str, url: &str) -> Result<u16> {
+    let output = Command::new(curl)
+        .args(["--silent", "--show-error", "--location", "--max-time", "20"])
+        .args([
+            "--user-agent",
+            "changelogger",
+            "--output",
+            NULL_DEVICE,
+            "--write-out",
+            "%{http_code}",
+        ])
+        .arg(url)
+        .output()
+        .context("Could not run curl to verify links")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_status(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Reads the status code that `--write-out` prints.
+fn parse_status(output: &str) -> Result<u16> {
+    output
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("curl did not report a status code"))
+}
+
+/// Checks every URL with `curl`, pausing between requests.
+///
+/// # Arguments
+///
+/// * `urls` - The URLs to check
+///
+/// # Returns
+///
+/// The broken links, as `(url, problem)` pairs: responses outside 2xx and failed requests.
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be run at all, rather than reporting every link as
+/// broken.
+pub fn find_broken_links(urls: &[String]) -> Result<Vec<(String, String)>> {
+    check_links("curl", urls)
+}
+
+/// Checks every URL with the given curl program, as [`find_broken_links`] does.
+fn check_links(curl: &str, urls: &[String]) -> Result<Vec<(String, String)>> {
+    let mut broken = Vec::new();
+    for (i, url) in urls.iter().enumerate() {
+        if i > 0 {
+            thread::sleep(REQUEST_INTERVAL);
+        }
+        match fetch_status(curl, url) {
+            Ok(status) if (200..300).contains(&status) => {}
+            Ok(status) => broken.push((url.clone(), format!("HTTP {status}"))),
+            Err(err) if err.downcast_ref::<std::io::Error>().is_some() => return Err(err),
+            Err(err) => broken.push((url.clone(), format!("{err:#}"))),
+        }
+    }
+    Ok(broken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let section = "## [Version 1.2.0](https://github.com/o/r/releases/tag/v1.2.0)\n\
+                       * fix: [`abc1234`](https://github.com/o/r/commit/abc1234) \
+                       ([#12](https://github.com/o/r/issues/12))\n\
+                       * again: [`def5678`](https://github.com/o/r/commit/def5678) \
+                       ([#12](https://github.com/o/r/issues/12))\n\
+                       * [abc|https://gitlab.com/o/r/commit/abc]\n\
+                       [local](docs/upgrade.md)\n";
+        assert_eq!(
+            extract_links(section),
+            [
+                "https://github.com/o/r/releases/tag/v1.2.0",
+                "https://github.com/o/r/commit/abc1234",
+                "https://github.com/o/r/issues/12",
+                "https://github.com/o/r/commit/def5678",
+                "https://gitlab.com/o/r/commit/abc",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_links_without_curl() {
+        let urls = [
+            "https://example.com/a".to_string(),
+            "https://example.com/b".to_string(),
+        ];
+        let err = check_links("changelogger-missing-curl", &urls).unwrap_err();
+        assert_eq!(err.to_string(), "Could not run curl to verify links");
+        assert!(check_links("changelogger-missing-curl", &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status("404").unwrap(), 404);
+        assert_eq!(parse_status("200\n").unwrap(), 200);
+        assert!(parse_status("").is_err());
+        assert!(parse_status("<html>").is_err());
+    }
+}
@@ -3,239 +3,3172 @@
 //! This module handles command-line argument parsing, orchestrates the changelog
 //! generation process, and provides interactive classification of commits.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 use anyhow::{anyhow, Context, Result};
-use chrono::Local;
-use clap::Parser;
-use dialoguer::{theme::ColorfulTheme, Select};
+use chrono::{Local, NaiveDate};
+use clap::{Arg, ArgAction, Command, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use git2::{Oid, Repository};
 use owo_colors::OwoColorize;
-use semver::Version;
+use regex::Regex;
+use semver::{BuildMetadata, Prerelease, Version};
 
 mod changelog;
+mod checkpoint;
 mod classify;
+mod color;
+mod config;
 mod git;
+mod github;
+mod links;
+mod messages;
 
-use changelog::{build_release_section, write_changelog};
-use classify::{auto_classify, CommitCategory};
-use git::{commits_since, find_latest_semver_tag, get_remote_info, open_repo, CommitInfo};
+use changelog::{
+    audit_sections, build_release_section, check_date_format, closed_issues,
+    find_duplicate_issue_refs, format_version, has_issue_ref, is_generated, latest_release_heading,
+    nix_manifest, open_output_fd, print_changelog, read_existing, release_toml, render_confluence,
+    render_json, render_markdown, tag_name, trim_trailing_whitespace, unreleased_heading,
+    wrap_bullets, write_changelog, IssueSyntax, ListFormat, ReleaseSection, RenderOptions,
+    WriteOptions, DEFAULT_DATE_FORMAT, FORMAT_VERSION,
+};
+use checkpoint::{load_checkpoint, save_checkpoint};
+use classify::{
+    auto_classify, find_near_duplicates, fixup_target, parse_subject, ClassifyOptions,
+    CommitCategory, KeywordSet,
+};
+use color::{colorize_category, ColorScheme, Role};
+use config::{config_args, find_config_in_manifest, load_config_file, load_repo_config};
+use git::{
+    attribute_merged_prs, commit_diff_stats, commit_has_signature, commits_since,
+    find_latest_semver_tag, has_uncommitted_changes, is_ancestor, merge_base, open_repo,
+    parse_tag_version, remote_from_env, resolve_branch, resolve_commit, walk_tips, write_note,
+    CommitInfo, RemoteInfo, RemoteInfoCache, TagFilter, WalkOptions,
+};
+use github::{github_repo, IssueStateCache};
+use links::{extract_links, find_broken_links};
+use messages::{CategoryLabel, Lang};
+
+/// Markup language of the generated release section.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Format {
+    /// Markdown, suitable for CHANGELOG.md
+    #[default]
+    Markdown,
+    /// Confluence wiki markup
+    Confluence,
+    /// JSON release data (version, date, and the breaking changes, features, fixes and
+    /// other sections), e.g. for CI pipelines posting release notes to chat or a forge.
+    /// Replaces the file given with --output rather than being merged into the changelog
+    Json,
+}
+
+/// What the "full changes" link compares.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum CompareBy {
+    /// The previous and new version tags
+    #[default]
+    Tags,
+    /// The boundary commits, for releases that are not tagged yet
+    Shas,
+}
+
+/// Whether to attach the commit the release is built from to the new version.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum VersionSuffixStrategy {
+    /// Leave the version as computed
+    #[default]
+    Never,
+    /// Append the short commit id as a pre-release (1.2.3-gabc1234)
+    Prerelease,
+    /// Append the short commit id as build metadata (1.2.3+abc1234)
+    BuildMetadata,
+}
+
+/// How the new version is printed to stdout.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum VersionOutputFormat {
+    /// The full version (1.2.3)
+    Semver,
+    /// Major and minor only (1.2)
+    SemverShort,
+    /// The version with a `v` prefix, as in tags (v1.2.3)
+    PackageVersion,
+}
+
+impl VersionOutputFormat {
+    /// Formats a version.
+    fn format(self, version: &Version) -> String {
+        match self {
+            VersionOutputFormat::Semver => version.to_string(),
+            VersionOutputFormat::SemverShort => format!("{}.{}", version.major, version.minor),
+            VersionOutputFormat::PackageVersion => format!("v{version}"),
+        }
+    }
+}
 
 /// Command-line interface arguments for changelogger.
 #[derive(Parser, Debug)]
 #[command(
     name = "changelogger",
     version,
-    about = "Generate or update CHANGELOG.md from git commits"
+    about = "Generate or update CHANGELOG.md from git commits",
+    after_help = "Every flag can be turned off with --no-<flag>, e.g. --no-dry-run to override \
+                  a configuration file.",
+    args_override_self = true
 )]
 struct Cli {
     /// Path to the repository, defaults to current directory
     #[arg(long, default_value = ".")]
     repo: String,
 
+    /// Read settings from this file instead of the repository's `.changelogger.toml` or
+    /// `changelogger.toml`
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
     /// Optional new version, otherwise computed from commits
     #[arg(long)]
     new_version: Option<String>,
 
+    /// Build metadata to append to the new version, e.g. "exp.sha.5114f85" for 1.2.3+exp.sha.5114f85
+    #[arg(long)]
+    build_metadata: Option<String>,
+
+    /// Attach the short id of the released commit to the new version, unless
+    /// --build-metadata is given
+    #[arg(long, value_enum, default_value_t = VersionSuffixStrategy::Never)]
+    version_suffix_strategy: VersionSuffixStrategy,
+
     /// Optional tag to start from, otherwise latest semver tag is used
     #[arg(long)]
     from_tag: Option<String>,
 
-    /// File to write the changelog to
+    /// Start from the latest tag matching this regex instead of "v*". The version is read
+    /// from the first capture group if any, e.g. "^release-(.+)$"
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new, conflicts_with = "from_tag")]
+    from_tag_pattern: Option<Regex>,
+
+    /// Start from the tag on the most recent commit instead of the highest version
+    #[arg(long, conflicts_with = "from_tag")]
+    latest_by_time: bool,
+
+    /// Also list everything since the last stable (non pre-release) tag, under a nested
+    /// "Since <tag>" heading, e.g. to summarize the changes since the last GA in RC notes
+    #[arg(long)]
+    since_last_stable: bool,
+
+    /// Read the previous version from this environment variable (e.g. PREVIOUS_VERSION)
+    /// instead of discovering it from tags
+    #[arg(long, value_name = "VAR", conflicts_with = "from_tag")]
+    last_version_env: Option<String>,
+
+    /// Only consider tags of a release channel, e.g. "-stable" for v1.2.3-stable.
+    /// The suffix is stripped before the version is parsed
+    #[arg(long)]
+    tag_suffix: Option<String>,
+
+    /// Optional branch to generate the changelog for, otherwise HEAD is used
+    #[arg(long)]
+    base_branch: Option<String>,
+
+    /// Only walk the first-parent history of branches matching a glob (e.g. "release/*"),
+    /// instead of the whole graph from HEAD
+    #[arg(long, value_name = "PATTERN", conflicts_with = "base_branch")]
+    branch_filter: Option<String>,
+
+    /// Show full 40-character commit ids instead of abbreviations (links keep a short text)
+    #[arg(long)]
+    show_full_oid: bool,
+
+    /// Leave out commits authored before this day (UTC)
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    ignore_commits_before: Option<NaiveDate>,
+
+    /// Only include commits unique to HEAD (or --base-branch) relative to this branch,
+    /// starting from their merge base instead of the latest tag
+    #[arg(long, value_name = "BRANCH")]
+    merge_base: Option<String>,
+
+    /// Optional ref (e.g. origin/main) to start from when no semver tag is found,
+    /// otherwise the full history is used
+    #[arg(long)]
+    base_ref: Option<String>,
+
+    /// File to write the changelog to, or "-" to print the merged changelog to stdout.
+    /// Defaults to the `output` set in pyproject.toml / Cargo.toml, or CHANGELOG.md
+    #[arg(long)]
+    output: Option<String>,
+
+    /// Write the merged changelog to this inherited file descriptor instead (Unix only)
+    #[arg(long, value_name = "FD", conflicts_with = "output")]
+    output_fd: Option<i32>,
+
+    /// Existing changelog to merge the new section into when writing to stdout or a descriptor
     #[arg(long, default_value = "CHANGELOG.md")]
-    output: String,
+    input: String,
+
+    /// Markup language of the generated changelog
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
 
     /// Dry run, print to stdout instead of writing file
     #[arg(long)]
     dry_run: bool,
 
+    /// In a dry run, also list the ignored commits with the rule that excluded each
+    #[arg(long, requires = "dry_run")]
+    explain_ignored: bool,
+
     /// Do not ask interactive questions, unknown commits become patch by default
     #[arg(long)]
     non_interactive: bool,
-}
 
-/// Main entry point for the changelogger application.
-///
-/// Processes command-line arguments, opens the git repository, finds commits since
-/// the last version tag, classifies commits (interactively or automatically),
-/// determines the new version number, and generates/updates the changelog file.
-///
-/// # Errors
-///
-/// Returns an error if:
-/// - The repository cannot be opened
-/// - No commits are found since the starting point
-/// - Version parsing fails
-/// - The changelog file cannot be written
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+    /// Category for unclassified commits that are not prompted for
+    #[arg(long, value_enum, default_value_t = CommitCategory::Patch)]
+    unknown: CommitCategory,
 
-    let repo = open_repo(&cli.repo)
-        .with_context(|| format!("Could not open git repository at {}", cli.repo))?;
-    println!("{}", "Opened repository".cyan());
+    /// Prompt in pages of this many commits, asking whether to continue after each page
+    #[arg(long, value_name = "N")]
+    interactive_batch_size: Option<NonZeroUsize>,
 
-    let (last_version, since_oid) = if let Some(tag_name) = cli.from_tag {
-        let obj = repo
-            .revparse_single(&tag_name)
-            .with_context(|| format!("Could not find tag {tag_name}"))?;
-        let commit = obj.peel_to_commit()?;
-        let version_str = tag_name.trim_start_matches('v');
-        let version = Version::parse(version_str)
-            .with_context(|| format!("Tag {tag_name} does not look like a semver version"))?;
-        (version, Some(commit.id()))
-    } else if let Some((tag, oid, v)) = find_latest_semver_tag(&repo)? {
-        println!(
-            "{} latest tag is {} (commit {})",
-            "Info".bright_blue(),
-            tag,
-            oid
-        );
-        (v, Some(oid))
-    } else {
-        println!(
-            "{} no semver git tags found, assuming previous version 0.0.0 and using full history",
-            "Info".bright_blue()
-        );
-        (Version::parse("0.0.0")?, None)
-    };
+    /// Category for the remaining commits when stopping after a page of prompts
+    #[arg(long, value_enum, default_value_t = CommitCategory::Ignore)]
+    batch_default: CommitCategory,
 
-    let commits = commits_since(&repo, since_oid)?;
-    if commits.is_empty() {
-        return Err(anyhow!("No commits found since starting point"));
-    }
+    /// Only prompt for unclassified commits whose summary matches this regex
+    #[arg(long, value_name = "REGEX")]
+    prompt_only_matching: Option<String>,
 
-    let mut classified: Vec<(CommitInfo, Option<CommitCategory>)> = commits
-        .into_iter()
-        .map(|mut c| {
-            let cat = auto_classify(&mut c);
-            (c, cat)
-        })
-        .collect();
+    /// Also recognize commit prefixes in this language (e.g. "fr" for "corrige:")
+    #[arg(long)]
+    keyword_locale: Option<String>,
 
-    if !cli.non_interactive {
-        static ITEMS: &[&str] = &["patch", "minor", "major", "ignore"];
-        let theme = ColorfulTheme::default();
-        for (commit, cat) in classified.iter_mut() {
-            if cat.is_some() {
-                continue;
-            }
+    /// Classify commits with an unrecognized prefix (e.g. "ci:") as patches instead of
+    /// prompting for them
+    #[arg(long)]
+    conventional_commits_lax: bool,
 
-            println!(
-                "\n{} {} {}",
-                "Commit".bold(),
-                commit.short_id.yellow(),
-                commit.summary.bold()
-            );
+    /// Classify combined types such as "feat/fix:" by the highest-impact recognized type
+    #[arg(long)]
+    split_slash_types: bool,
 
-            let choice = Select::with_theme(&theme)
-                .with_prompt("Select type")
-                .items(ITEMS)
-                .default(0)
-                .interact()
-                .unwrap_or(0);
+    /// Category of commits with this scope and an unrecognized type, e.g. "deps=ignore" for
+    /// "build(deps): bump serde" (can be repeated)
+    #[arg(long, value_name = "SCOPE=CATEGORY", value_parser = parse_key_category)]
+    scope_category: Vec<(String, CommitCategory)>,
 
-            let selected = match ITEMS[choice] {
-                "patch" => CommitCategory::Patch,
-                "minor" => CommitCategory::Minor,
-                "major" => CommitCategory::Major,
-                _ => CommitCategory::Ignore,
-            };
+    /// Classify commits with this prefix in this category, e.g. "perf=patch" (can be
+    /// repeated, later occurrences win)
+    #[arg(long, value_name = "PREFIX=CATEGORY", value_parser = parse_key_category)]
+    prefix_override: Vec<(String, CommitCategory)>,
+
+    /// Classify `fixup!`/`squash!` commits by the subject they refer to, unless that commit
+    /// is part of the release too, instead of ignoring them
+    #[arg(long)]
+    fold_fixups: bool,
+
+    /// Classify release commits ("-> v1.2.3", or the tag per --tag-name-format) instead of
+    /// ignoring them, to list them in the changelog
+    #[arg(long)]
+    keep_release_commits: bool,
+
+    /// List reverts (`Revert "feat: X"`) under their own "Reverts" section instead of
+    /// classifying them by the reverted subject
+    #[arg(long)]
+    reverts_section: bool,
+
+    /// Version bump of reverts listed under the "Reverts" section
+    #[arg(long, value_enum, default_value_t = CommitCategory::Patch, requires = "reverts_section")]
+    revert_bump: CommitCategory,
+
+    /// List commits with an unknown, scope-like prefix (e.g. "api:") under a section named
+    /// after it
+    #[arg(long)]
+    scope_as_section: bool,
+
+    /// Warn when the same issue number is referenced by more than one commit
+    #[arg(long)]
+    warn_duplicate_issue_refs: bool,
+
+    /// Route commits with a "Changelog: <section>" trailer (Added, Changed, Deprecated,
+    /// Removed, Fixed, Security) to that section
+    #[arg(long)]
+    categories_from_labels: bool,
+
+    /// Leave pull request merge commits out, crediting their PR number to the merged commits
+    #[arg(long)]
+    strip_merge_pr_commits: bool,
+
+    /// Add a sentence summarizing the number of changes under the version header
+    #[arg(long)]
+    summary_line: bool,
+
+    /// strftime-style format of the release date in the header
+    #[arg(long, default_value = DEFAULT_DATE_FORMAT)]
+    date_format: String,
+
+    /// Remove a single trailing period from entry titles (ellipses are kept)
+    #[arg(long)]
+    strip_trailing_period: bool,
+
+    /// Show the number of added and removed lines next to each commit
+    #[arg(long)]
+    diff_stats: bool,
+
+    /// Show a "has signature" badge next to commits that carry a GPG or SSH signature. The
+    /// signature is not verified
+    #[arg(long, alias = "verify-badges")]
+    signature_badges: bool,
+
+    /// Show the version as its git tag (v1.2.3) in the header instead of "Version 1.2.3"
+    #[arg(long)]
+    tag_prefix_in_header: bool,
+
+    /// Name of the release tags in links and headers, with {version}, {major}, {minor}
+    /// and {patch} placeholders, e.g. "release-{version}" (default "v{version}"). Use
+    /// --from-tag-pattern to find the previous release by the same naming
+    #[arg(long, value_name = "TEMPLATE")]
+    tag_name_format: Option<String>,
+
+    /// Fail instead of generating a changelog without links when no remote can be found
+    #[arg(long)]
+    require_remote: bool,
+
+    /// Build the links from CI environment variables (GITHUB_REPOSITORY, CI_PROJECT_URL,
+    /// BUILD_REPOSITORY_URI) when the repository has no usable origin remote
+    #[arg(long)]
+    remote_from_env: bool,
+
+    /// Do not link the version header to the release tag (the comparison link is kept)
+    #[arg(long)]
+    no_release_link: bool,
 
-            *cat = Some(selected);
+    /// Append a "Closed issues" index of every issue referenced in the release
+    #[arg(long)]
+    issues_index: bool,
+
+    /// End the changelog with a note that it follows Conventional Commits
+    #[arg(long)]
+    conventional_commits_footer: bool,
+
+    /// Text to end the changelog with, replacing the --conventional-commits-footer note
+    #[arg(long)]
+    footer_text: Option<String>,
+
+    /// Compare the version tags or the boundary commits in the "full changes" link
+    #[arg(long, value_enum, default_value_t = CompareBy::Tags)]
+    compare_by: CompareBy,
+
+    /// How to list the commits of a Markdown section
+    #[arg(long, value_enum, default_value_t = ListFormat::Bullets)]
+    list_format: ListFormat,
+
+    /// Only render these categories (e.g. "minor,major"); the others still count for the
+    /// version bump
+    #[arg(long, value_enum, value_delimiter = ',')]
+    only: Option<Vec<CommitCategory>>,
+
+    /// Flag commits whose summaries are more similar than this (0.0 to 1.0) as likely
+    /// duplicates, and ask which to keep
+    #[arg(long, value_name = "SIMILARITY", value_parser = parse_threshold)]
+    fuzzy_dedup_threshold: Option<f64>,
+
+    /// Language of the section headings and generated phrases
+    #[arg(long, value_enum, default_value_t = Lang::En)]
+    lang: Lang,
+
+    /// Show at most N bullets per section followed by "...and M more" (breaking changes
+    /// are always listed in full)
+    #[arg(long, value_name = "N")]
+    max_per_section: Option<usize>,
+
+    /// List the authors and co-authors of the release in a "Contributors" section
+    #[arg(long)]
+    contributors: bool,
+
+    /// Lead the contributors list with their number, e.g. "5 contributors"
+    #[arg(long, requires = "contributors")]
+    contributors_count: bool,
+
+    /// Leave bot accounts (e.g. dependabot[bot]) out of the contributors
+    #[arg(long, requires = "contributors")]
+    exclude_bots: bool,
+
+    /// Always include a "Security fixes" section for `Changelog: Security` commits
+    /// (see --categories-from-labels), stating "None." when there are none
+    #[arg(long, requires = "categories_from_labels")]
+    group_security_separate: bool,
+
+    /// Start a newly created changelog with an encoding declaration comment
+    #[arg(long)]
+    encoding_declaration: bool,
+
+    /// Color palette of the terminal output
+    #[arg(long, value_enum, default_value_t = ColorScheme::Dark)]
+    color_scheme: ColorScheme,
+
+    /// Shell command to run before any git operation, e.g. to refresh credentials
+    #[arg(long, value_name = "CMD")]
+    pre_run_hook: Option<String>,
+
+    /// Shell command to run after the changelog file has been written
+    #[arg(long, value_name = "CMD")]
+    post_run_hook: Option<String>,
+
+    /// Print the commits the interactive mode would ask about, then exit without writing
+    #[arg(long)]
+    list_prompts: bool,
+
+    /// Print how each commit is parsed and classified, then exit without writing
+    #[arg(long)]
+    dump_ast: bool,
+
+    /// Save interactive decisions to this JSON file as they are made, and resume from it
+    #[arg(long, value_name = "PATH")]
+    checkpoint: Option<PathBuf>,
+
+    /// Keep the checkpoint file after a successful run instead of deleting it (a --dry-run
+    /// always keeps it)
+    #[arg(long, requires = "checkpoint")]
+    keep_checkpoint: bool,
+
+    /// Write the changelog even if it has uncommitted changes
+    #[arg(long)]
+    force: bool,
+
+    /// Fail instead of writing or modifying any file; printing to stdout is still allowed.
+    /// Conflicts with --pre-run-hook and --post-run-hook
+    #[arg(long, conflicts_with_all = ["pre_run_hook", "post_run_hook"])]
+    read_only: bool,
+
+    /// Render the commits as an unreleased section instead of a new version, replacing the
+    /// unreleased section left by a previous run
+    #[arg(long)]
+    prepend_unreleased: bool,
+
+    /// Replace the newest section of the changelog instead of adding one when it is for the
+    /// same, not yet tagged, version (e.g. when running twice before tagging)
+    #[arg(long, conflicts_with = "prepend_unreleased")]
+    squash_multiple_bumps: bool,
+
+    /// Label of the unreleased section, as in "## [Unreleased]"
+    #[arg(long, value_name = "LABEL", default_value = "Unreleased")]
+    unreleased_label: String,
+
+    /// Rename a category in section headings and the summary line, with an optional emoji,
+    /// e.g. "major=💥:Breaking". Can be repeated
+    #[arg(long, value_name = "CATEGORY=[EMOJI:]LABEL", value_parser = parse_category_label)]
+    category_label: Vec<(CommitCategory, CategoryLabel)>,
+
+    /// GitHub token used to query the issue tracker (defaults to $GITHUB_TOKEN)
+    #[arg(long)]
+    github_token: Option<String>,
+
+    /// Append ✅ to issue references that are closed in the GitHub issue tracker
+    /// (requires --github-token)
+    #[arg(long)]
+    issue_closed_emoji: bool,
+
+    /// Recognize issue ids after "#" and "!" with this regex instead of numbers, e.g.
+    /// "[A-Z]+-\d+" for "#ABC-1"
+    #[arg(long, value_name = "REGEX")]
+    issue_id_pattern: Option<String>,
+
+    /// Link issue references to this URL, with "{id}" replaced by the issue id, instead of
+    /// the remote's issue tracker, e.g. "https://tracker.example.com/browse/{id}"
+    #[arg(long, value_name = "TEMPLATE")]
+    issue_url_template: Option<String>,
+
+    /// Check that the links of the generated section (commits, issues, compare and release
+    /// links) respond with HTTP 2xx, and warn about broken ones. Links to the tag of the new
+    /// version are skipped, as it is not pushed yet
+    #[arg(long)]
+    verify_links: bool,
+
+    /// Fail instead of warning when --verify-links finds broken links
+    #[arg(long, requires = "verify_links")]
+    fail_on_broken_links: bool,
+
+    /// Strip trailing whitespace from every line of the generated section
+    #[arg(long)]
+    trim_trailing_whitespace: bool,
+
+    /// Hard-wrap Markdown bullets at this many columns, e.g. for plain-text or email
+    /// release notes. Continuation lines are indented under the bullet text
+    #[arg(long, value_name = "N")]
+    wrap: Option<NonZeroUsize>,
+
+    /// List each section on a single line of one paragraph instead of as bullets, for
+    /// brief release notes such as hotfix announcements
+    #[arg(long)]
+    compact: bool,
+
+    /// Show the text of each `BREAKING CHANGE:` footer under its breaking change bullet
+    #[arg(long)]
+    breaking_notes: bool,
+
+    /// Leave out commits that reference no issue or pull request, with a warning for each,
+    /// to enforce linking changes to tickets
+    #[arg(long)]
+    exclude_no_issue: bool,
+
+    /// Check that every included commit is listed exactly once in the sections of the release
+    /// (commits hidden by --max-per-section excepted), warning about any discrepancy
+    #[arg(long)]
+    audit: bool,
+
+    /// Group the bullets of each section under date subheadings, newest first
+    #[arg(long)]
+    by_date: bool,
+
+    /// Append the number of listed commits to the version header, e.g. "· 9 commits"
+    #[arg(long)]
+    commit_count: bool,
+
+    /// Shift all Markdown headings down by N levels, e.g. 1 for "###" release headers,
+    /// to embed the changelog in a larger document
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    base_level: usize,
+
+    /// Template of the Markdown version header, e.g. "## [{version}] - {date}". Supports
+    /// {version}, {tag} (the tag name, see --tag-name-format), {date} and {link} (the
+    /// release URL)
+    #[arg(long, value_name = "TEMPLATE")]
+    version_header_format: Option<String>,
+
+    /// Keep commits with a blank title, and the sections holding only such commits, instead
+    /// of dropping them
+    #[arg(long, alias = "no-filter-empty-sections")]
+    keep_empty_sections: bool,
+
+    /// Add the new section at the end of the changelog instead of the top, for changelogs
+    /// listing the oldest release first
+    #[arg(long)]
+    output_append: bool,
+
+    /// Write the commits marked internal (an "internal" scope or an `Audience: internal`
+    /// trailer) to this changelog instead of the main one
+    #[arg(long, value_name = "PATH")]
+    internal_output: Option<PathBuf>,
+
+    /// Store the generated section as a git note on the released commit (HEAD, or the
+    /// --base-branch tip) instead of writing the changelog file
+    #[arg(long, conflicts_with = "output_fd")]
+    output_git_notes: bool,
+
+    /// Also write the release data (version, date, and the commits of each section) as
+    /// TOML to this file
+    #[arg(long, value_name = "PATH")]
+    output_toml: Option<PathBuf>,
+
+    /// Only compute the version bump and report it as the exit code, without prompting or
+    /// writing anything: 12 for major, 11 for minor, 10 for patch, 0 for nothing to release.
+    /// Implies --non-interactive and silences --explain-ignored
+    #[arg(long, conflicts_with_all = ["list_prompts", "dump_ast"])]
+    exit_bump: bool,
+
+    /// Print the new version to stdout in this format once the changelog is generated
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    version_output_format: Option<VersionOutputFormat>,
+
+    /// Also write the version, date and release notes as a Nix attribute set to this file
+    #[arg(long, value_name = "PATH")]
+    output_nix_manifest: Option<PathBuf>,
+}
+
+/// Returns the closing text of the changelog: `--footer-text`, or the Conventional Commits
+/// note if `--conventional-commits-footer` is set.
+fn changelog_footer(cli: &Cli) -> Option<String> {
+    cli.footer_text.clone().or_else(|| {
+        cli.conventional_commits_footer
+            .then(|| conventional_commits_footer(cli.format))
+    })
+}
+
+/// Returns the note that the changelog follows Conventional Commits, in the given format.
+fn conventional_commits_footer(format: Format) -> String {
+    match format {
+        Format::Markdown => {
+            "This changelog follows [Conventional Commits](https://www.conventionalcommits.org/)."
         }
+        Format::Confluence => {
+            "This changelog follows [Conventional Commits|https://www.conventionalcommits.org/]."
+        }
+        Format::Json => "",
     }
+    .to_string()
+}
 
-    if cli.non_interactive {
-        for (_, cat) in classified.iter_mut() {
-            if cat.is_none() {
-                *cat = Some(CommitCategory::Patch);
-            }
-        }
+/// Renders a release in the output format.
+fn render(format: Format, release: &ReleaseSection, opts: &RenderOptions) -> String {
+    match format {
+        Format::Markdown => render_markdown(release, opts),
+        Format::Confluence => render_confluence(release, opts),
+        Format::Json => render_json(release, opts),
     }
+}
 
-    let mut grouped: HashMap<CommitCategory, Vec<CommitInfo>> = HashMap::new();
-    for (commit, cat_opt) in classified.into_iter() {
-        if let Some(cat) = cat_opt {
-            if cat == CommitCategory::Ignore {
-                continue;
-            }
-            grouped.entry(cat).or_default().push(commit);
-        }
+/// Encodes the version bump of the grouped commits as an exit code, for `--exit-bump`.
+///
+/// # Returns
+///
+/// 12 for a major bump, 11 for minor, 10 for patch, and 0 if there is nothing to release.
+fn bump_exit_code(grouped: &HashMap<CommitCategory, Vec<CommitInfo>>) -> u8 {
+    [
+        (CommitCategory::Major, 12),
+        (CommitCategory::Minor, 11),
+        (CommitCategory::Patch, 10),
+    ]
+    .into_iter()
+    .find(|(cat, _)| grouped.get(cat).is_some_and(|commits| !commits.is_empty()))
+    .map_or(0, |(_, code)| code)
+}
+
+/// Parses a custom category name, e.g. "major=💥:Breaking" or "minor=Features".
+fn parse_category_label(
+    value: &str,
+) -> std::result::Result<(CommitCategory, CategoryLabel), String> {
+    let (cat, label) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected CATEGORY=[EMOJI:]LABEL, got {value}"))?;
+    let cat = CommitCategory::from_str(cat.trim(), true)?;
+    let (emoji, label) = match label.split_once(':') {
+        Some((emoji, label)) => (Some(emoji.trim().to_string()), label),
+        None => (None, label),
+    };
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(format!("the label of {} is empty", cat.name()));
     }
+    Ok((
+        cat,
+        CategoryLabel {
+            emoji: emoji.filter(|emoji| !emoji.is_empty()),
+            label: label.to_string(),
+        },
+    ))
+}
 
-    if !grouped.contains_key(&CommitCategory::Major)
-        && !grouped.contains_key(&CommitCategory::Minor)
-        && !grouped.contains_key(&CommitCategory::Patch)
-    {
-        return Err(anyhow!(
-            "No important commits found, nothing to put into changelog"
-        ));
+/// Parses a `KEY=CATEGORY` pair, e.g. "deps=ignore" for `--scope-category` or
+/// "perf=patch" for `--prefix-override`. The key is lowercased.
+fn parse_key_category(value: &str) -> std::result::Result<(String, CommitCategory), String> {
+    let (key, cat) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=CATEGORY, got {value}"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("the key of {value} is empty"));
+    }
+    Ok((
+        key.to_lowercase(),
+        CommitCategory::from_str(cat.trim(), true)?,
+    ))
+}
+
+/// Parses a similarity threshold between 0.0 and 1.0.
+fn parse_threshold(value: &str) -> std::result::Result<f64, String> {
+    let threshold: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..=1.0).contains(&threshold) {
+        Ok(threshold)
+    } else {
+        Err(format!("{threshold} is not between 0.0 and 1.0"))
     }
+}
 
-    let new_version = if let Some(v) = cli.new_version {
-        let parsed = Version::parse(&v)
+/// Computes the version of the release.
+///
+/// # Arguments
+///
+/// * `last_version` - The previous version
+/// * `grouped` - The included commits, grouped by category
+/// * `requested` - The version given with `--new-version`, if any
+///
+/// # Returns
+///
+/// The requested version, or the previous version bumped according to the commits
+/// (breaking changes bump the minor version and features the patch version before 1.0.0).
+///
+/// # Errors
+///
+/// Returns an error if the requested version is not semver or not greater than the
+/// previous version.
+fn next_version(
+    last_version: &Version,
+    grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+    requested: Option<&str>,
+) -> Result<Version> {
+    if let Some(v) = requested {
+        let parsed = Version::parse(v)
             .with_context(|| format!("Provided version {v} is not valid semver"))?;
-        if parsed <= last_version {
+        if parsed <= *last_version {
             return Err(anyhow!(
                 "New version {} must be greater than previous version {}",
                 parsed,
                 last_version
             ));
         }
-        parsed
-    } else {
-        let unstable = last_version < Version::new(1, 0, 0);
-        let has_major = grouped.contains_key(&CommitCategory::Major);
-        let has_minor = grouped.contains_key(&CommitCategory::Minor);
-
-        if has_major {
-            if unstable {
-                Version::new(last_version.major, last_version.minor + 1, 0)
-            } else {
-                Version::new(last_version.major + 1, 0, 0)
-            }
-        } else if has_minor {
-            if unstable {
-                Version::new(
-                    last_version.major,
-                    last_version.minor,
-                    last_version.patch + 1,
-                )
-            } else {
-                Version::new(last_version.major, last_version.minor + 1, 0)
-            }
-        } else {
-            Version::new(
-                last_version.major,
-                last_version.minor,
-                last_version.patch + 1,
-            )
-        }
-    };
+        return Ok(parsed);
+    }
 
-    println!(
-        "{} previous version {} -> new version {}",
-        "Version".green(),
-        last_version,
-        new_version
-    );
+    let unstable = *last_version < Version::new(1, 0, 0);
+    let has_major = grouped.contains_key(&CommitCategory::Major);
+    let has_minor = grouped.contains_key(&CommitCategory::Minor);
 
-    let remote_info = get_remote_info(&repo);
-    let today = Local::now().date_naive();
+    let (major, minor, patch) = (last_version.major, last_version.minor, last_version.patch);
+    Ok(match (has_major, has_minor, unstable) {
+        (true, _, true) | (false, true, false) => Version::new(major, minor + 1, 0),
+        (true, _, false) => Version::new(major + 1, 0, 0),
+        _ => Version::new(major, minor, patch + 1),
+    })
+}
 
-    let section = build_release_section(
-        &new_version,
-        &last_version,
-        today,
-        remote_info.as_ref(),
+/// Determines the remote repository to link to, from origin or the CI environment.
+///
+/// # Returns
+///
+/// The remote information, or `None` if there is none and `--require-remote` is not set.
+///
+/// # Errors
+///
+/// Returns an error explaining why no remote was found, if `--require-remote` is set.
+fn resolve_remote(
+    repo: &Repository,
+    cli: &Cli,
+    cache: &mut RemoteInfoCache,
+) -> Result<Option<RemoteInfo>> {
+    let err = match cache.get_or_insert(repo) {
+        Ok(remote) => return Ok(Some(remote.clone())),
+        Err(err) => err,
+    };
+    match cli.remote_from_env.then(remote_from_env).flatten() {
+        Some(remote) => Ok(Some(remote)),
+        None if cli.require_remote => Err(err).context("--require-remote is set"),
+        None => Ok(None),
+    }
+}
+
+/// Determines the previous version and the commit to start the changelog from.
+///
+/// Uses `--from-tag` if given, otherwise the latest semver tag. When no tag is found,
+/// `--base-ref` bounds the walk if given, otherwise the full history is used.
+///
+/// With `--last-version-env`, the previous version is read from the environment instead,
+/// and the walk starts from its tag if it exists, otherwise as if no tag was found.
+///
+/// # Returns
+///
+/// A tuple of the previous version and the (exclusive) starting commit, if any.
+///
+/// # Errors
+///
+/// Returns an error if the tag or base ref cannot be resolved, the tag is not semver, or
+/// the `--last-version-env` variable is unset or not semver.
+fn resolve_starting_point(repo: &Repository, cli: &Cli) -> Result<(Version, Option<Oid>)> {
+    starting_point_from_vars(repo, cli, |name| std::env::var(name).ok())
+}
+
+/// Determines the starting point as [`resolve_starting_point`] does, with environment
+/// variables looked up with `lookup`.
+fn starting_point_from_vars(
+    repo: &Repository,
+    cli: &Cli,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(Version, Option<Oid>)> {
+    if let Some(var) = &cli.last_version_env {
+        let value = lookup(var).ok_or_else(|| anyhow!("Environment variable {var} is not set"))?;
+        let version = Version::parse(value.trim().trim_start_matches('v')).with_context(|| {
+            format!("Environment variable {var}={value} is not a semver version")
+        })?;
+        let tag_name = format!(
+            "{}{}",
+            tag_name(cli.tag_name_format.as_deref(), &version),
+            cli.tag_suffix.as_deref().unwrap_or_default()
+        );
+        let since = match repo.revparse_single(&tag_name) {
+            Ok(obj) => Some(obj.peel_to_commit()?.id()),
+            Err(_) => match &cli.base_ref {
+                Some(base_ref) => Some(
+                    resolve_commit(repo, base_ref)
+                        .with_context(|| format!("Could not resolve base ref {base_ref}"))?,
+                ),
+                None => None,
+            },
+        };
+        eprintln!(
+            "{} previous version is {} (from {})",
+            cli.color_scheme.paint("Info", Role::Info),
+            version,
+            var
+        );
+        Ok((version, since))
+    } else if let Some(tag_name) = &cli.from_tag {
+        let obj = repo
+            .revparse_single(tag_name)
+            .with_context(|| format!("Could not find tag {tag_name}"))?;
+        let commit = obj.peel_to_commit().map_err(|_| {
+            // Name what an annotated tag points to rather than the tag object itself.
+            let kind = obj
+                .as_tag()
+                .and_then(|tag| tag.target_type())
+                .or(obj.kind())
+                .map_or_else(|| "unknown object".to_string(), |kind| kind.to_string());
+            anyhow!("Tag {tag_name} points to a {kind}, not a commit")
+        })?;
+        let version = parse_tag_version(tag_name, cli.tag_suffix.as_deref())
+            .with_context(|| format!("Tag {tag_name} does not look like a semver version"))?;
+        Ok((version, Some(commit.id())))
+    } else if let Some((tag, oid, v)) = find_latest_semver_tag(repo, tag_filter(cli))? {
+        eprintln!(
+            "{} latest tag is {} (commit {})",
+            cli.color_scheme.paint("Info", Role::Info),
+            tag,
+            oid
+        );
+        Ok((v, Some(oid)))
+    } else if let Some(base_ref) = &cli.base_ref {
+        let oid = resolve_commit(repo, base_ref)
+            .with_context(|| format!("Could not resolve base ref {base_ref}"))?;
+        eprintln!(
+            "{} no semver git tags found, assuming previous version 0.0.0 and using commits since {}",
+            cli.color_scheme.paint("Info", Role::Info),
+            base_ref
+        );
+        Ok((Version::parse("0.0.0")?, Some(oid)))
+    } else {
+        eprintln!(
+            "{} no semver git tags found, assuming previous version 0.0.0 and using full history",
+            cli.color_scheme.paint("Info", Role::Info)
+        );
+        Ok((Version::parse("0.0.0")?, None))
+    }
+}
+
+/// The `--wrap` width, if bullets are wrapped. Confluence list items cannot span lines,
+/// so only Markdown is wrapped.
+fn wrap_width(cli: &Cli) -> Option<usize> {
+    cli.wrap
+        .filter(|_| cli.format == Format::Markdown)
+        .map(NonZeroUsize::get)
+}
+
+/// Builds the commit prefixes to recognize: the built-in ones (and those of
+/// `--keyword-locale`), overridden or extended by `--prefix-override` and the `[prefixes]`
+/// table of the configuration file.
+///
+/// # Errors
+///
+/// Returns an error if there are no keywords for the requested locale.
+fn keyword_set(cli: &Cli) -> Result<KeywordSet> {
+    let mut keywords = match &cli.keyword_locale {
+        Some(locale) => KeywordSet::for_locale(locale)
+            .ok_or_else(|| anyhow!("No commit keywords available for locale {locale}"))?,
+        None => KeywordSet::default(),
+    };
+    for (prefix, cat) in &cli.prefix_override {
+        keywords.extend(KeywordSet::new([(prefix.as_str(), *cat)]));
+    }
+    Ok(keywords)
+}
+
+/// The tags considered when looking for the previous release.
+fn tag_filter(cli: &Cli) -> TagFilter<'_> {
+    TagFilter {
+        suffix: cli.tag_suffix.as_deref(),
+        pattern: cli.from_tag_pattern.as_ref(),
+        stable_only: false,
+        by_time: cli.latest_by_time,
+    }
+}
+
+/// Reads the commits of a release and adds what the options ask for: the pull requests
+/// of squashed merges, diff stats and signatures.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `cli` - The command line arguments
+/// * `tip` - The commit the release is built from, or `None` for HEAD
+/// * `since` - The commit the release starts after, or `None` for the whole history
+///
+/// # Errors
+///
+/// Returns an error if the commits cannot be read.
+fn collect_commits(
+    repo: &Repository,
+    cli: &Cli,
+    tip: Option<Oid>,
+    since: Option<Oid>,
+) -> Result<Vec<CommitInfo>> {
+    let walk_opts = WalkOptions {
+        branch_filter: cli.branch_filter.clone(),
+        ignore_before: cli.ignore_commits_before,
+        full_oid: cli.show_full_oid,
+    };
+    let mut commits = commits_since(repo, tip, since, &walk_opts)?;
+
+    if cli.strip_merge_pr_commits {
+        attribute_merged_prs(repo, &mut commits)?;
+    }
+
+    if cli.diff_stats {
+        for commit in commits.iter_mut() {
+            let git_commit = repo.find_commit(commit.oid)?;
+            commit.diff_stats = Some(commit_diff_stats(repo, &git_commit));
+        }
+    }
+
+    if cli.signature_badges {
+        for commit in commits.iter_mut() {
+            commit.has_signature = commit_has_signature(repo, commit.oid);
+        }
+    }
+
+    Ok(commits)
+}
+
+/// The commit being released: the --base-branch tip if given, HEAD otherwise.
+///
+/// # Errors
+///
+/// Returns an error if HEAD does not point to a commit.
+fn release_tip(repo: &Repository, tip: Option<Oid>) -> Result<Oid> {
+    match tip {
+        Some(oid) => Ok(oid),
+        None => Ok(repo.head()?.peel_to_commit()?.id()),
+    }
+}
+
+/// Warns if the starting commit is not in the history that will be walked, in which
+/// case the changelog may pick up unrelated commits.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `cli` - Supplies --branch-filter and the color scheme
+/// * `tip` - The --base-branch tip, HEAD if `None`
+/// * `since` - The starting commit
+///
+/// # Returns
+///
+/// Whether the warning was printed.
+///
+/// # Errors
+///
+/// Returns an error if the tips cannot be resolved.
+fn warn_unrelated_start(
+    repo: &Repository,
+    cli: &Cli,
+    tip: Option<Oid>,
+    since: Oid,
+) -> Result<bool> {
+    let tips = walk_tips(repo, tip, cli.branch_filter.as_deref())?;
+    for to in &tips {
+        if is_ancestor(repo, since, *to)? {
+            return Ok(false);
+        }
+    }
+    let tips: Vec<_> = tips.iter().map(Oid::to_string).collect();
+    eprintln!(
+        "{} starting commit {} is not an ancestor of {}, the changelog may include unrelated history",
+        cli.color_scheme.paint("Warning", Role::Warning),
+        cli.color_scheme.paint(since, Role::Highlight),
+        tips.join(", ")
+    );
+    Ok(true)
+}
+
+/// Classifies commits automatically, which also strips the prefix from their summary.
+///
+/// A fixup of a commit in the same list is ignored: that commit already covers it.
+///
+/// # Returns
+///
+/// Each commit with its category, or `None` if it has to be classified by hand.
+fn classify_commits(
+    commits: Vec<CommitInfo>,
+    classify_opts: &ClassifyOptions,
+) -> Vec<(CommitInfo, Option<CommitCategory>)> {
+    let subjects: HashSet<String> = commits.iter().map(|c| c.summary.clone()).collect();
+    commits
+        .into_iter()
+        .map(|mut c| {
+            if fixup_target(&c.summary).is_some_and(|target| subjects.contains(target)) {
+                c.ignore_reason = Some("fixup of a commit in this release".to_string());
+                return (c, Some(CommitCategory::Ignore));
+            }
+            let cat = auto_classify(&mut c, classify_opts);
+            (c, cat)
+        })
+        .collect()
+}
+
+/// The commits since the last stable release, for `--since-last-stable`.
+struct StableView {
+    /// The last stable tag.
+    tag: String,
+    /// Its version.
+    version: Version,
+    /// The commits since, grouped by category.
+    grouped: HashMap<CommitCategory, Vec<CommitInfo>>,
+}
+
+/// Collects the commits since the last stable (non pre-release) tag, for
+/// `--since-last-stable`.
+///
+/// The commits are prepared like those of the release. Commits of the release keep the
+/// category they were given; older ones are classified automatically, falling back to
+/// `--unknown`.
+///
+/// # Arguments
+///
+/// * `repo` - The git repository
+/// * `cli` - The command line arguments
+/// * `tip` - The commit the release is built from, or `None` for HEAD
+/// * `last_version` - The version the release starts from
+/// * `decisions` - The categories of the commits of the release
+/// * `classify_opts` - Classification options
+///
+/// # Returns
+///
+/// The commits since the last stable tag, or `None` if the release already starts from it.
+///
+/// # Errors
+///
+/// Returns an error if the tags or commits cannot be read.
+fn since_last_stable(
+    repo: &Repository,
+    cli: &Cli,
+    tip: Option<Oid>,
+    last_version: &Version,
+    decisions: &HashMap<Oid, CommitCategory>,
+    classify_opts: &ClassifyOptions,
+) -> Result<Option<StableView>> {
+    let filter = TagFilter {
+        stable_only: true,
+        ..tag_filter(cli)
+    };
+    let Some((tag, oid, version)) = find_latest_semver_tag(repo, filter)? else {
+        return Ok(None);
+    };
+    if version == *last_version {
+        return Ok(None);
+    }
+
+    let commits = collect_commits(repo, cli, tip, Some(oid))?;
+    let mut grouped: HashMap<CommitCategory, Vec<CommitInfo>> = HashMap::new();
+    for (commit, auto) in classify_commits(commits, classify_opts) {
+        let cat = match decisions.get(&commit.oid) {
+            Some(&cat) => cat,
+            None => auto.unwrap_or(cli.unknown),
+        };
+        if cat != CommitCategory::Ignore {
+            grouped.entry(cat).or_default().push(commit);
+        }
+    }
+    Ok(Some(StableView {
+        tag,
+        version,
+        grouped,
+    }))
+}
+
+/// Builds the command-line parser: [`Cli`] plus a hidden `--no-<flag>` for every flag, so
+/// that a flag set in a configuration file can be turned off on the command line.
+///
+/// Whichever of `--<flag>` and `--no-<flag>` comes last wins.
+fn cli_command() -> Command {
+    let command = Cli::command();
+    let flags: Vec<(String, String)> = command
+        .get_arguments()
+        .filter(|arg| matches!(arg.get_action(), ArgAction::SetTrue))
+        .filter_map(|arg| Some((arg.get_id().to_string(), arg.get_long()?.to_string())))
+        .collect();
+    flags.into_iter().fold(command, |command, (id, long)| {
+        let negated = format!("no-{long}");
+        if command
+            .get_arguments()
+            .any(|arg| arg.get_long() == Some(negated.as_str()))
+        {
+            return command;
+        }
+        let negated_id = format!("no_{id}");
+        command
+            .mut_arg(&id, |arg| arg.overrides_with(negated_id.clone()))
+            .arg(
+                Arg::new(negated_id)
+                    .long(negated)
+                    .action(ArgAction::SetTrue)
+                    .hide(true)
+                    .overrides_with(id),
+            )
+    })
+}
+
+/// Parses command-line arguments with [`cli_command`].
+///
+/// # Arguments
+///
+/// * `args` - The command line, including the program name
+///
+/// # Errors
+///
+/// Returns an error if the arguments are invalid, or for `--help` and `--version`.
+fn parse_cli<I, T>(args: I) -> clap::error::Result<Cli>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    Cli::from_arg_matches(&cli_command().try_get_matches_from(args)?)
+}
+
+/// Parses the command line on top of a configuration file: the one given with `--config`,
+/// or the repository's `.changelogger.toml` or `changelogger.toml`, if any.
+///
+/// Settings from the file act as defaults: they are placed before the real arguments,
+/// and a later occurrence of an option overrides an earlier one.
+///
+/// # Arguments
+///
+/// * `args` - The command line, including the program name
+/// * `root` - The repository root
+/// * `config` - The file given with `--config`, if any
+///
+/// # Errors
+///
+/// Returns an error if the configuration file cannot be loaded or holds invalid settings.
+fn parse_with_repo_config(args: Vec<OsString>, root: &Path, config: Option<&Path>) -> Result<Cli> {
+    let loaded = match config {
+        Some(path) => Some((path.to_path_buf(), load_config_file(path)?)),
+        None => load_repo_config(root)?,
+    };
+    let Some((path, settings)) = loaded else {
+        return Ok(parse_cli(args)?);
+    };
+
+    let settings = config_args(&settings, root, &cli_command())
+        .with_context(|| format!("Invalid settings in {}", path.display()))?;
+    let mut args = args.into_iter();
+    let merged: Vec<OsString> = args
+        .next()
+        .into_iter()
+        .chain(settings)
+        .chain(args)
+        .collect();
+    parse_cli(merged).with_context(|| format!("Invalid settings in {}", path.display()))
+}
+
+/// Attaches `--build-metadata` or, per `--version-suffix-strategy`, the short id of the
+/// released commit to the new version.
+///
+/// # Arguments
+///
+/// * `version` - The new version
+/// * `cli` - The command-line arguments
+/// * `short_id` - The short id of the commit being released
+///
+/// # Errors
+///
+/// Returns an error if the build metadata is not a valid semver identifier.
+fn apply_version_suffix(mut version: Version, cli: &Cli, short_id: &str) -> Result<Version> {
+    if let Some(metadata) = &cli.build_metadata {
+        version.build = BuildMetadata::new(metadata)
+            .with_context(|| format!("Build metadata {metadata} is not valid semver"))?;
+        return Ok(version);
+    }
+
+    match cli.version_suffix_strategy {
+        VersionSuffixStrategy::Never => {}
+        // The "g" prefix keeps ids like "0123abc" valid, as numeric pre-release ids can't
+        // have leading zeros.
+        VersionSuffixStrategy::Prerelease => {
+            version.pre = Prerelease::new(&format!("g{short_id}"))?
+        }
+        VersionSuffixStrategy::BuildMetadata => version.build = BuildMetadata::new(short_id)?,
+    }
+    Ok(version)
+}
+
+/// Determines the file to write the changelog to.
+///
+/// Uses `--output` if given, otherwise the `output` configured in the repository's
+/// `pyproject.toml` or `Cargo.toml` (relative to the repository root), falling back to
+/// `CHANGELOG.md`.
+fn resolve_output(repo: &Repository, output: Option<&str>) -> String {
+    if let Some(output) = output {
+        return output.to_string();
+    }
+
+    repo.workdir()
+        .and_then(|root| {
+            let configured = find_config_in_manifest(root)?;
+            Some(root.join(configured).to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "CHANGELOG.md".to_string())
+}
+
+/// Refuses to overwrite a changelog with uncommitted changes, which may be manual edits,
+/// unless `--force` is given.
+///
+/// This includes reruns with `--prepend-unreleased` or `--squash-multiple-bumps` over the
+/// uncommitted section of a previous run, which may have been edited by hand since.
+///
+/// # Errors
+///
+/// Returns an error if the output file is tracked and modified, or its status cannot be read.
+fn check_output_clean(repo: &Repository, cli: &Cli, output: &str) -> Result<()> {
+    if cli.force || !writes_file(cli, output) {
+        return Ok(());
+    }
+    if has_uncommitted_changes(repo, Path::new(output))? {
+        return Err(anyhow!(
+            "{output} has uncommitted changes, refusing to overwrite them (use --force)"
+        ));
+    }
+    Ok(())
+}
+
+/// Refuses to replace a changelog with `--format json`, whose output replaces the whole file
+/// instead of being merged into it.
+///
+/// The file must be given explicitly with `--output`, rather than defaulting to the
+/// changelog, and must not already hold anything but JSON.
+///
+/// # Errors
+///
+/// Returns an error if the JSON would be written to the default output, or over an
+/// existing file that is not JSON, or if the file cannot be read.
+fn check_json_output(cli: &Cli, output: &str) -> Result<()> {
+    if cli.format != Format::Json || !writes_file(cli, output) {
+        return Ok(());
+    }
+    if cli.output.is_none() {
+        return Err(anyhow!(
+            "--format json replaces the output file, refusing to write it to {output} \
+             (use --output <PATH>, --output - or --dry-run)"
+        ));
+    }
+    let existing = match std::fs::read_to_string(output) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("Could not read {output}")),
+    };
+    let existing = existing.trim_start();
+    if !existing.is_empty() && !existing.starts_with('{') {
+        return Err(anyhow!(
+            "{output} is not a JSON file, refusing to replace it with --format json"
+        ));
+    }
+    Ok(())
+}
+
+/// Runs a hook command through the platform shell.
+///
+/// The command's output is forwarded to stderr, keeping stdout for the changelog.
+///
+/// # Arguments
+///
+/// * `name` - The name of the hook option, for error messages
+/// * `command` - The shell command to run
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be started or exits unsuccessfully, including
+/// its stderr output.
+fn run_hook(name: &str, command: &str) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = std::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = std::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let output = shell
+        .arg(command)
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .with_context(|| format!("Could not run {name} {command}"))?;
+
+    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{name} {command} failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    Ok(())
+}
+
+/// Returns `true` if the run would write the changelog to a file.
+///
+/// Dry runs, `--output -` and `--output-fd` print the changelog instead.
+fn writes_file(cli: &Cli, output: &str) -> bool {
+    !cli.dry_run
+        && !cli.exit_bump
+        && !cli.output_git_notes
+        && cli.output_fd.is_none()
+        && output != "-"
+}
+
+/// Looks up which of the referenced issues are closed in the GitHub issue tracker.
+///
+/// Failures only print a warning: the release notes are still written, without the marks.
+///
+/// # Returns
+///
+/// The numbers of the closed issues.
+fn closed_issue_ids(
+    cli: &Cli,
+    remote: Option<&RemoteInfo>,
+    grouped: &HashMap<CommitCategory, Vec<CommitInfo>>,
+    syntax: &IssueSyntax,
+) -> HashSet<String> {
+    let warn = |message: String| {
+        eprintln!(
+            "{} {message}",
+            cli.color_scheme.paint("Warning", Role::Warning)
+        );
+        HashSet::new()
+    };
+    let Some(token) = cli
+        .github_token
+        .clone()
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+    else {
+        return warn("--issue-closed-emoji needs --github-token or $GITHUB_TOKEN".to_string());
+    };
+    let Some((owner, repo)) = remote.and_then(github_repo) else {
+        return warn("--issue-closed-emoji only supports github.com repositories".to_string());
+    };
+
+    let mut cache = IssueStateCache::new(&token, &owner, &repo);
+    let mut closed = HashSet::new();
+    for issue in closed_issues(grouped, syntax) {
+        let Ok(number) = issue.id.parse::<u64>() else {
+            continue;
+        };
+        match cache.is_closed(number) {
+            Ok(true) => {
+                closed.insert(issue.id);
+            }
+            Ok(false) => {}
+            Err(err) => return warn(format!("could not check issue #{number}: {err:#}")),
+        }
+    }
+    closed
+}
+
+/// Writes the release of the commits marked internal to the `--internal-output` changelog.
+///
+/// A dry run prints it instead. When the main changelog is not written to a file either
+/// (`--output -`, `--output-fd`, `--output-git-notes`), it is left untouched.
+///
+/// # Arguments
+///
+/// * `cli` - The command line arguments
+/// * `output` - The main changelog
+/// * `path` - The internal changelog
+/// * `release` - The release of the internal commits
+/// * `render_opts` - Rendering options
+///
+/// # Errors
+///
+/// Returns an error if the internal changelog cannot be read or written.
+fn write_internal_changelog(
+    cli: &Cli,
+    output: &str,
+    path: &Path,
+    release: &ReleaseSection,
+    render_opts: &RenderOptions,
+) -> Result<()> {
+    let mut section = render(cli.format, release, render_opts);
+    if let Some(width) = wrap_width(cli) {
+        section = wrap_bullets(&section, width);
+    }
+    if cli.trim_trailing_whitespace {
+        section = trim_trailing_whitespace(&section);
+    }
+    let path = path.to_string_lossy();
+    if cli.dry_run {
+        println!("\n{}", section);
+    } else if writes_file(cli, output) {
+        let internal_opts = WriteOptions {
+            encoding_declaration: cli.encoding_declaration,
+            no_format_version: cli.format == Format::Confluence,
+            append: cli.output_append,
+            footer: changelog_footer(cli),
+            ..Default::default()
+        };
+        write_changelog(&path, &section, &internal_opts)?;
+        eprintln!(
+            "{} updated {}",
+            cli.color_scheme.paint("Success", Role::Success),
+            path
+        );
+    } else {
+        eprintln!(
+            "{} not writing {}, the changelog is not written to a file",
+            cli.color_scheme.paint("Info", Role::Info),
+            path
+        );
+    }
+    Ok(())
+}
+
+/// Moves the commits marked internal out of the grouped commits.
+///
+/// # Returns
+///
+/// The user-facing commits and the internal ones, both grouped by category.
+fn split_internal(
+    grouped: HashMap<CommitCategory, Vec<CommitInfo>>,
+) -> (
+    HashMap<CommitCategory, Vec<CommitInfo>>,
+    HashMap<CommitCategory, Vec<CommitInfo>>,
+) {
+    let mut public = HashMap::new();
+    let mut internal = HashMap::new();
+    for (cat, commits) in grouped {
+        let (private, user_facing): (Vec<_>, Vec<_>) =
+            commits.into_iter().partition(|commit| commit.internal);
+        if !user_facing.is_empty() {
+            public.insert(cat, user_facing);
+        }
+        if !private.is_empty() {
+            internal.insert(cat, private);
+        }
+    }
+    (public, internal)
+}
+
+/// Decides whether to ask the user to classify a commit interactively.
+///
+/// Only unclassified commits are prompted for, and if a filter is given, only those whose
+/// summary matches it. The rest fall back to the `--unknown` category.
+fn should_prompt(commit: &CommitInfo, cat: Option<CommitCategory>, filter: Option<&Regex>) -> bool {
+    cat.is_none() && filter.map_or(true, |re| re.is_match(&commit.summary))
+}
+
+/// Summarizes the categories picked so far, e.g. "5 patch, 3 minor, 2 major so far".
+fn tally_summary(classified: &[(CommitInfo, Option<CommitCategory>)]) -> String {
+    let count = |wanted: CommitCategory| {
+        classified
+            .iter()
+            .filter(|(_, cat)| *cat == Some(wanted))
+            .count()
+    };
+    format!(
+        "{} patch, {} minor, {} major so far",
+        count(CommitCategory::Patch),
+        count(CommitCategory::Minor),
+        count(CommitCategory::Major)
+    )
+}
+
+/// Number of body lines shown under a commit in `--list-prompts`.
+const BODY_PREVIEW_LINES: usize = 3;
+
+/// Describes the interactive prompt for a commit, for `--list-prompts`.
+///
+/// # Returns
+///
+/// The line the prompt shows, followed by a preview of the commit body, if any.
+fn prompt_text(commit: &CommitInfo) -> String {
+    let mut text = format!("Commit {} {}", commit.short_id, commit.summary);
+    for line in commit
+        .body
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(BODY_PREVIEW_LINES)
+    {
+        text.push_str("\n    ");
+        text.push_str(line);
+    }
+    text
+}
+
+/// The ignore reason of a commit classified by hand or by default, if it was ignored.
+fn ignored_by(cat: CommitCategory, source: &str) -> Option<String> {
+    (cat == CommitCategory::Ignore).then(|| format!("ignored by {source}"))
+}
+
+/// Describes why a commit is left out of the changelog, for `--explain-ignored`.
+///
+/// # Returns
+///
+/// E.g. "abc1234 update readme (ignored: docs prefix)".
+fn explain_ignored(commit: &CommitInfo) -> String {
+    format!(
+        "{} {} (ignored: {})",
+        commit.short_id,
+        commit.summary,
+        commit.ignore_reason.as_deref().unwrap_or("unknown reason")
+    )
+}
+
+/// Describes how a commit is parsed and classified, for `--dump-ast`.
+///
+/// # Arguments
+///
+/// * `commit` - The commit, before classification
+/// * `opts` - Classification options
+/// * `syntax` - How issue references are recognized
+///
+/// # Returns
+///
+/// An indented `field: value` block for the commit.
+fn dump_commit(commit: &CommitInfo, opts: &ClassifyOptions, syntax: &IssueSyntax) -> String {
+    let parsed = parse_subject(&commit.summary);
+    let mut classified = commit.clone();
+    let category = auto_classify(&mut classified, opts);
+    let (title, refs) = syntax.extract(&parsed.title);
+
+    let or_none = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let refs: Vec<String> = refs.iter().map(ToString::to_string).collect();
+    [
+        format!("commit {}", commit.short_id),
+        format!("  subject: {}", commit.summary),
+        format!("  type: {}", or_none(parsed.kind)),
+        format!("  scope: {}", or_none(parsed.scope)),
+        format!("  breaking: {}", parsed.breaking),
+        format!(
+            "  category: {}",
+            or_none(category.map(|cat| cat.name().to_string()))
+        ),
+        format!("  section: {}", or_none(classified.section)),
+        format!("  title: {title}"),
+        format!("  refs: {}", refs.join(", ")),
+    ]
+    .join("\n")
+}
+
+/// Main entry point for the changelogger application.
+///
+/// Processes command-line arguments, opens the git repository, finds commits since
+/// the last version tag, classifies commits (interactively or automatically),
+/// determines the new version number, and generates/updates the changelog file.
+///
+/// # Returns
+///
+/// The `--exit-bump` code if set, success otherwise.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The repository cannot be opened
+/// - No commits are found since the starting point
+/// - Version parsing fails
+/// - The changelog file cannot be written
+fn main() -> Result<ExitCode> {
+    let cli = parse_cli(std::env::args_os()).unwrap_or_else(|err| err.exit());
+
+    if let Some(hook) = &cli.pre_run_hook {
+        run_hook("--pre-run-hook", hook)?;
+    }
+
+    let repo = open_repo(&cli.repo)
+        .with_context(|| format!("Could not open git repository at {}", cli.repo))?;
+    let cli = match (repo.workdir(), &cli.config) {
+        (Some(root), config) => {
+            parse_with_repo_config(std::env::args_os().collect(), root, config.as_deref())?
+        }
+        (None, Some(config)) => {
+            parse_with_repo_config(std::env::args_os().collect(), Path::new("."), Some(config))?
+        }
+        (None, None) => cli,
+    };
+    eprintln!(
+        "{}",
+        cli.color_scheme.paint("Opened repository", Role::Highlight)
+    );
+
+    let issue_syntax = IssueSyntax::new(
+        cli.issue_id_pattern.as_deref(),
+        cli.issue_url_template.clone(),
+    )?;
+
+    if cli.format == Format::Json && (cli.since_last_stable || cli.internal_output.is_some()) {
+        return Err(anyhow!(
+            "--format json cannot be combined with --since-last-stable or --internal-output"
+        ));
+    }
+
+    let missing = check_date_format(&cli.date_format)?;
+    if !missing.is_empty() {
+        eprintln!(
+            "{} date format {} has no {} component",
+            cli.color_scheme.paint("Warning", Role::Warning),
+            cli.date_format,
+            missing.join(", ")
+        );
+    }
+
+    let output = resolve_output(&repo, cli.output.as_deref());
+    check_json_output(&cli, &output)?;
+    if cli.read_only && writes_file(&cli, &output) {
+        return Err(anyhow!(
+            "--read-only is set, refusing to write {output} (use --dry-run or --output -)"
+        ));
+    }
+    if cli.read_only && cli.output_git_notes && !cli.dry_run {
+        return Err(anyhow!("--read-only is set, refusing to write a git note"));
+    }
+    if let (true, false, Some(path)) = (cli.read_only, cli.dry_run, &cli.internal_output) {
+        return Err(anyhow!(
+            "--read-only is set, refusing to write {}",
+            path.display()
+        ));
+    }
+    for path in [&cli.output_nix_manifest, &cli.output_toml] {
+        if let (true, false, Some(path)) = (cli.read_only, cli.dry_run, path) {
+            return Err(anyhow!(
+                "--read-only is set, refusing to write {}",
+                path.display()
+            ));
+        }
+    }
+    if let (true, Some(path)) = (cli.read_only, &cli.checkpoint) {
+        return Err(anyhow!(
+            "--read-only is set, refusing to write checkpoint {}",
+            path.display()
+        ));
+    }
+    check_output_clean(&repo, &cli, &output)?;
+
+    let (last_version, since_oid) = resolve_starting_point(&repo, &cli)?;
+
+    let tip = match &cli.base_branch {
+        Some(branch) => Some(resolve_branch(&repo, branch)?),
+        None => None,
+    };
+
+    let since_oid = match &cli.merge_base {
+        Some(branch) => {
+            let oid = merge_base(&repo, release_tip(&repo, tip)?, branch)?;
+            eprintln!(
+                "{} using commits since the merge base with {} (commit {})",
+                cli.color_scheme.paint("Info", Role::Info),
+                branch,
+                oid
+            );
+            Some(oid)
+        }
+        None => since_oid,
+    };
+
+    if let Some(since) = since_oid {
+        warn_unrelated_start(&repo, &cli, tip, since)?;
+    }
+
+    let compare_range = match (cli.compare_by, since_oid) {
+        (CompareBy::Shas, Some(since)) => {
+            Some((since.to_string(), release_tip(&repo, tip)?.to_string()))
+        }
+        _ => None,
+    };
+
+    let commits = collect_commits(&repo, &cli, tip, since_oid)?;
+    if commits.is_empty() {
+        return Err(anyhow!("No commits found since starting point"));
+    }
+
+    let classify_opts = ClassifyOptions {
+        keywords: keyword_set(&cli)?,
+        scope_as_section: cli.scope_as_section,
+        ignore_merges: cli.strip_merge_pr_commits,
+        categories_from_labels: cli.categories_from_labels,
+        reverts_section: cli.reverts_section.then_some(cli.revert_bump),
+        keep_release_commits: cli.keep_release_commits,
+        tag_name_format: cli.tag_name_format.clone(),
+        lax: cli.conventional_commits_lax,
+        split_slash_types: cli.split_slash_types,
+        scope_categories: cli.scope_category.iter().cloned().collect(),
+        fold_fixups: cli.fold_fixups,
+    };
+
+    if cli.dump_ast {
+        for commit in &commits {
+            println!("{}", dump_commit(commit, &classify_opts, &issue_syntax));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let prompt_filter = match &cli.prompt_only_matching {
+        Some(pattern) => Some(
+            Regex::new(pattern)
+                .with_context(|| format!("Invalid --prompt-only-matching regex {pattern}"))?,
+        ),
+        None => None,
+    };
+
+    let mut classified = classify_commits(commits, &classify_opts);
+
+    let mut checkpoint = match &cli.checkpoint {
+        Some(path) => load_checkpoint(path)?,
+        None => Default::default(),
+    };
+    let mut resumed = 0;
+    for (commit, cat) in classified.iter_mut() {
+        if let (None, Some(saved)) = (*cat, checkpoint.get(&commit.oid.to_string())) {
+            *cat = Some(*saved);
+            commit.ignore_reason = ignored_by(*saved, "checkpoint");
+            resumed += 1;
+        }
+    }
+    if resumed > 0 {
+        eprintln!(
+            "{} resumed {} decisions from the checkpoint",
+            cli.color_scheme.paint("Info", Role::Info),
+            resumed
+        );
+    }
+
+    if cli.list_prompts {
+        for (commit, cat) in &classified {
+            if should_prompt(commit, *cat, prompt_filter.as_ref()) {
+                println!("{}", prompt_text(commit));
+            }
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let interactive = !cli.non_interactive && !cli.exit_bump;
+    if interactive {
+        static ITEMS: &[&str] = &["patch", "minor", "major", "ignore"];
+        let theme = ColorfulTheme::default();
+        let mut prompted = 0;
+        let mut stopped = false;
+        for i in 0..classified.len() {
+            let (commit, cat) = &classified[i];
+            if !should_prompt(commit, *cat, prompt_filter.as_ref()) {
+                continue;
+            }
+
+            if stopped {
+                classified[i].1 = Some(cli.batch_default);
+                classified[i].0.ignore_reason = ignored_by(cli.batch_default, "--batch-default");
+                continue;
+            }
+
+            eprintln!(
+                "\n{} {} {}",
+                "Commit".bold(),
+                cli.color_scheme.paint(&commit.short_id, Role::Highlight),
+                commit.summary.bold()
+            );
+
+            let choice = Select::with_theme(&theme)
+                .with_prompt("Select type")
+                .items(ITEMS)
+                .default(0)
+                .interact()
+                .unwrap_or(0);
+
+            let selected = match ITEMS[choice] {
+                "patch" => CommitCategory::Patch,
+                "minor" => CommitCategory::Minor,
+                "major" => CommitCategory::Major,
+                _ => CommitCategory::Ignore,
+            };
+
+            classified[i].1 = Some(selected);
+            classified[i].0.ignore_reason = ignored_by(selected, "prompt");
+            prompted += 1;
+
+            if let Some(path) = &cli.checkpoint {
+                checkpoint.insert(classified[i].0.oid.to_string(), selected);
+                save_checkpoint(path, &checkpoint)?;
+            }
+
+            let remaining = classified[i + 1..]
+                .iter()
+                .any(|(c, cat)| should_prompt(c, *cat, prompt_filter.as_ref()));
+            if let Some(batch_size) = cli.interactive_batch_size {
+                if remaining && prompted % batch_size.get() == 0 {
+                    eprintln!("\n{}", tally_summary(&classified));
+                    stopped = !Confirm::with_theme(&theme)
+                        .with_prompt("Continue?")
+                        .default(true)
+                        .interact()
+                        .unwrap_or(false);
+                }
+            }
+        }
+    }
+
+    for (commit, cat) in classified.iter_mut() {
+        if cat.is_none() {
+            *cat = Some(cli.unknown);
+            commit.ignore_reason = ignored_by(cli.unknown, "--unknown");
+        }
+    }
+
+    if let Some(threshold) = cli.fuzzy_dedup_threshold {
+        let included: Vec<usize> = (0..classified.len())
+            .filter(|&i| classified[i].1 != Some(CommitCategory::Ignore))
+            .collect();
+        let commits: Vec<&CommitInfo> = included.iter().map(|&i| &classified[i].0).collect();
+        let pairs = find_near_duplicates(&commits, threshold);
+
+        let theme = ColorfulTheme::default();
+        for (a, b, similarity) in pairs {
+            let (a, b) = (included[a], included[b]);
+            let dropped = |i: usize| classified[i].1 == Some(CommitCategory::Ignore);
+            if dropped(a) || dropped(b) {
+                continue;
+            }
+
+            eprintln!(
+                "{} possible duplicates ({:.0}% similar):\n  {} {}\n  {} {}",
+                cli.color_scheme.paint("Warning", Role::Warning),
+                similarity * 100.0,
+                classified[a].0.short_id,
+                classified[a].0.summary,
+                classified[b].0.short_id,
+                classified[b].0.summary
+            );
+            if !interactive {
+                continue;
+            }
+
+            let items = [
+                "keep both".to_string(),
+                format!("keep only {}", classified[a].0.short_id),
+                format!("keep only {}", classified[b].0.short_id),
+            ];
+            let choice = Select::with_theme(&theme)
+                .with_prompt("Which to keep?")
+                .items(&items)
+                .default(0)
+                .interact()
+                .unwrap_or(0);
+            let (dropped, kept) = match choice {
+                1 => (b, a),
+                2 => (a, b),
+                _ => continue,
+            };
+            classified[dropped].1 = Some(CommitCategory::Ignore);
+            classified[dropped].0.ignore_reason =
+                Some(format!("duplicate of {}", classified[kept].0.short_id));
+        }
+    }
+
+    if cli.exclude_no_issue {
+        for (commit, cat) in classified.iter_mut() {
+            if cat.is_some_and(|cat| cat != CommitCategory::Ignore)
+                && !has_issue_ref(commit, &issue_syntax)
+            {
+                eprintln!(
+                    "{} skipping {} {}: no issue reference",
+                    cli.color_scheme.paint("Warning", Role::Warning),
+                    commit.short_id,
+                    commit.summary
+                );
+                *cat = Some(CommitCategory::Ignore);
+                commit.ignore_reason = Some("no issue reference".to_string());
+            }
+        }
+    }
+
+    if cli.explain_ignored && !cli.exit_bump {
+        for (commit, _) in classified
+            .iter()
+            .filter(|(_, cat)| *cat == Some(CommitCategory::Ignore))
+        {
+            println!("{}", explain_ignored(commit));
+        }
+    }
+
+    let decisions: HashMap<Oid, CommitCategory> = classified
+        .iter()
+        .filter_map(|(commit, cat)| cat.map(|cat| (commit.oid, cat)))
+        .collect();
+    let included: Vec<(CommitInfo, CommitCategory)> = if cli.audit {
+        classified
+            .iter()
+            .filter_map(|(commit, cat)| cat.map(|cat| (commit.clone(), cat)))
+            // The internal commits are audited in their own changelog.
+            .filter(|(commit, _)| !commit.internal || cli.internal_output.is_none())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut grouped: HashMap<CommitCategory, Vec<CommitInfo>> = HashMap::new();
+    for (commit, cat_opt) in classified.into_iter() {
+        if let Some(cat) = cat_opt {
+            if cat == CommitCategory::Ignore {
+                continue;
+            }
+            grouped.entry(cat).or_default().push(commit);
+        }
+    }
+
+    if cli.exit_bump {
+        remove_checkpoint(&cli)?;
+        return Ok(ExitCode::from(bump_exit_code(&grouped)));
+    }
+
+    if !grouped.contains_key(&CommitCategory::Major)
+        && !grouped.contains_key(&CommitCategory::Minor)
+        && !grouped.contains_key(&CommitCategory::Patch)
+    {
+        return Err(anyhow!(
+            "No important commits found, nothing to put into changelog"
+        ));
+    }
+
+    if cli.warn_duplicate_issue_refs {
+        for (issue, commits) in find_duplicate_issue_refs(&grouped, &issue_syntax) {
+            eprintln!(
+                "{} issue #{} is referenced by multiple commits: {}",
+                cli.color_scheme.paint("Warning", Role::Warning),
+                issue,
+                commits.join(", ")
+            );
+        }
+    }
+
+    let new_version = next_version(&last_version, &grouped, cli.new_version.as_deref())?;
+
+    let short_id = repo
+        .find_object(release_tip(&repo, tip)?, None)?
+        .short_id()?;
+    let new_version =
+        apply_version_suffix(new_version, &cli, short_id.as_str().unwrap_or_default())?;
+
+    let bump = [CommitCategory::Major, CommitCategory::Minor]
+        .into_iter()
+        .find(|cat| grouped.contains_key(cat))
+        .unwrap_or(CommitCategory::Patch);
+    eprintln!(
+        "{} previous version {} -> new version {} ({} changes)",
+        cli.color_scheme.paint("Version", Role::Success),
+        last_version,
+        new_version,
+        colorize_category(bump, cli.color_scheme)
+    );
+
+    let mut remote_cache = RemoteInfoCache::default();
+    let remote_info = resolve_remote(&repo, &cli, &mut remote_cache)?;
+    let today = Local::now().date_naive();
+
+    let render_opts = RenderOptions {
+        summary_line: cli.summary_line,
+        tag_prefix_in_header: cli.tag_prefix_in_header,
+        strip_trailing_period: cli.strip_trailing_period,
+        date_format: Some(cli.date_format.clone()),
+        no_release_link: cli.no_release_link,
+        issues_index: cli.issues_index,
+        compare_range,
+        max_per_section: cli.max_per_section,
+        contributors: cli.contributors,
+        contributors_count: cli.contributors_count,
+        exclude_bots: cli.exclude_bots,
+        group_security_separate: cli.group_security_separate,
+        list_format: cli.list_format,
+        only: cli.only.clone(),
+        lang: cli.lang,
+        keep_empty_sections: cli.keep_empty_sections,
+        version_header_format: cli.version_header_format.clone(),
+        base_level: cli.base_level,
+        commit_count: cli.commit_count,
+        by_date: cli.by_date,
+        category_labels: cli.category_label.iter().cloned().collect(),
+        compact: cli.compact,
+        breaking_notes: cli.breaking_notes,
+        issue_syntax: issue_syntax.clone(),
+        tag_name_format: cli.tag_name_format.clone(),
+        closed_issue_ids: if cli.issue_closed_emoji {
+            closed_issue_ids(&cli, remote_info.as_ref(), &grouped, &issue_syntax)
+        } else {
+            HashSet::new()
+        },
+        unreleased: cli.prepend_unreleased.then(|| cli.unreleased_label.clone()),
+    };
+
+    let grouped = match &cli.internal_output {
+        Some(path) => {
+            let (public, internal) = split_internal(grouped);
+            if !internal.is_empty() {
+                let internal_release = build_release_section(
+                    &new_version,
+                    &last_version,
+                    today,
+                    remote_info.as_ref(),
+                    &internal,
+                    &render_opts,
+                );
+                write_internal_changelog(&cli, &output, path, &internal_release, &render_opts)?;
+            }
+            public
+        }
+        None => grouped,
+    };
+
+    let release = build_release_section(
+        &new_version,
+        &last_version,
+        today,
+        remote_info.as_ref(),
         &grouped,
+        &render_opts,
     );
+    let mut section = render(cli.format, &release, &render_opts);
+    if cli.since_last_stable {
+        if let Some(stable) =
+            since_last_stable(&repo, &cli, tip, &last_version, &decisions, &classify_opts)?
+        {
+            let stable_opts = RenderOptions {
+                unreleased: Some(format!("Since {}", stable.tag)),
+                base_level: cli.base_level + 1,
+                ..render_opts.clone()
+            };
+            let stable_release = build_release_section(
+                &new_version,
+                &stable.version,
+                today,
+                remote_info.as_ref(),
+                &stable.grouped,
+                &stable_opts,
+            );
+            section.push_str(&render(cli.format, &stable_release, &stable_opts));
+        }
+    }
+    if let Some(width) = wrap_width(&cli) {
+        section = wrap_bullets(&section, width);
+    }
+    if cli.trim_trailing_whitespace {
+        section = trim_trailing_whitespace(&section);
+    }
 
-    if cli.dry_run {
+    if cli.audit {
+        for problem in audit_sections(&included, &release, &render_opts) {
+            eprintln!(
+                "{} audit: {}",
+                cli.color_scheme.paint("Warning", Role::Warning),
+                problem
+            );
+        }
+    }
+
+    if cli.verify_links {
+        let unreleased = release.new_tag_links(&render_opts);
+        let mut links = extract_links(&section);
+        links.retain(|url| !unreleased.contains(url));
+        let broken = find_broken_links(&links)?;
+        for (url, problem) in &broken {
+            eprintln!(
+                "{} broken link {} ({})",
+                cli.color_scheme.paint("Warning", Role::Warning),
+                url,
+                problem
+            );
+        }
+        if cli.fail_on_broken_links && !broken.is_empty() {
+            return Err(anyhow!(
+                "{} broken links in the generated section",
+                broken.len()
+            ));
+        }
+    }
+
+    let mut write_opts = WriteOptions {
+        encoding_declaration: cli.encoding_declaration,
+        no_format_version: cli.format == Format::Confluence,
+        append: cli.output_append,
+        replace_heading: cli
+            .prepend_unreleased
+            .then(|| unreleased_heading(&cli.unreleased_label, cli.base_level)),
+        footer: changelog_footer(&cli),
+    };
+
+    let existing_path = if output == "-" || cli.output_fd.is_some() {
+        &cli.input
+    } else {
+        &output
+    };
+    let existing = read_existing(Path::new(existing_path))
+        .with_context(|| format!("Could not read {existing_path}"))?;
+    if !cli.dry_run
+        && !cli.output_git_notes
+        && cli.format != Format::Json
+        && !existing.trim().is_empty()
+    {
+        match format_version(&existing) {
+            Some(FORMAT_VERSION) => {}
+            Some(version) => eprintln!(
+                "{} {} has changelogger format version {}, expected {}; it may not be updated correctly",
+                cli.color_scheme.paint("Warning", Role::Warning),
+                existing_path,
+                version,
+                FORMAT_VERSION
+            ),
+            None if is_generated(&existing) => {}
+            None => eprintln!(
+                "{} {} has no changelogger format version comment; it may have been created by \
+                 another tool, and replacing sections (--prepend-unreleased, \
+                 --squash-multiple-bumps) may not work correctly",
+                cli.color_scheme.paint("Warning", Role::Warning),
+                existing_path
+            ),
+        }
+    }
+
+    if cli.squash_multiple_bumps {
+        if let Some(heading) = squashed_heading(&existing, &new_version, &last_version) {
+            eprintln!(
+                "{} {} already has a section for {}, replacing it",
+                cli.color_scheme.paint("Info", Role::Info),
+                existing_path,
+                new_version
+            );
+            write_opts.replace_heading = Some(heading);
+        }
+    }
+
+    if cli.dry_run && cli.format == Format::Json {
+        print!("{section}");
+    } else if cli.dry_run {
         println!("\n{}", section);
+    } else if cli.output_git_notes {
+        let released = repo.find_commit(release_tip(&repo, tip)?)?;
+        write_note(&repo, released.id(), &section)?;
+        eprintln!(
+            "{} added note to {}",
+            cli.color_scheme.paint("Success", Role::Success),
+            cli.color_scheme.paint(
+                released
+                    .as_object()
+                    .short_id()?
+                    .as_str()
+                    .unwrap_or_default(),
+                Role::Highlight
+            )
+        );
+        if let Some(hook) = &cli.post_run_hook {
+            run_hook("--post-run-hook", hook)?;
+        }
+    } else if cli.format == Format::Json {
+        // Release data replaces the output rather than being merged into a changelog.
+        match cli.output_fd {
+            Some(fd) => open_output_fd(fd)?.write_all(section.as_bytes())?,
+            None if output == "-" => print!("{section}"),
+            None => {
+                std::fs::write(&output, &section)
+                    .with_context(|| format!("Could not write {output}"))?;
+                eprintln!(
+                    "{} wrote {}",
+                    cli.color_scheme.paint("Success", Role::Success),
+                    output
+                );
+                if let Some(hook) = &cli.post_run_hook {
+                    run_hook("--post-run-hook", hook)?;
+                }
+            }
+        }
+    } else if let Some(fd) = cli.output_fd {
+        let mut out = open_output_fd(fd)?;
+        print_changelog(&mut out, &cli.input, &section, &write_opts)?;
+    } else if output == "-" {
+        print_changelog(&mut std::io::stdout(), &cli.input, &section, &write_opts)?;
     } else {
-        write_changelog(&cli.output, &section)?;
-        println!("{} updated {}", "Success".bright_green(), &cli.output);
+        write_changelog(&output, &section, &write_opts)?;
+        eprintln!(
+            "{} updated {}",
+            cli.color_scheme.paint("Success", Role::Success),
+            output
+        );
+        if let Some(hook) = &cli.post_run_hook {
+            run_hook("--post-run-hook", hook)?;
+        }
+    }
+
+    if let (Some(path), false) = (&cli.output_nix_manifest, cli.dry_run) {
+        std::fs::write(path, nix_manifest(&new_version, today, &section))
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        eprintln!(
+            "{} wrote {}",
+            cli.color_scheme.paint("Success", Role::Success),
+            path.display()
+        );
+    }
+
+    if let (Some(path), false) = (&cli.output_toml, cli.dry_run) {
+        let data = release_toml(&release, &render_opts)?;
+        std::fs::write(path, data)
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        eprintln!(
+            "{} wrote {}",
+            cli.color_scheme.paint("Success", Role::Success),
+            path.display()
+        );
+    }
+
+    if let Some(format) = cli.version_output_format {
+        println!("{}", format.format(&new_version));
     }
 
+    remove_checkpoint(&cli)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Finds the section that `--squash-multiple-bumps` replaces: the latest release of the
+/// changelog, if it has the new version and is newer than the last tag, so that it comes
+/// from an earlier run of this release cycle.
+///
+/// # Arguments
+///
+/// * `existing` - The changelog content
+/// * `new_version` - The version being released
+/// * `last_version` - The version of the last tag
+///
+/// # Returns
+///
+/// The heading of the section to replace, if any.
+fn squashed_heading(
+    existing: &str,
+    new_version: &Version,
+    last_version: &Version,
+) -> Option<String> {
+    latest_release_heading(existing)
+        .filter(|(version, _)| version == new_version && version > last_version)
+        .map(|(_, heading)| heading)
+}
+
+/// Deletes the checkpoint of a finished run, unless `--keep-checkpoint` or `--dry-run` is
+/// set: a dry run is usually followed by the real one, which should not have to ask again.
+///
+/// # Errors
+///
+/// Returns an error if the checkpoint exists but cannot be removed.
+fn remove_checkpoint(cli: &Cli) -> Result<()> {
+    if let (Some(path), false, false) = (&cli.checkpoint, cli.keep_checkpoint, cli.dry_run) {
+        if path.exists() {
+            std::fs::remove_file(path)
+                .with_context(|| format!("Could not remove checkpoint {}", path.display()))?;
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::{CONFIG_FILE, HIDDEN_CONFIG_FILE};
+    use git2::Signature;
+    use tempfile::TempDir;
+
+    fn init_repo() -> (TempDir, Repository) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        (dir, repo)
+    }
+
+    fn commit(repo: &Repository, message: &str) -> Oid {
+        let parent = repo
+            .refname_to_id("HEAD")
+            .ok()
+            .map(|oid| repo.find_commit(oid).unwrap());
+        let tree_oid = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree_oid).unwrap();
+        let sig = Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        parse_cli(std::iter::once("changelogger").chain(args.iter().copied())).unwrap()
+    }
+
+    fn commit_info(summary: &str) -> CommitInfo {
+        CommitInfo {
+            oid: Oid::zero(),
+            short_id: "abc1234".to_string(),
+            abbrev_id: "abc1234".to_string(),
+            summary: summary.to_string(),
+            body: String::new(),
+            section: None,
+            parent_count: 1,
+            pr_number: None,
+            diff_stats: None,
+            author_name: String::new(),
+            author_email: String::new(),
+            has_signature: false,
+            internal: false,
+            time: 0,
+            ignore_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_should_prompt_only_matching() {
+        let filter = Regex::new("(?i)api|auth").unwrap();
+        let commits = [
+            commit_info("rework auth tokens"),
+            commit_info("bump deps"),
+            commit_info("new API endpoint"),
+            commit_info("typo"),
+        ];
+
+        let prompted: Vec<&str> = commits
+            .iter()
+            .filter(|c| should_prompt(c, None, Some(&filter)))
+            .map(|c| c.summary.as_str())
+            .collect();
+        assert_eq!(prompted, vec!["rework auth tokens", "new API endpoint"]);
+
+        // Without a filter every unclassified commit is prompted for.
+        assert!(commits.iter().all(|c| should_prompt(c, None, None)));
+
+        // Classified commits are never prompted for.
+        assert!(!should_prompt(
+            &commits[0],
+            Some(CommitCategory::Minor),
+            Some(&filter)
+        ));
+    }
+
+    #[test]
+    fn test_tally_summary() {
+        let classified = vec![
+            (commit_info("a"), Some(CommitCategory::Patch)),
+            (commit_info("b"), Some(CommitCategory::Minor)),
+            (commit_info("c"), Some(CommitCategory::Patch)),
+            (commit_info("d"), Some(CommitCategory::Ignore)),
+            (commit_info("e"), Some(CommitCategory::Major)),
+            (commit_info("f"), None),
+        ];
+        assert_eq!(
+            tally_summary(&classified),
+            "2 patch, 1 minor, 1 major so far"
+        );
+    }
+
+    #[test]
+    fn test_parse_with_repo_config() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let args = |extra: &[&str]| -> Vec<OsString> {
+            std::iter::once("changelogger")
+                .chain(extra.iter().copied())
+                .map(OsString::from)
+                .collect()
+        };
+
+        let parsed = parse_with_repo_config(args(&[]), root, None).unwrap();
+        assert_eq!(parsed.output, None);
+
+        std::fs::write(
+            root.join(CONFIG_FILE),
+            "output = \"HISTORY.md\"\nsummary_line = true\n",
+        )
+        .unwrap();
+        let parsed = parse_with_repo_config(args(&[]), root, None).unwrap();
+        let expected = root.join("HISTORY.md");
+        assert_eq!(parsed.output.as_deref(), expected.to_str());
+        assert!(parsed.summary_line);
+
+        let parsed = parse_with_repo_config(args(&["--output", "OTHER.md"]), root, None).unwrap();
+        assert_eq!(parsed.output.as_deref(), Some("OTHER.md"));
+
+        std::fs::write(root.join(CONFIG_FILE), "no_such_setting = 1\n").unwrap();
+        assert!(parse_with_repo_config(args(&[]), root, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_with_hidden_config_precedence() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let args = |extra: &[&str]| -> Vec<OsString> {
+            std::iter::once("changelogger")
+                .chain(extra.iter().copied())
+                .map(OsString::from)
+                .collect()
+        };
+        std::fs::write(
+            root.join(HIDDEN_CONFIG_FILE),
+            "dry_run = true\nnon_interactive = true\nfrom_tag = \"v1.0.0\"\n\
+             [prefix_overrides]\nperf = \"patch\"\nbuild = \"ignore\"\n",
+        )
+        .unwrap();
+
+        let parsed = parse_with_repo_config(args(&[]), root, None).unwrap();
+        assert!(parsed.dry_run);
+        assert!(parsed.non_interactive);
+        assert_eq!(parsed.from_tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            parsed.prefix_override,
+            [
+                ("build".to_string(), CommitCategory::Ignore),
+                ("perf".to_string(), CommitCategory::Patch),
+            ]
+        );
+
+        // The command line comes last, so its values win.
+        let parsed = parse_with_repo_config(
+            args(&["--from-tag", "v2.0.0", "--prefix-override", "perf=minor"]),
+            root,
+            None,
+        )
+        .unwrap();
+        assert_eq!(parsed.from_tag.as_deref(), Some("v2.0.0"));
+        assert_eq!(
+            parsed.prefix_override.last(),
+            Some(&("perf".to_string(), CommitCategory::Minor))
+        );
+
+        // Flags set in the file are turned off with --no-<flag>.
+        let parsed =
+            parse_with_repo_config(args(&["--no-dry-run", "--no-non-interactive"]), root, None)
+                .unwrap();
+        assert!(!parsed.dry_run);
+        assert!(!parsed.non_interactive);
+        let parsed =
+            parse_with_repo_config(args(&["--no-dry-run", "--dry-run"]), root, None).unwrap();
+        assert!(parsed.dry_run);
+
+        std::fs::write(root.join(HIDDEN_CONFIG_FILE), "dry_run = [\n").unwrap();
+        let err = parse_with_repo_config(args(&[]), root, None).unwrap_err();
+        assert!(format!("{err:#}").contains(HIDDEN_CONFIG_FILE));
+
+        std::fs::write(
+            root.join(HIDDEN_CONFIG_FILE),
+            "[prefix_overrides]\nperf = \"huge\"\n",
+        )
+        .unwrap();
+        let err = parse_with_repo_config(args(&[]), root, None).unwrap_err();
+        assert!(format!("{err:#}").contains("Invalid settings in"));
+    }
+
+    #[test]
+    fn test_group_security_separate_requires_labels() {
+        let args = ["changelogger", "--group-security-separate"];
+        let err = parse_cli(args).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+        assert!(
+            cli(&["--group-security-separate", "--categories-from-labels"]).group_security_separate
+        );
+    }
+
+    #[test]
+    fn test_keep_empty_sections() {
+        assert!(!cli(&[]).keep_empty_sections);
+        assert!(cli(&["--keep-empty-sections"]).keep_empty_sections);
+        assert!(cli(&["--no-filter-empty-sections"]).keep_empty_sections);
+        assert!(!RenderOptions::default().keep_empty_sections);
+    }
+
+    #[test]
+    fn test_squashed_heading() {
+        let last = Version::new(1, 0, 0);
+        let new = Version::new(1, 1, 0);
+        let markdown = "## Version 1.1.0 (2024-01-02)\n\n* feat\n\n## Version 1.0.0 (2024-01-01)\n";
+        assert_eq!(
+            squashed_heading(markdown, &new, &last).as_deref(),
+            Some("## Version 1.1.0 (2024-01-02)")
+        );
+        let confluence =
+            "h2. [Version 1.1.0|https://example.com/releases/tag/v1.1.0] (2024-01-02)\n\n\
+                          * feat\n\nh2. Version 1.0.0 (2024-01-01)\n";
+        assert_eq!(
+            squashed_heading(confluence, &new, &last).as_deref(),
+            Some("h2. [Version 1.1.0|https://example.com/releases/tag/v1.1.0] (2024-01-02)")
+        );
+
+        // A bump to another version, or a section of an already tagged release, is kept.
+        assert_eq!(
+            squashed_heading(markdown, &Version::new(2, 0, 0), &last),
+            None
+        );
+        assert_eq!(squashed_heading(markdown, &new, &new), None);
+        assert_eq!(squashed_heading("", &new, &last), None);
+    }
+
+    #[test]
+    fn test_remove_checkpoint() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        let checkpoint = path.to_str().unwrap();
+        std::fs::write(&path, "{}").unwrap();
+
+        remove_checkpoint(&cli(&["--checkpoint", checkpoint, "--dry-run"])).unwrap();
+        assert!(path.exists());
+        let args = ["--checkpoint", checkpoint, "--keep-checkpoint"];
+        remove_checkpoint(&cli(&args)).unwrap();
+        assert!(path.exists());
+        remove_checkpoint(&cli(&["--checkpoint", checkpoint])).unwrap();
+        assert!(!path.exists());
+        remove_checkpoint(&cli(&["--checkpoint", checkpoint])).unwrap();
+    }
+
+    #[test]
+    fn test_negated_flags() {
+        assert!(cli(&["--dry-run"]).dry_run);
+        assert!(!cli(&["--dry-run", "--no-dry-run"]).dry_run);
+        assert!(cli(&["--no-dry-run", "--dry-run"]).dry_run);
+        assert!(!cli(&["--no-summary-line"]).summary_line);
+        // Hidden from the help, which mentions them once instead.
+        let help = cli_command().render_help().to_string();
+        assert!(!help.contains("--no-summary-line"));
+        assert!(help.contains("--no-<flag>"));
+    }
+
+    #[test]
+    fn test_read_only_refuses_hooks() {
+        for hook in ["--pre-run-hook", "--post-run-hook"] {
+            assert!(parse_cli(["changelogger", "--read-only", hook, "true"]).is_err());
+            assert!(parse_cli([
+                "changelogger",
+                "--read-only",
+                "--no-read-only",
+                hook,
+                "true"
+            ])
+            .is_ok());
+        }
+
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(CONFIG_FILE), "read_only = true\n").unwrap();
+        let args = ["changelogger", "--post-run-hook", "true"].map(OsString::from);
+        assert!(parse_with_repo_config(args.to_vec(), root, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_with_repo_config_rejects_hooks() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let args = vec![OsString::from("changelogger")];
+        for setting in [
+            "post_run_hook = \"touch pwned\"",
+            "pre-run-hook = \"touch pwned\"",
+            "repo = \"../other\"",
+            "[templates]\nbullet = \"* {title}\"",
+        ] {
+            std::fs::write(root.join(CONFIG_FILE), format!("{setting}\n")).unwrap();
+            let err = parse_with_repo_config(args.clone(), root, None).unwrap_err();
+            assert!(
+                format!("{err:#}").contains("Invalid settings in"),
+                "{setting}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_with_repo_config_names_bad_settings() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let args = vec![OsString::from("changelogger")];
+        for (setting, expected) in [
+            ("no_such_setting = true", "Unknown setting no_such_setting"),
+            (
+                "non_interactive = 1",
+                "non_interactive must be true or false",
+            ),
+            ("format = \"html\"", "Invalid value html for format"),
+        ] {
+            std::fs::write(root.join(CONFIG_FILE), format!("{setting}\n")).unwrap();
+            let err = parse_with_repo_config(args.clone(), root, None).unwrap_err();
+            assert!(format!("{err:#}").contains(expected), "{setting}: {err:#}");
+        }
+
+        // An alias negates the flag it stands for.
+        std::fs::write(root.join(CONFIG_FILE), "verify_badges = false\n").unwrap();
+        let parsed = parse_with_repo_config(args, root, None).unwrap();
+        assert!(!parsed.signature_badges);
+    }
+
+    #[test]
+    fn test_parse_with_explicit_config() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join(HIDDEN_CONFIG_FILE), "dry_run = true\n").unwrap();
+        let config = root.join("shared.toml");
+        std::fs::write(&config, "non_interactive = true\n").unwrap();
+
+        let args = vec![OsString::from("changelogger")];
+        let parsed = parse_with_repo_config(args.clone(), root, Some(&config)).unwrap();
+        assert!(parsed.non_interactive);
+        assert!(!parsed.dry_run);
+
+        assert!(parse_with_repo_config(args, root, Some(&root.join("missing.toml"))).is_err());
+    }
+
+    #[test]
+    fn test_keyword_set_prefix_overrides() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(HIDDEN_CONFIG_FILE),
+            "[prefixes]\ndeps = \"patch\"\nsecurity = \"minor\"\nfix = \"ignore\"\n",
+        )
+        .unwrap();
+        let args = vec![OsString::from("changelogger")];
+        let parsed = parse_with_repo_config(args, dir.path(), None).unwrap();
+
+        let keywords = keyword_set(&parsed).unwrap();
+        assert_eq!(keywords.lookup("deps"), Some(CommitCategory::Patch));
+        assert_eq!(keywords.lookup("security"), Some(CommitCategory::Minor));
+        assert_eq!(keywords.lookup("fix"), Some(CommitCategory::Ignore));
+        // Built-in prefixes not in the table still apply.
+        assert_eq!(keywords.lookup("feat"), Some(CommitCategory::Minor));
+
+        let defaults = keyword_set(&cli(&[])).unwrap();
+        assert_eq!(defaults.lookup("deps"), None);
+        assert_eq!(defaults.lookup("fix"), Some(CommitCategory::Patch));
+    }
+
+    #[test]
+    fn test_parse_key_category() {
+        assert_eq!(
+            parse_key_category("Perf=patch"),
+            Ok(("perf".to_string(), CommitCategory::Patch))
+        );
+        assert_eq!(
+            parse_key_category(" Deps = ignore"),
+            Ok(("deps".to_string(), CommitCategory::Ignore))
+        );
+        assert!(parse_key_category("perf=huge").is_err());
+        assert!(parse_key_category("=patch").is_err());
+        assert!(parse_key_category("perf").is_err());
+    }
+
+    #[test]
+    fn test_resolve_output() {
+        let (dir, repo) = init_repo();
+        assert_eq!(resolve_output(&repo, None), "CHANGELOG.md");
+
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package.metadata.changelogger]\noutput = \"HISTORY.md\"\n",
+        )
+        .unwrap();
+        let configured = resolve_output(&repo, None);
+        assert!(configured.ends_with("HISTORY.md"));
+        assert!(std::path::Path::new(&configured).starts_with(repo.workdir().unwrap()));
+
+        assert_eq!(resolve_output(&repo, Some("NEWS.md")), "NEWS.md");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_hook() {
+        assert!(run_hook("--pre-run-hook", "true").is_ok());
+
+        let err = run_hook("--pre-run-hook", "echo token expired >&2; exit 3").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("token expired"), "{message}");
+    }
+
+    #[test]
+    fn test_internal_commits_go_to_internal_changelog() {
+        let dir = TempDir::new().unwrap();
+        let main_path = dir.path().join("CHANGELOG.md");
+        let internal_path = dir.path().join("INTERNAL.md");
+        let mut internal = commit_info("retry the deploy script");
+        internal.internal = true;
+        let mut grouped = HashMap::new();
+        grouped.insert(
+            CommitCategory::Patch,
+            vec![commit_info("fix the parser"), internal],
+        );
+
+        let (public, internal) = split_internal(grouped);
+        let version = Version::new(1, 0, 1);
+        let last = Version::new(1, 0, 0);
+        let today = Local::now().date_naive();
+        let opts = RenderOptions::default();
+        let release = build_release_section(&version, &last, today, None, &internal, &opts);
+        let output = main_path.to_str().unwrap();
+        let internal_output = internal_path.to_str().unwrap();
+
+        // Printing the changelog leaves the internal one alone too.
+        for args in [
+            &["--output", "-"][..],
+            &["--output-git-notes"],
+            &["--dry-run"],
+        ] {
+            let cli = cli(&[args, &["--internal-output", internal_output]].concat());
+            let output = if args[0] == "--output" { "-" } else { output };
+            write_internal_changelog(&cli, output, &internal_path, &release, &opts).unwrap();
+            assert!(!internal_path.exists(), "{args:?}");
+        }
+
+        let cli = cli(&["--internal-output", internal_output]);
+        write_internal_changelog(&cli, output, &internal_path, &release, &opts).unwrap();
+        let internal = std::fs::read_to_string(&internal_path).unwrap();
+        assert!(internal.contains("retry the deploy script"));
+        assert!(!internal.contains("fix the parser"));
+        assert!(public[&CommitCategory::Patch]
+            .iter()
+            .all(|commit| commit.summary == "fix the parser"));
+    }
+
+    #[test]
+    fn test_parse_category_label() {
+        assert_eq!(
+            parse_category_label("major=💥:Breaking").unwrap(),
+            (
+                CommitCategory::Major,
+                CategoryLabel {
+                    emoji: Some("💥".to_string()),
+                    label: "Breaking".to_string(),
+                }
+            )
+        );
+        assert_eq!(
+            parse_category_label("Minor=Features").unwrap().1,
+            CategoryLabel {
+                emoji: None,
+                label: "Features".to_string(),
+            }
+        );
+        assert!(parse_category_label("huge=Big").is_err());
+        assert!(parse_category_label("patch=🐛:").is_err());
+        assert!(parse_category_label("patch").is_err());
+    }
+
+    #[test]
+    fn test_prompt_text() {
+        let mut commit = commit_info("Rework the cache");
+        assert_eq!(prompt_text(&commit), "Commit abc1234 Rework the cache");
+
+        commit.body = "First line.\n\nSecond line.\nThird.\nFourth.\n".to_string();
+        assert_eq!(
+            prompt_text(&commit),
+            "Commit abc1234 Rework the cache\n    First line.\n    Second line.\n    Third."
+        );
+    }
+
+    #[test]
+    fn test_dump_commit() {
+        let dump = dump_commit(
+            &commit_info("feat(api)!: thing (#9)"),
+            &ClassifyOptions::default(),
+            &IssueSyntax::default(),
+        );
+        let fields: Vec<&str> = dump.lines().map(str::trim).collect();
+        assert!(fields.contains(&"type: feat"));
+        assert!(fields.contains(&"scope: api"));
+        assert!(fields.contains(&"breaking: true"));
+        assert!(fields.contains(&"title: thing"));
+        assert!(fields.contains(&"refs: #9"));
+    }
+
+    #[test]
+    fn test_dump_commit_agrees_with_category() {
+        let dump = |summary: &str| {
+            dump_commit(
+                &commit_info(summary),
+                &ClassifyOptions::default(),
+                &IssueSyntax::default(),
+            )
+        };
+        let fields = dump("Big change!: x");
+        let fields: Vec<&str> = fields.lines().map(str::trim).collect();
+        assert!(fields.contains(&"type: -"));
+        assert!(fields.contains(&"breaking: false"));
+        assert!(fields.contains(&"category: -"));
+
+        let fields = dump("fix!: x");
+        let fields: Vec<&str> = fields.lines().map(str::trim).collect();
+        assert!(fields.contains(&"breaking: true"));
+        assert!(fields.contains(&"category: major"));
+    }
+
+    #[test]
+    fn test_bump_exit_code() {
+        let mut grouped = HashMap::new();
+        assert_eq!(bump_exit_code(&grouped), 0);
+        grouped.insert(
+            CommitCategory::Minor,
+            vec![commit_info("feat: a"), commit_info("feat: b")],
+        );
+        assert_eq!(bump_exit_code(&grouped), 11);
+        grouped.insert(CommitCategory::Patch, vec![commit_info("fix: c")]);
+        assert_eq!(bump_exit_code(&grouped), 11);
+        grouped.insert(CommitCategory::Major, vec![commit_info("feat!: d")]);
+        assert_eq!(bump_exit_code(&grouped), 12);
+    }
+
+    #[test]
+    fn test_exit_bump_prints_no_listing() {
+        for listing in ["--list-prompts", "--dump-ast"] {
+            assert!(parse_cli(["changelogger", "--exit-bump", listing]).is_err());
+        }
+        assert!(parse_cli([
+            "changelogger",
+            "--exit-bump",
+            "--dry-run",
+            "--explain-ignored"
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_version_output_format() {
+        let version = Version::new(1, 2, 3);
+        assert_eq!(VersionOutputFormat::Semver.format(&version), "1.2.3");
+        assert_eq!(VersionOutputFormat::SemverShort.format(&version), "1.2");
+        assert_eq!(
+            VersionOutputFormat::PackageVersion.format(&version),
+            "v1.2.3"
+        );
+        assert_eq!(
+            cli(&["--version-output-format", "semver-short"]).version_output_format,
+            Some(VersionOutputFormat::SemverShort)
+        );
+    }
+
+    #[test]
+    fn test_explain_ignored() {
+        let mut commit = commit_info("docs: update readme");
+        assert_eq!(
+            auto_classify(&mut commit, &ClassifyOptions::default()),
+            Some(CommitCategory::Ignore)
+        );
+        assert!(explain_ignored(&commit).ends_with("(ignored: docs prefix)"));
+        assert_eq!(
+            ignored_by(CommitCategory::Ignore, "prompt").as_deref(),
+            Some("ignored by prompt")
+        );
+        assert_eq!(ignored_by(CommitCategory::Patch, "prompt"), None);
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(parse_threshold("0.85"), Ok(0.85));
+        assert!(parse_threshold("1.5").is_err());
+        assert!(parse_threshold("high").is_err());
+    }
+
+    #[test]
+    fn test_apply_version_suffix() {
+        let version = Version::new(1, 2, 3);
+        let apply = |args: &[&str]| {
+            apply_version_suffix(version.clone(), &cli(args), "0123abc")
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(apply(&[]), "1.2.3");
+        assert_eq!(
+            apply(&["--version-suffix-strategy", "build-metadata"]),
+            "1.2.3+0123abc"
+        );
+        assert_eq!(
+            apply(&["--version-suffix-strategy", "prerelease"]),
+            "1.2.3-g0123abc"
+        );
+        assert_eq!(
+            apply(&[
+                "--version-suffix-strategy",
+                "prerelease",
+                "--build-metadata",
+                "ci.42"
+            ]),
+            "1.2.3+ci.42"
+        );
+        assert!(apply_version_suffix(
+            version.clone(),
+            &cli(&["--build-metadata", "a b"]),
+            "0123abc"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dirty_changelog_blocks_write() {
+        let (_dir, repo) = init_repo();
+        let path = git::tests::commit_file(&repo, "CHANGELOG.md", "## Version 1.0.0\n");
+        let output = path.to_str().unwrap();
+
+        assert!(check_output_clean(&repo, &cli(&[]), output).is_ok());
+        std::fs::write(&path, "## Version 1.0.0\n\nHand-written notes.\n").unwrap();
+        let err = check_output_clean(&repo, &cli(&[]), output).unwrap_err();
+        assert!(err.to_string().contains("has uncommitted changes"));
+        assert!(check_output_clean(&repo, &cli(&["--force"]), output).is_ok());
+        assert!(check_output_clean(&repo, &cli(&["--dry-run"]), output).is_ok());
+        // Rerunning over the section of a previous run may drop edits made to it since.
+        for rerun in ["--prepend-unreleased", "--squash-multiple-bumps"] {
+            assert!(check_output_clean(&repo, &cli(&[rerun]), output).is_err());
+            assert!(check_output_clean(&repo, &cli(&[rerun, "--force"]), output).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_check_json_output() {
+        let dir = TempDir::new().unwrap();
+        let changelog = dir.path().join("CHANGELOG.md");
+        std::fs::write(&changelog, "## Version 1.0.0\n").unwrap();
+        let changelog = changelog.to_str().unwrap();
+        let release = dir.path().join("release.json");
+        let release = release.to_str().unwrap();
+
+        // The default output is the changelog.
+        let err = check_json_output(&cli(&["--format", "json"]), "CHANGELOG.md").unwrap_err();
+        assert!(err.to_string().contains("--output <PATH>"));
+        let err = check_json_output(
+            &cli(&["--format", "json", "--output", changelog]),
+            changelog,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("is not a JSON file"));
+
+        assert!(
+            check_json_output(&cli(&["--format", "json", "--output", release]), release).is_ok()
+        );
+        std::fs::write(release, "{\n  \"version\": \"1.0.0\"\n}\n").unwrap();
+        assert!(
+            check_json_output(&cli(&["--format", "json", "--output", release]), release).is_ok()
+        );
+        assert!(check_json_output(&cli(&["--format", "json", "--output", "-"]), "-").is_ok());
+        assert!(
+            check_json_output(&cli(&["--format", "json", "--dry-run"]), "CHANGELOG.md").is_ok()
+        );
+        assert!(check_json_output(&cli(&[]), "CHANGELOG.md").is_ok());
+    }
+
+    #[test]
+    fn test_writes_file() {
+        assert!(writes_file(&cli(&[]), "CHANGELOG.md"));
+        assert!(!writes_file(&cli(&["--dry-run"]), "CHANGELOG.md"));
+        assert!(!writes_file(&cli(&["--output", "-"]), "-"));
+        assert!(!writes_file(&cli(&["--output-fd", "3"]), "CHANGELOG.md"));
+        assert!(!writes_file(&cli(&["--output-git-notes"]), "CHANGELOG.md"));
+        assert!(!writes_file(&cli(&["--exit-bump"]), "CHANGELOG.md"));
+    }
+
+    #[test]
+    fn test_last_version_env_drives_bump() {
+        let (_dir, repo) = init_repo();
+        commit(&repo, "feat: first");
+        let args = cli(&["--last-version-env", "PREVIOUS_VERSION"]);
+        let vars = |name: &str| (name == "PREVIOUS_VERSION").then(|| "v1.2.3".to_string());
+        let (version, since) = starting_point_from_vars(&repo, &args, vars).unwrap();
+        assert_eq!(version, Version::new(1, 2, 3));
+        assert_eq!(since, None);
+
+        let mut grouped = HashMap::new();
+        grouped.insert(CommitCategory::Minor, vec![commit_info("feat: thing")]);
+        assert_eq!(
+            next_version(&version, &grouped, None).unwrap(),
+            Version::new(1, 3, 0)
+        );
+        let err = next_version(&version, &grouped, Some("1.2.0")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "New version 1.2.0 must be greater than previous version 1.2.3"
+        );
+
+        let args = cli(&["--last-version-env", "UNSET_VERSION"]);
+        let err = starting_point_from_vars(&repo, &args, vars).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Environment variable UNSET_VERSION is not set"
+        );
+    }
+
+    #[test]
+    fn test_next_version_bumps() {
+        let last = Version::new(1, 2, 3);
+        let unstable = Version::new(0, 2, 3);
+        let mut grouped = HashMap::new();
+        grouped.insert(CommitCategory::Patch, vec![commit_info("fix: thing")]);
+        assert_eq!(
+            next_version(&last, &grouped, None).unwrap(),
+            Version::new(1, 2, 4)
+        );
+        grouped.insert(CommitCategory::Minor, vec![commit_info("feat: thing")]);
+        assert_eq!(
+            next_version(&last, &grouped, None).unwrap(),
+            Version::new(1, 3, 0)
+        );
+        assert_eq!(
+            next_version(&unstable, &grouped, None).unwrap(),
+            Version::new(0, 2, 4)
+        );
+        grouped.insert(CommitCategory::Major, vec![commit_info("feat!: thing")]);
+        assert_eq!(
+            next_version(&last, &grouped, None).unwrap(),
+            Version::new(2, 0, 0)
+        );
+        assert_eq!(
+            next_version(&unstable, &grouped, None).unwrap(),
+            Version::new(0, 3, 0)
+        );
+    }
+
+    #[test]
+    fn test_require_remote() {
+        let (_dir, repo) = init_repo();
+        commit(&repo, "feat: first");
+        repo.remote("origin", "/srv/git/repo.git").unwrap();
+
+        assert!(
+            resolve_remote(&repo, &cli(&[]), &mut RemoteInfoCache::default())
+                .unwrap()
+                .is_none()
+        );
+        let err = resolve_remote(
+            &repo,
+            &cli(&["--require-remote"]),
+            &mut RemoteInfoCache::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "--require-remote is set");
+        assert!(format!("{err:#}").contains("Could not parse the origin remote URL"));
+
+        repo.remote_set_url("origin", "https://github.com/owner/repo.git")
+            .unwrap();
+        let remote = resolve_remote(
+            &repo,
+            &cli(&["--require-remote"]),
+            &mut RemoteInfoCache::default(),
+        )
+        .unwrap();
+        assert_eq!(remote.unwrap().base_url, "https://github.com/owner/repo/");
+    }
+
+    #[test]
+    fn test_resolve_starting_point_full_history() {
+        let (_dir, repo) = init_repo();
+        commit(&repo, "feat: first");
+
+        let (version, since) = resolve_starting_point(&repo, &cli(&[])).unwrap();
+        assert_eq!(version, Version::new(0, 0, 0));
+        assert_eq!(since, None);
+    }
+
+    #[test]
+    fn test_resolve_starting_point_base_ref_bounds_walk() {
+        let (_dir, repo) = init_repo();
+        let base = commit(&repo, "feat: already on main");
+        repo.reference("refs/remotes/origin/main", base, false, "test")
+            .unwrap();
+        commit(&repo, "fix: new work");
+
+        let (version, since) =
+            resolve_starting_point(&repo, &cli(&["--base-ref", "origin/main"])).unwrap();
+        assert_eq!(version, Version::new(0, 0, 0));
+        assert_eq!(since, Some(base));
+
+        let commits = commits_since(&repo, None, since, &WalkOptions::default()).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].summary, "fix: new work");
+    }
+
+    #[test]
+    fn test_resolve_starting_point_from_tag_errors() {
+        let (_dir, repo) = init_repo();
+        commit(&repo, "feat: first");
+        let blob = repo.blob(b"not a commit").unwrap();
+        repo.tag_lightweight("v1.0.0", &repo.find_object(blob, None).unwrap(), false)
+            .unwrap();
+
+        let err = resolve_starting_point(&repo, &cli(&["--from-tag", "v1.0.0"])).unwrap_err();
+        assert_eq!(err.to_string(), "Tag v1.0.0 points to a blob, not a commit");
+
+        let err = resolve_starting_point(&repo, &cli(&["--from-tag", "v9.9.9"])).unwrap_err();
+        assert_eq!(err.to_string(), "Could not find tag v9.9.9");
+
+        let err = resolve_starting_point(&repo, &cli(&["--from-tag", "HEAD"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Tag HEAD does not look like a semver version"
+        );
+    }
+
+    #[test]
+    fn test_resolve_starting_point_tag_wins_over_base_ref() {
+        let (_dir, repo) = init_repo();
+        let base = commit(&repo, "feat: first");
+        let tagged = commit(&repo, "feat: second");
+        repo.reference("refs/heads/base", base, false, "test")
+            .unwrap();
+        repo.tag_lightweight("v1.0.0", &repo.find_object(tagged, None).unwrap(), false)
+            .unwrap();
+
+        let (version, since) =
+            resolve_starting_point(&repo, &cli(&["--base-ref", "base"])).unwrap();
+        assert_eq!(version, Version::new(1, 0, 0));
+        assert_eq!(since, Some(tagged));
+    }
+
+    #[test]
+    fn test_release_tip() {
+        let (_dir, repo) = init_repo();
+        let first = commit(&repo, "feat: first");
+        let second = commit(&repo, "feat: second");
+        assert_eq!(release_tip(&repo, None).unwrap(), second);
+        assert_eq!(release_tip(&repo, Some(first)).unwrap(), first);
+    }
+
+    #[test]
+    fn test_warn_unrelated_start() {
+        let (_dir, repo) = init_repo();
+        let first = commit(&repo, "feat: first");
+        let second = commit(&repo, "feat: second");
+        repo.branch("old", &repo.find_commit(first).unwrap(), false)
+            .unwrap();
+        let main_branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        repo.set_head("refs/heads/old").unwrap();
+
+        // HEAD is behind the starting commit, the released tip is not.
+        assert!(warn_unrelated_start(&repo, &cli(&[]), None, second).unwrap());
+        assert!(!warn_unrelated_start(&repo, &cli(&[]), Some(second), second).unwrap());
+        assert!(!warn_unrelated_start(&repo, &cli(&[]), Some(second), first).unwrap());
+        let filtered = cli(&["--branch-filter", &main_branch]);
+        assert!(!warn_unrelated_start(&repo, &filtered, None, second).unwrap());
+        let filtered = cli(&["--branch-filter", "old"]);
+        assert!(warn_unrelated_start(&repo, &filtered, Some(second), second).unwrap());
+    }
+
+    #[test]
+    fn test_since_last_stable() {
+        let (_dir, repo) = init_repo();
+        let stable = commit(&repo, "feat: first");
+        repo.tag_lightweight("v1.0.0", &repo.find_object(stable, None).unwrap(), false)
+            .unwrap();
+        commit(&repo, "feat: add parser");
+        commit(&repo, "fixup! feat: add parser");
+        let rc = commit(&repo, "chore: release");
+        repo.tag_lightweight("v1.1.0-rc.1", &repo.find_object(rc, None).unwrap(), false)
+            .unwrap();
+        let fix = commit(&repo, "something vague");
+
+        let cli = cli(&["--diff-stats"]);
+        let classify_opts = ClassifyOptions {
+            keywords: keyword_set(&cli).unwrap(),
+            ..Default::default()
+        };
+        let decisions = HashMap::from([(fix, CommitCategory::Patch)]);
+        let last_version = Version::parse("1.1.0-rc.1").unwrap();
+        let view = since_last_stable(&repo, &cli, None, &last_version, &decisions, &classify_opts)
+            .unwrap()
+            .unwrap();
+        assert_eq!(view.tag, "v1.0.0");
+        assert_eq!(view.version, Version::new(1, 0, 0));
+
+        // The fixup is folded like in the release, and commits get their diff stats.
+        let features = &view.grouped[&CommitCategory::Minor];
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].summary, "add parser");
+        assert!(features[0].diff_stats.is_some());
+        assert_eq!(view.grouped[&CommitCategory::Patch][0].oid, fix);
+
+        let last_version = Version::new(1, 0, 0);
+        let view = since_last_stable(&repo, &cli, None, &last_version, &decisions, &classify_opts);
+        assert!(view.unwrap().is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! Wording of the generated release notes.
+//!
+//! Section headings and generated phrases are looked up in a [`Messages`] table, selected
+//! by [`Lang`], so that changelogs can be written in languages other than English.
+
+use std::fmt;
+
+use clap::ValueEnum;
+
+/// Language of the generated headings and phrases.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// French
+    Fr,
+}
+
+impl Lang {
+    /// Returns the messages table of the language.
+    pub fn messages(self) -> &'static Messages {
+        match self {
+            Lang::En => &ENGLISH,
+            Lang::Fr => &FRENCH,
+        }
+    }
+}
+
+/// A custom name for a commit category, with an optional emoji, e.g. "💥 Breaking".
+///
+/// Replaces the category's heading and its count in the summary line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryLabel {
+    /// Emoji shown before the label, if any.
+    pub emoji: Option<String>,
+    /// The label itself.
+    pub label: String,
+}
+
+impl fmt::Display for CategoryLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.emoji {
+            Some(emoji) => write!(f, "{emoji} {}", self.label),
+            None => f.write_str(&self.label),
+        }
+    }
+}
+
+/// The headings and phrases of a release section in one language.
+///
+/// Phrases with a `{n}` or `{list}` placeholder have it replaced when rendered;
+/// `(singular, plural)` pairs are chosen by count.
+#[derive(Debug)]
+pub struct Messages {
+    /// Release header prefix, as in "Version 1.2.3".
+    pub version: &'static str,
+    /// Heading of breaking changes.
+    pub breaking_changes: &'static str,
+    /// Heading of new features.
+    pub new_features: &'static str,
+    /// Heading of bug fixes.
+    pub bug_fixes: &'static str,
+    /// Heading of the dedicated security section.
+    pub security_fixes: &'static str,
+    /// Heading of the reverted changes.
+    pub reverts: &'static str,
+    /// Heading of the known issues.
+    pub known_issues: &'static str,
+    /// Heading of the contributors.
+    pub contributors: &'static str,
+    /// Heading of the closed issues index.
+    pub closed_issues: &'static str,
+    /// Text of the link to the migration guide of a breaking change.
+    pub migration_guide: &'static str,
+    /// Text of the link comparing the release with the previous one.
+    pub full_changes: &'static str,
+    /// Placeholder of an empty section.
+    pub none: &'static str,
+    /// Line replacing the bullets beyond the per-section limit.
+    pub and_more: &'static str,
+    /// Summary sentence under the header.
+    pub release_includes: &'static str,
+    /// Separator between the two items of a list.
+    pub pair_separator: &'static str,
+    /// Separator before the last item of a longer list.
+    pub last_separator: &'static str,
+    /// Breaking change count in the summary sentence.
+    pub breaking_change: (&'static str, &'static str),
+    /// New feature count in the summary sentence.
+    pub new_feature: (&'static str, &'static str),
+    /// Fix count in the summary sentence.
+    pub fix: (&'static str, &'static str),
+    /// Contributor count.
+    pub contributor: (&'static str, &'static str),
+    /// Commit count in the version header.
+    pub commit: (&'static str, &'static str),
+}
+
+impl Messages {
+    /// Formats a count with the singular or plural noun, e.g. "1 fix" or "3 fixes".
+    pub fn count(&self, n: usize, (singular, plural): (&str, &str)) -> String {
+        if n == 1 {
+            format!("1 {singular}")
+        } else {
+            format!("{n} {plural}")
+        }
+    }
+
+    /// Joins items into a sentence list, e.g. "a, b, and c".
+    pub fn join(&self, items: &[String]) -> String {
+        match items {
+            [] => String::new(),
+            [one] => one.clone(),
+            [first, second] => format!("{first}{}{second}", self.pair_separator),
+            [rest @ .., last] => format!("{}{}{last}", rest.join(", "), self.last_separator),
+        }
+    }
+}
+
+/// English messages.
+pub static ENGLISH: Messages = Messages {
+    version: "Version",
+    breaking_changes: "Breaking changes",
+    new_features: "New features",
+    bug_fixes: "Bug fixes",
+    security_fixes: "Security fixes",
+    reverts: "Reverts",
+    known_issues: "Known issues",
+    contributors: "Contributors",
+    closed_issues: "Closed issues",
+    migration_guide: "Migration guide",
+    full_changes: "...full changes",
+    none: "None.",
+    and_more: "...and {n} more",
+    release_includes: "This release includes {list}.",
+    pair_separator: " and ",
+    last_separator: ", and ",
+    breaking_change: ("breaking change", "breaking changes"),
+    new_feature: ("new feature", "new features"),
+    fix: ("fix", "fixes"),
+    contributor: ("contributor", "contributors"),
+    commit: ("commit", "commits"),
+};
+
+/// French messages.
+pub static FRENCH: Messages = Messages {
+    version: "Version",
+    breaking_changes: "Changements incompatibles",
+    new_features: "Nouvelles fonctionnalités",
+    bug_fixes: "Corrections de bugs",
+    security_fixes: "Correctifs de sécurité",
+    reverts: "Annulations",
+    known_issues: "Problèmes connus",
+    contributors: "Contributeurs",
+    closed_issues: "Tickets fermés",
+    migration_guide: "Guide de migration",
+    full_changes: "...tous les changements",
+    none: "Aucun.",
+    and_more: "...et {n} de plus",
+    release_includes: "Cette version comprend {list}.",
+    pair_separator: " et ",
+    last_separator: " et ",
+    breaking_change: ("changement incompatible", "changements incompatibles"),
+    new_feature: ("nouvelle fonctionnalité", "nouvelles fonctionnalités"),
+    fix: ("correction", "corrections"),
+    contributor: ("contributeur", "contributeurs"),
+    commit: ("commit", "commits"),
+};